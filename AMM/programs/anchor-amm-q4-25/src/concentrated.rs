@@ -0,0 +1,296 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::AmmError, state::MAX_BPS};
+
+/// Bounds of the tick index, the price of tick `i` being `1.0001^i`. They keep square root
+/// prices between 2^-32 and 2^32, so Q64.64 products stay within 256 bits
+pub const MIN_TICK: i32 = -443_636;
+pub const MAX_TICK: i32 = 443_636;
+/// Largest distance between two usable ticks of a pool
+pub const MAX_TICK_SPACING: u16 = 1_000;
+
+/// 1.0 in Q64.64 fixed point, the format of square root prices and fee growth
+pub const Q64: u128 = 1 << 64;
+
+// sqrt(1.0001)^(2^i) in Q64.64, for the bits of a positive tick
+const SQRT_PRICE_POWERS: [u128; 19] = [
+    18447666387855959850,
+    18448588748116922571,
+    18450433606991734263,
+    18454123878217468680,
+    18461506635090006701,
+    18476281010653910144,
+    18505865242158250041,
+    18565175891880433522,
+    18684368066214940582,
+    18925053041275764671,
+    19415764168677886926,
+    20435687552633177494,
+    22639080592224303007,
+    27784196929998399742,
+    41848122137994986128,
+    94936283578220370716,
+    488590176327622479860,
+    12941056668319229769860,
+    9078618265828848800676189,
+];
+
+// sqrt(1.0001)^(-2^i) in Q64.64, for the bits of a negative tick
+const INVERSE_SQRT_PRICE_POWERS: [u128; 19] = [
+    18445821805675392311,
+    18444899583751176498,
+    18443055278223354162,
+    18439367220385604838,
+    18431993317065449817,
+    18417254355718160513,
+    18387811781193591352,
+    18329067761203520168,
+    18212142134806087854,
+    17980523815641551639,
+    17526086738831147013,
+    16651378430235024244,
+    15030750278693429944,
+    12247334978882834399,
+    8131365268884726200,
+    3584323654723342297,
+    696457651847595233,
+    26294789957452057,
+    37481735321082,
+];
+
+/// Square root of the price at `tick`, in Q64.64, rounded down
+pub fn sqrt_price_at_tick(tick: i32) -> Result<u128> {
+    require!(
+        (MIN_TICK..=MAX_TICK).contains(&tick),
+        AmmError::InvalidTickRange
+    );
+
+    let powers = match tick >= 0 {
+        true => &SQRT_PRICE_POWERS,
+        false => &INVERSE_SQRT_PRICE_POWERS,
+    };
+    let bits = tick.unsigned_abs();
+
+    let mut sqrt_price = Q64;
+    for (i, power) in powers.iter().enumerate() {
+        if bits & (1 << i) != 0 {
+            sqrt_price = mul_div(sqrt_price, *power, Q64, false)?;
+        }
+    }
+
+    Ok(sqrt_price)
+}
+
+/// Bounds of the square root price, those of MIN_TICK and MAX_TICK
+pub fn sqrt_price_bounds() -> Result<(u128, u128)> {
+    Ok((sqrt_price_at_tick(MIN_TICK)?, sqrt_price_at_tick(MAX_TICK)?))
+}
+
+/// Largest tick whose square root price is at most `sqrt_price`
+pub fn tick_at_sqrt_price(sqrt_price: u128) -> Result<i32> {
+    let (min, max) = sqrt_price_bounds()?;
+    require!(
+        (min..=max).contains(&sqrt_price),
+        AmmError::InvalidTickRange
+    );
+
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        match sqrt_price_at_tick(mid)? <= sqrt_price {
+            true => low = mid,
+            false => high = mid - 1,
+        }
+    }
+
+    Ok(low)
+}
+
+/// Amount of X backing `liquidity` between two square root prices:
+/// `liquidity * (1 / sqrt_a - 1 / sqrt_b)`
+pub fn amount_x_delta(sqrt_a: u128, sqrt_b: u128, liquidity: u64, round_up: bool) -> Result<u64> {
+    let (sqrt_a, sqrt_b) = (sqrt_a.min(sqrt_b), sqrt_a.max(sqrt_b));
+    require!(sqrt_a != 0, AmmError::ZeroBalance);
+
+    let amount = mul_div((liquidity as u128) << 64, sqrt_b - sqrt_a, sqrt_b, round_up)?;
+    let amount = match round_up {
+        true => amount.div_ceil(sqrt_a),
+        false => amount / sqrt_a,
+    };
+
+    u64::try_from(amount).map_err(|_| AmmError::Overflow.into())
+}
+
+/// Amount of Y backing `liquidity` between two square root prices:
+/// `liquidity * (sqrt_b - sqrt_a)`
+pub fn amount_y_delta(sqrt_a: u128, sqrt_b: u128, liquidity: u64, round_up: bool) -> Result<u64> {
+    let (sqrt_a, sqrt_b) = (sqrt_a.min(sqrt_b), sqrt_a.max(sqrt_b));
+
+    let amount = mul_div(liquidity as u128, sqrt_b - sqrt_a, Q64, round_up)?;
+
+    u64::try_from(amount).map_err(|_| AmmError::Overflow.into())
+}
+
+/// Square root price after `amount` of X (or Y if `!is_x`) is added to `liquidity`.
+/// Rounds against the swapper, up when X comes in and down when Y does
+pub fn next_sqrt_price_from_input(
+    sqrt_price: u128,
+    liquidity: u64,
+    amount: u64,
+    is_x: bool,
+) -> Result<u128> {
+    require!(liquidity != 0, AmmError::NoLiquidityInPool);
+    if amount == 0 {
+        return Ok(sqrt_price);
+    }
+
+    match is_x {
+        // liquidity / (liquidity / sqrt_price + amount)
+        true => {
+            let liquidity = (liquidity as u128) << 64;
+            let denominator = (liquidity / sqrt_price)
+                .checked_add(amount as u128)
+                .ok_or(AmmError::Overflow)?;
+
+            Ok(liquidity.div_ceil(denominator))
+        }
+        // sqrt_price + amount / liquidity
+        false => sqrt_price
+            .checked_add(((amount as u128) << 64) / liquidity as u128)
+            .ok_or(AmmError::Overflow.into()),
+    }
+}
+
+/// One step of a swap, within a range of constant liquidity
+pub struct SwapStep {
+    pub sqrt_price: u128, // Square root price the step ends at
+    pub amount_in: u64,   // Input priced by the step, fee excluded
+    pub amount_out: u64,  // Output of the step
+    pub fee: u64,         // Fee charged on the step's input
+}
+
+/// Swaps up to `amount_remaining` of input, fee included, towards `sqrt_price_target`.
+/// X comes in when the target is below the current price, Y when it's above
+pub fn swap_step(
+    sqrt_price: u128,
+    sqrt_price_target: u128,
+    liquidity: u64,
+    amount_remaining: u64,
+    fee_bps: u16,
+) -> Result<SwapStep> {
+    require!(fee_bps < MAX_BPS, AmmError::InvalidFee);
+
+    let is_x = sqrt_price_target <= sqrt_price;
+    let amount_in_for = |to: u128| match is_x {
+        true => amount_x_delta(to, sqrt_price, liquidity, true),
+        false => amount_y_delta(sqrt_price, to, liquidity, true),
+    };
+
+    let net = (amount_remaining as u128 * (MAX_BPS - fee_bps) as u128 / MAX_BPS as u128) as u64;
+    let to_target = amount_in_for(sqrt_price_target)?;
+
+    let (next, amount_in) = match net >= to_target {
+        true => (sqrt_price_target, to_target),
+        false => {
+            let next = next_sqrt_price_from_input(sqrt_price, liquidity, net, is_x)?;
+            (next, amount_in_for(next)?.min(net))
+        }
+    };
+
+    let amount_out = match is_x {
+        true => amount_y_delta(next, sqrt_price, liquidity, false)?,
+        false => amount_x_delta(sqrt_price, next, liquidity, false)?,
+    };
+
+    // A step that stops short of the target takes the whole remaining input, the
+    // difference being the fee
+    let left = amount_remaining - amount_in;
+    let fee = match next == sqrt_price_target {
+        true => {
+            let fee = (amount_in as u128 * fee_bps as u128).div_ceil((MAX_BPS - fee_bps) as u128);
+            (fee as u64).min(left)
+        }
+        false => left,
+    };
+
+    Ok(SwapStep {
+        sqrt_price: next,
+        amount_in,
+        amount_out,
+        fee,
+    })
+}
+
+/// `liquidity` changed by a signed `delta`
+pub fn add_liquidity_delta(liquidity: u64, delta: i64) -> Result<u64> {
+    match delta >= 0 {
+        true => liquidity
+            .checked_add(delta as u64)
+            .ok_or(AmmError::Overflow),
+        false => liquidity
+            .checked_sub(delta.unsigned_abs())
+            .ok_or(AmmError::Underflow),
+    }
+    .map_err(Into::into)
+}
+
+/// Fees accrued by `liquidity` over a Q64.64 fee growth per unit of liquidity
+pub fn fees_for_growth(growth: u128, liquidity: u64) -> Result<u64> {
+    let fees = mul_div(growth, liquidity as u128, Q64, false)?;
+
+    u64::try_from(fees).map_err(|_| AmmError::Overflow.into())
+}
+
+/// `a * b / c` with a 256-bit intermediate product
+pub fn mul_div(a: u128, b: u128, c: u128, round_up: bool) -> Result<u128> {
+    require!(c != 0, AmmError::CurveError);
+
+    let (high, low) = full_mul(a, b);
+    // The quotient must fit in 128 bits
+    require!(high < c, AmmError::Overflow);
+
+    let (quotient, remainder) = match high {
+        0 => (low / c, low % c),
+        // Dividing by Q64 is a shift, cheaper than the long division
+        _ if c == Q64 => ((high << 64) | (low >> 64), low & (Q64 - 1)),
+        _ => {
+            // Long division of the low half, the remainder starting as the high half
+            let (mut quotient, mut remainder) = (0u128, high);
+            for i in (0..128).rev() {
+                let carry = remainder >> 127;
+                remainder = (remainder << 1) | ((low >> i) & 1);
+                quotient <<= 1;
+                if carry == 1 || remainder >= c {
+                    remainder = remainder.wrapping_sub(c);
+                    quotient |= 1;
+                }
+            }
+            (quotient, remainder)
+        }
+    };
+
+    match round_up && remainder != 0 {
+        true => quotient.checked_add(1).ok_or(AmmError::Overflow.into()),
+        false => Ok(quotient),
+    }
+}
+
+// 256-bit product of `a` and `b`, as its high and low 128 bits
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+
+    let (a_high, a_low) = (a >> 64, a & MASK);
+    let (b_high, b_low) = (b >> 64, b & MASK);
+
+    let low_low = a_low * b_low;
+    let low_high = a_low * b_high;
+    let high_low = a_high * b_low;
+    let high_high = a_high * b_high;
+
+    let middle = (low_low >> 64) + (low_high & MASK) + (high_low & MASK);
+
+    let low = (low_low & MASK) | (middle << 64);
+    let high = high_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+
+    (high, low)
+}
@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    concentrated::{self, Q64},
+    errors::AmmError,
+    state::MAX_BPS,
+};
+
+/// Amounts of a priced swap, `amount_in` includes `fee`
+pub struct SwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+}
+
+/// Net input needed to take exactly `amount_out` from a constant-product pool,
+/// rounded up so that `k` never decreases
+pub fn amount_in_for_exact_out(reserve_in: u64, reserve_out: u64, amount_out: u64) -> Result<u64> {
+    require!(reserve_in != 0, AmmError::ZeroBalance);
+    require!(amount_out < reserve_out, AmmError::InsufficientBalance);
+
+    // x * dy / (y - dy)
+    let amount_in = (reserve_in as u128)
+        .checked_mul(amount_out as u128)
+        .ok_or(AmmError::Overflow)?
+        .div_ceil((reserve_out - amount_out) as u128);
+
+    u64::try_from(amount_in).map_err(|_| AmmError::Overflow.into())
+}
+
+/// LP minted by the first deposit, the geometric mean of the reserves it creates
+pub fn initial_liquidity(reserve_x: u64, reserve_y: u64) -> Result<u64> {
+    let liquidity = isqrt((reserve_x as u128) * (reserve_y as u128));
+
+    u64::try_from(liquidity).map_err(|_| AmmError::Overflow.into())
+}
+
+/// Token amounts a deposit must add to mint `amount` out of `shares` LP, rounded up
+pub fn deposit_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    shares: u64,
+    amount: u64,
+) -> Result<(u64, u64)> {
+    require!(shares != 0, AmmError::NoLiquidityInPool);
+
+    Ok((
+        mul_div(reserve_x, amount, shares, true)?,
+        mul_div(reserve_y, amount, shares, true)?,
+    ))
+}
+
+/// Token amounts returned for burning `amount` out of `shares` LP, rounded down
+pub fn withdraw_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    shares: u64,
+    amount: u64,
+) -> Result<(u64, u64)> {
+    require!(shares != 0, AmmError::NoLiquidityInPool);
+    require!(amount <= shares, AmmError::InsufficientBalance);
+
+    Ok((
+        mul_div(reserve_x, amount, shares, false)?,
+        mul_div(reserve_y, amount, shares, false)?,
+    ))
+}
+
+/// Part of a single-sided deposit of `amount` to swap through a constant-product pool first,
+/// so that the rest and the swap output add liquidity in the ratio of the reserves.
+/// Solves `k s^2 + m R s - a R = 0`, where `R` is `reserve_in`, `m = 2 - f` and
+/// `k = (1 - f)(1 - f p)` for the swap fee `f` and the protocol's share `p` of it, which
+/// leaves the vault. Rounding makes it off by a unit or so, so callers check its neighbours
+pub fn zap_swap_amount(reserve_in: u64, amount: u64, fee: u16, protocol_fee: u16) -> Result<u64> {
+    require!(reserve_in != 0, AmmError::ZeroBalance);
+
+    let (bps, fee, protocol_fee) = (MAX_BPS as u128, fee as u128, protocol_fee as u128);
+    let (reserve_in, amount) = (reserve_in as u128, amount as u128);
+
+    // m and k scaled by 1e4 and 1e12
+    let m = 2 * bps - fee;
+    let k = (bps - fee) * (bps * bps - fee * protocol_fee);
+
+    // s = 2a / (m (1 + sqrt(1 + t))), with t = 4 k a / (m^2 R)
+    let t = concentrated::mul_div(4 * k * amount, Q64, bps * m * m * reserve_in, false)?;
+    let root = isqrt(Q64.checked_add(t).ok_or(AmmError::Overflow)?) << 32;
+    let swap = concentrated::mul_div(2 * bps * amount, Q64, m * (Q64 + root), false)?;
+
+    Ok(swap.min(amount) as u64)
+}
+
+/// `a * b / c` in u128, rounded in the requested direction
+pub fn mul_div(a: u64, b: u64, c: u64, round_up: bool) -> Result<u64> {
+    require!(c != 0, AmmError::ZeroBalance);
+
+    let product = (a as u128) * (b as u128);
+    let result = match round_up {
+        true => product.div_ceil(c as u128),
+        false => product / c as u128,
+    };
+
+    u64::try_from(result).map_err(|_| AmmError::Overflow.into())
+}
+
+// Integer square root (floor), Newton's method
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    let mut x = n;
+    let mut y = (x + n / x) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
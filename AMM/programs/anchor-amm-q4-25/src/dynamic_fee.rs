@@ -0,0 +1,63 @@
+use crate::{
+    concentrated::{self, Q64},
+    state::MAX_BPS,
+};
+
+/// Seconds for the volatility accumulator to decay by half
+pub const VOLATILITY_HALF_LIFE: i64 = 60;
+/// Basis points of accumulated price movement that add one basis point to the fee
+pub const VOLATILITY_PER_FEE_BPS: u64 = 10;
+
+/// Volatility accumulated `elapsed` seconds ago, halved every VOLATILITY_HALF_LIFE and
+/// interpolated linearly in between
+pub fn decay(volatility: u64, elapsed: i64) -> u64 {
+    let elapsed = elapsed.max(0);
+    let halvings = (elapsed / VOLATILITY_HALF_LIFE).min(63) as u32;
+    let volatility = volatility >> halvings;
+
+    // Half of what's left is lost linearly over the next half-life
+    let remainder = (elapsed % VOLATILITY_HALF_LIFE) as u128;
+    let lost = volatility as u128 * remainder / (2 * VOLATILITY_HALF_LIFE as u128);
+
+    volatility - lost as u64
+}
+
+/// Price movement of Y in X between two states of the reserves, in basis points
+pub fn price_move(before: (u64, u64), after: (u64, u64)) -> u64 {
+    let (x0, y0) = (before.0 as u128, before.1 as u128);
+    let (x1, y1) = (after.0 as u128, after.1 as u128);
+
+    // p1 / p0 - 1 = (y1 * x0 - y0 * x1) / (y0 * x1)
+    let denominator = y0 * x1;
+    if denominator == 0 {
+        return 0;
+    }
+    let difference = (y1 * x0).abs_diff(denominator);
+
+    difference
+        .checked_mul(MAX_BPS as u128)
+        .map_or(u64::MAX, |n| {
+            u64::try_from(n / denominator).unwrap_or(u64::MAX)
+        })
+}
+
+/// Price movement between two Q64.64 square root prices, in basis points
+pub fn sqrt_price_move(before: u128, after: u128) -> u64 {
+    if before == 0 {
+        return 0;
+    }
+
+    // p1 / p0 - 1 = (s1 / s0)^2 - 1
+    concentrated::mul_div(after, Q64, before, false)
+        .and_then(|ratio| concentrated::mul_div(ratio, ratio, Q64, false))
+        .ok()
+        .and_then(|ratio| ratio.abs_diff(Q64).checked_mul(MAX_BPS as u128))
+        .map_or(u64::MAX, |n| u64::try_from(n / Q64).unwrap_or(u64::MAX))
+}
+
+/// Fee in basis points for an accumulated `volatility`, between `min_fee` and `max_fee`
+pub fn fee(volatility: u64, min_fee: u16, max_fee: u16) -> u16 {
+    let fee = (min_fee as u64).saturating_add(volatility / VOLATILITY_PER_FEE_BPS);
+
+    fee.min(max_fee as u64) as u16
+}
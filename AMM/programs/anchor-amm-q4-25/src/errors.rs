@@ -39,6 +39,52 @@ pub enum AmmError {
     InsufficientBalance,
     #[msg("Zero balance.")]
     ZeroBalance,
+    #[msg("No pending authority to accept.")]
+    NoPendingAuthority,
+    #[msg("Invalid pause flags.")]
+    InvalidPauseFlags,
+    #[msg("Config account is already migrated.")]
+    AlreadyMigrated,
+    #[msg("Invalid swap route.")]
+    InvalidRoute,
+    #[msg("Not enough oracle history for the requested window.")]
+    OracleWindowTooLong,
+    #[msg("Invalid amplification coefficient.")]
+    InvalidAmp,
+    #[msg("Invalid amplification ramp.")]
+    InvalidRamp,
+    #[msg("Not supported by the pool's curve.")]
+    InvalidCurve,
+    #[msg("Invalid pool weights.")]
+    InvalidWeight,
+    #[msg("A flash loan is outstanding.")]
+    FlashLoanActive,
+    #[msg("Flash loan is not repaid in the same transaction.")]
+    FlashLoanNotRepaid,
+    #[msg("Invalid flash loan.")]
+    InvalidFlashLoan,
+    #[msg("A pool needs two different mints.")]
+    IdenticalMints,
+    #[msg("LP tokens are still outstanding.")]
+    LiquidityOutstanding,
+    #[msg("Invalid emission schedule.")]
+    InvalidEmission,
+    #[msg("Referral fee is above the pool's maximum.")]
+    InvalidReferralFee,
+    #[msg("Invalid tick range.")]
+    InvalidTickRange,
+    #[msg("Invalid or missing tick array.")]
+    InvalidTickArray,
+    #[msg("Position still holds liquidity or uncollected fees.")]
+    PositionNotEmpty,
+    #[msg("Invalid treasury.")]
+    InvalidTreasury,
+    #[msg("Invalid registry page.")]
+    InvalidRegistryPage,
+    #[msg("Not the factory admin.")]
+    InvalidAdmin,
+    #[msg("Referrer isn't registered with the pool, or is the swapper.")]
+    InvalidReferrer,
 }
 
 impl From<CurveError> for AmmError {
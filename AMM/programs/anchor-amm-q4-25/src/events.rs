@@ -0,0 +1,158 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Config, CurveType};
+
+/// A pool created by `initialize`
+#[event]
+pub struct PoolInitialized {
+    pub config: Pubkey,            // Pool created
+    pub initializer: Pubkey,       // Payer of the pool's accounts
+    pub mint_x: Pubkey,            // Token X
+    pub mint_y: Pubkey,            // Token Y
+    pub mint_lp: Pubkey,           // LP token of the pool
+    pub curve_type: CurveType,     // Invariant the pool prices swaps with
+    pub fee: u16,                  // Swap fee in basis points
+    pub protocol_fee: u16,         // Protocol share of the swap fee, in basis points
+    pub authority: Option<Pubkey>, // Update authority of the pool
+}
+
+/// A swap through a pool, one per hop of a routed swap
+#[event]
+pub struct Swapped {
+    pub config: Pubkey,    // Pool the swap went through
+    pub user: Pubkey,      // Signer of the swap
+    pub mint_in: Pubkey,   // Token paid into the pool
+    pub mint_out: Pubkey,  // Token paid out by the pool
+    pub amount_in: u64,    // Input sent to the pool, fee included
+    pub amount_out: u64,   // Output sent by the pool
+    pub fee: u64,          // Swap fee charged, in the input token
+    pub protocol_fee: u64, // Part of the fee set aside for the protocol
+    pub reserve_x: u64,    // LP-owned reserve of X after the swap
+    pub reserve_y: u64,    // LP-owned reserve of Y after the swap
+}
+
+/// Liquidity added by `deposit` or `deposit_single`
+#[event]
+pub struct LiquidityAdded {
+    pub config: Pubkey, // Pool deposited into
+    pub user: Pubkey,   // Depositor, who receives the LP tokens
+    pub amount_x: u64,  // Token X deposited
+    pub amount_y: u64,  // Token Y deposited
+    pub lp_minted: u64, // LP tokens minted to the user
+    pub lp_supply: u64, // LP supply after the deposit
+    pub reserve_x: u64, // LP-owned reserve of X after the deposit
+    pub reserve_y: u64, // LP-owned reserve of Y after the deposit
+}
+
+/// Liquidity removed by `withdraw` or `withdraw_single`
+#[event]
+pub struct LiquidityRemoved {
+    pub config: Pubkey, // Pool withdrawn from
+    pub user: Pubkey,   // Withdrawer, whose LP tokens are burned
+    pub amount_x: u64,  // Token X paid out
+    pub amount_y: u64,  // Token Y paid out
+    pub lp_burned: u64, // LP tokens burned from the user
+    pub lp_supply: u64, // LP supply after the withdrawal
+    pub reserve_x: u64, // LP-owned reserve of X after the withdrawal
+    pub reserve_y: u64, // LP-owned reserve of Y after the withdrawal
+}
+
+/// Share of a swap fee paid to the referrer that routed the swap
+#[event]
+pub struct ReferralPaid {
+    pub config: Pubkey,   // Pool the swap went through
+    pub referrer: Pubkey, // Token account the referral was paid to
+    pub mint: Pubkey,     // Token of the swap input, the referral is paid in
+    pub fee: u64,         // Swap fee charged
+    pub amount: u64,      // Part of the fee paid to the referrer
+}
+
+/// Referrer registered with a pool, or its share updated, by the pool's authority
+#[event]
+pub struct ReferrerSet {
+    pub config: Pubkey,    // Pool the referrer is registered with
+    pub wallet: Pubkey,    // Owner of the token accounts the referral is paid to
+    pub referral_fee: u16, // Share of the swap fee paid to the referrer, 0 when turned off
+}
+
+/// Settings of a pool after an admin instruction changed them
+#[event]
+pub struct ConfigUpdated {
+    pub config: Pubkey,                    // Pool updated
+    pub authority: Option<Pubkey>,         // Update authority, none once renounced
+    pub pending_authority: Option<Pubkey>, // Authority proposed by the current one
+    pub fee: u16,                          // Swap fee in basis points
+    pub protocol_fee: u16,                 // Protocol share of the swap fee, in basis points
+    pub dynamic_fee: bool,                 // Whether the swap fee follows the volatility
+    pub min_fee: u16,                      // Dynamic fee in basis points when the market is calm
+    pub max_fee: u16,                      // Upper bound of the dynamic fee, in basis points
+    pub max_referral_fee: u16,             // Largest share of the swap fee a referrer may take
+    pub initial_amp: u64,                  // StableSwap amplification at the start of the ramp
+    pub target_amp: u64,                   // StableSwap amplification at the end of the ramp
+    pub ramp_stop_ts: i64,                 // Unix timestamp the amplification ramp ends at
+    pub paused: u8,                        // Bitmask of paused operations (PAUSE_*)
+}
+
+impl ConfigUpdated {
+    pub fn new(key: Pubkey, config: &Config) -> Self {
+        Self {
+            config: key,
+            authority: config.authority,
+            pending_authority: config.pending_authority,
+            fee: config.fee,
+            protocol_fee: config.protocol_fee,
+            dynamic_fee: config.dynamic_fee,
+            min_fee: config.min_fee,
+            max_fee: config.max_fee,
+            max_referral_fee: config.max_referral_fee,
+            initial_amp: config.initial_amp,
+            target_amp: config.target_amp,
+            ramp_stop_ts: config.ramp_stop_ts,
+            paused: config.paused,
+        }
+    }
+}
+
+/// Protocol fees swept to the treasury by `collect_protocol_fees`
+#[event]
+pub struct ProtocolFeesCollected {
+    pub config: Pubkey,   // Pool the fees accrued in
+    pub treasury: Pubkey, // Recipient of the fees
+    pub amount_x: u64,    // Token X collected
+    pub amount_y: u64,    // Token Y collected
+}
+
+/// A pool closed by `close_pool`
+#[event]
+pub struct PoolClosed {
+    pub config: Pubkey,   // Pool closed
+    pub treasury: Pubkey, // Recipient of the uncollected protocol fees
+    pub amount_x: u64,    // Token X protocol fees swept to the treasury
+    pub amount_y: u64,    // Token Y protocol fees swept to the treasury
+    pub burned_x: u64,    // Token X left in the vault that no one could withdraw, burned
+    pub burned_y: u64,    // Token Y left in the vault that no one could withdraw, burned
+}
+
+/// Liquidity added to or removed from a concentrated pool position
+#[event]
+pub struct PositionUpdated {
+    pub config: Pubkey,       // Pool the position provides liquidity to
+    pub position: Pubkey,     // Position updated
+    pub owner: Pubkey,        // Owner of the position
+    pub tick_lower: i32,      // Lower bound of the position's price range
+    pub tick_upper: i32,      // Upper bound of the position's price range
+    pub liquidity_delta: i64, // Liquidity added, negative when removed
+    pub amount_x: u64,        // Token X deposited or paid out
+    pub amount_y: u64,        // Token Y deposited or paid out
+    pub liquidity: u64,       // Liquidity of the position after the update
+}
+
+/// Fees of a concentrated pool position paid to its owner
+#[event]
+pub struct PositionFeesCollected {
+    pub config: Pubkey,   // Pool the fees accrued in
+    pub position: Pubkey, // Position the fees accrued to
+    pub owner: Pubkey,    // Owner of the position, who receives the fees
+    pub amount_x: u64,    // Token X collected
+    pub amount_y: u64,    // Token Y collected
+}
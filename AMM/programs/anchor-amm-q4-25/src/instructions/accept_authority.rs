@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::AmmError, events::ConfigUpdated, state::Config};
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub pending_authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> AcceptAuthority<'info> {
+    pub fn accept_authority(&mut self) -> Result<()> {
+        let pending = self
+            .config
+            .pending_authority
+            .ok_or(AmmError::NoPendingAuthority)?;
+        require_keys_eq!(
+            pending,
+            self.pending_authority.key(),
+            AmmError::InvalidAuthority
+        );
+
+        self.config.authority = Some(pending);
+        self.config.pending_authority = None;
+
+        emit!(ConfigUpdated::new(self.config.key(), &self.config));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        burn, close_account, transfer_checked, Burn, CloseAccount, Mint, TokenAccount,
+        TokenInterface, TransferChecked,
+    },
+};
+
+use crate::{
+    errors::AmmError,
+    events::PoolClosed,
+    state::{Config, CurveType, Oracle, Pair, RegistryPage},
+};
+
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(mut)]
+    pub closer: Signer<'info>,
+    #[account(mut)]
+    pub initializer: SystemAccount<'info>,
+    /// CHECK: Only used as the owner of the treasury token accounts, validated against the config.
+    /// The default pubkey for a pool migrated without a treasury
+    pub treasury: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    /// LP mint of the pool, omitted for a concentrated pool. A mint can't be closed, so it
+    /// stays behind with no supply and is reused if the pair and fee tier get a new pool
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+    )]
+    pub mint_lp: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        close = initializer,
+        has_one = initializer,
+        has_one = mint_x,
+        has_one = mint_y,
+        has_one = treasury,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    /// Oracle of the pool, omitted for a migrated pool that has none
+    #[account(
+        mut,
+        close = initializer,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Option<Account<'info, Oracle>>,
+    /// Pair account of the pool, omitted for a migrated pool that was never registered
+    #[account(
+        mut,
+        close = initializer,
+        has_one = config,
+    )]
+    pub pair: Option<Account<'info, Pair>>,
+    /// Registry page listing the pool, omitted along with the pair account
+    #[account(mut)]
+    pub registry_page: Option<Account<'info, RegistryPage>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    /// Treasury token account of X, omitted when there are no protocol fees of X to sweep
+    /// or no treasury
+    #[account(
+        init_if_needed,
+        payer = closer,
+        associated_token::mint = mint_x,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program_x,
+    )]
+    pub treasury_x: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Treasury token account of Y, omitted when there are no protocol fees of Y to sweep
+    /// or no treasury
+    #[account(
+        init_if_needed,
+        payer = closer,
+        associated_token::mint = mint_y,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program_y,
+    )]
+    pub treasury_y: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> ClosePool<'info> {
+    // Closes the vaults, config, oracle and pair account of a pool without liquidity, and
+    // refunds their rent to the initializer. A concentrated pool's tick arrays stay behind,
+    // cleared by its last position, and are reused by a new pool of the same pair and fee tier,
+    // or reset by `initialize_tick_array` if it has another tick spacing. Transfer fees
+    // withheld in Token-2022 vaults must be harvested to their mint first
+    pub fn close(&mut self) -> Result<()> {
+        // A pool with an authority is closed by it, an ownerless one by anyone
+        if self.config.authority.is_some() {
+            self.config.check_authority(&self.closer.key())?;
+        }
+        match self.config.curve_type {
+            CurveType::Concentrated => {
+                require!(
+                    self.config.position_count == 0,
+                    AmmError::LiquidityOutstanding
+                );
+            }
+            _ => {
+                let mint_lp = self
+                    .mint_lp
+                    .as_ref()
+                    .ok_or(ErrorCode::ConstraintAccountIsNone)?;
+                require!(mint_lp.supply == 0, AmmError::LiquidityOutstanding);
+            }
+        }
+        // Only migrated pools may predate the oracle
+        require!(
+            self.oracle.is_some() || self.config.legacy_seed.is_some(),
+            ErrorCode::ConstraintAccountIsNone
+        );
+        require!(self.config.flash_loan_owed == 0, AmmError::FlashLoanActive);
+
+        // Only the uncollected protocol fees go to the treasury. The rest backs the locked
+        // minimum liquidity, or is a concentrated pool's rounding dust, and is burned since
+        // no one may withdraw it. So are the fees of a pool migrated without a treasury
+        let (x, y) = (self.vault_x.amount, self.vault_y.amount);
+        let (fees_x, fees_y) = match self.config.treasury == Pubkey::default() {
+            true => (0, 0),
+            false => (
+                self.config.protocol_fees_x.min(x),
+                self.config.protocol_fees_y.min(y),
+            ),
+        };
+        self.config.protocol_fees_x = 0;
+        self.config.protocol_fees_y = 0;
+
+        if fees_x != 0 {
+            self.withdraw_tokens(true, fees_x)?;
+        }
+        if fees_y != 0 {
+            self.withdraw_tokens(false, fees_y)?;
+        }
+        if x != fees_x {
+            self.burn_tokens(true, x - fees_x)?;
+        }
+        if y != fees_y {
+            self.burn_tokens(false, y - fees_y)?;
+        }
+
+        self.close_vault(true)?;
+        self.close_vault(false)?;
+
+        if self.config.registered {
+            let pair = self
+                .pair
+                .as_ref()
+                .ok_or(ErrorCode::ConstraintAccountIsNone)?;
+            let registry_page = self
+                .registry_page
+                .as_mut()
+                .ok_or(ErrorCode::ConstraintAccountIsNone)?;
+            registry_page.unregister(pair.registry_index)?;
+        }
+
+        emit!(PoolClosed {
+            config: self.config.key(),
+            treasury: self.treasury.key(),
+            amount_x: fees_x,
+            amount_y: fees_y,
+            burned_x: x - fees_x,
+            burned_y: y - fees_y,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, token_program) = match is_x {
+            true => (
+                &self.vault_x,
+                &self.treasury_x,
+                &self.mint_x,
+                &self.token_program_x,
+            ),
+            false => (
+                &self.vault_y,
+                &self.treasury_y,
+                &self.mint_y,
+                &self.token_program_y,
+            ),
+        };
+        let to = to.as_ref().ok_or(ErrorCode::ConstraintAccountIsNone)?;
+
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
+
+        let cpi_program = token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(ctx, amount, mint.decimals)
+    }
+
+    pub fn burn_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, mint, token_program) = match is_x {
+            true => (&self.vault_x, &self.mint_x, &self.token_program_x),
+            false => (&self.vault_y, &self.mint_y, &self.token_program_y),
+        };
+
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
+
+        let cpi_program = token_program.to_account_info();
+
+        let cpi_accounts = Burn {
+            mint: mint.to_account_info(),
+            from: from.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        burn(ctx, amount)
+    }
+
+    pub fn close_vault(&self, is_x: bool) -> Result<()> {
+        let (vault, token_program) = match is_x {
+            true => (&self.vault_x, &self.token_program_x),
+            false => (&self.vault_y, &self.token_program_y),
+        };
+
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
+
+        let cpi_program = token_program.to_account_info();
+
+        let cpi_accounts = CloseAccount {
+            account: vault.to_account_info(),
+            destination: self.initializer.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        close_account(ctx)
+    }
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Position},
+};
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        has_one = config,
+        seeds = [
+            b"position",
+            position.config.as_ref(),
+            owner.key().as_ref(),
+            position.tick_lower.to_le_bytes().as_ref(),
+            position.tick_upper.to_le_bytes().as_ref(),
+        ],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, Position>,
+}
+
+impl<'info> ClosePosition<'info> {
+    // Refunds the rent of a position whose liquidity is withdrawn and fees collected
+    pub fn close(&mut self) -> Result<()> {
+        require!(
+            self.position.liquidity == 0
+                && self.position.fees_owed_x == 0
+                && self.position.fees_owed_y == 0,
+            AmmError::PositionNotEmpty
+        );
+        self.config.position_count = self
+            .config
+            .position_count
+            .checked_sub(1)
+            .ok_or(AmmError::Underflow)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{events::ProtocolFeesCollected, state::Config};
+
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    #[account(mut)]
+    pub collector: Signer<'info>,
+    /// CHECK: Only used as the owner of the treasury token accounts, validated against the config
+    pub treasury: UncheckedAccount<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        has_one = treasury,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = collector,
+        associated_token::mint = mint_x,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program_x,
+    )]
+    pub treasury_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = collector,
+        associated_token::mint = mint_y,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program_y,
+    )]
+    pub treasury_y: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> CollectProtocolFees<'info> {
+    // Anyone can collect, the fees only ever go to the treasury set on the config, so they
+    // aren't stranded once the authority is renounced
+    pub fn collect(&mut self) -> Result<()> {
+        let (x, y) = (self.config.protocol_fees_x, self.config.protocol_fees_y);
+
+        // Reset the accounting first so only the protocol's balance ever leaves the vaults
+        self.config.protocol_fees_x = 0;
+        self.config.protocol_fees_y = 0;
+
+        if x != 0 {
+            self.withdraw_tokens(true, x)?;
+        }
+        if y != 0 {
+            self.withdraw_tokens(false, y)?;
+        }
+
+        emit!(ProtocolFeesCollected {
+            config: self.config.key(),
+            treasury: self.treasury.key(),
+            amount_x: x,
+            amount_y: y,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, token_program) = match is_x {
+            true => (
+                &self.vault_x,
+                &self.treasury_x,
+                &self.mint_x,
+                &self.token_program_x,
+            ),
+            false => (
+                &self.vault_y,
+                &self.treasury_y,
+                &self.mint_y,
+                &self.token_program_y,
+            ),
+        };
+
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
+
+        let cpi_program = token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(ctx, amount, mint.decimals)
+    }
+}
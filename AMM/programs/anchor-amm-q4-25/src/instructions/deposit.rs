@@ -1,63 +1,103 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{mint_to, transfer, Mint, MintTo, Token, TokenAccount, Transfer},
+    token_interface::{
+        mint_to, transfer_checked, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
-use constant_product_curve::ConstantProduct;
 
-use crate::{errors::AmmError, state::Config};
+use crate::{
+    curve,
+    errors::AmmError,
+    events::LiquidityAdded,
+    pool::Pool,
+    state::{Config, CurveType, Oracle, SwapPricer, PAUSE_DEPOSIT, PAUSE_SWAP},
+    token,
+};
+
+// Quotes a single-sided deposit may spend searching for its swap amount on curves
+// without a closed form
+const MAX_ZAP_ITERATIONS: usize = 10;
 
 #[derive(Accounts)]
 pub struct Deposit<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
     #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
-        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
-        bump = config.config_bump,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Account<'info, Oracle>,
     #[account(
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_x,
         associated_token::authority = user,
+        associated_token::token_program = token_program_x,
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_y,
         associated_token::authority = user,
+        associated_token::token_program = token_program_y,
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_lp,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
-    pub user_lp: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
+    /// Ephemeral wSOL account, at seeds `[b"native_sol", user]`, through which native SOL is
+    /// paid in place of `user_x` or `user_y`. It's created and closed
+    /// within the instruction
+    /// CHECK: the address is checked when it's created
+    #[account(mut)]
+    pub native_sol: Option<UncheckedAccount<'info>>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    /// Token program of the LP mint
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
@@ -65,64 +105,311 @@ pub struct Deposit<'info> {
 impl<'info> Deposit<'info> {
     pub fn deposit(
         &mut self,
-        amount: u64, // Amount of LP tokens that the user wants to "claim"
+        amount: u64, // Amount of LP tokens that the user wants to "claim" (minimum on the first deposit)
         max_x: u64,  // Maximum amount of token X that the user is willing to deposit
         max_y: u64,  // Maximum amount of token Y that the user is willing to deposit
+        expiration: i64, // Unix timestamp after which the deposit must not execute
     ) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
-        require!(amount != 0, AmmError::InvalidAmount);
+        require!(
+            Clock::get()?.unix_timestamp <= expiration,
+            AmmError::OfferExpired
+        );
 
-        let (x, y) = match self.mint_lp.supply == 0
-            && self.vault_x.amount == 0
-            && self.vault_y.amount == 0
-        {
-            true => (max_x, max_y),
-            false => {
-                let amounts = ConstantProduct::xy_deposit_amounts_from_l(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
-                    self.mint_lp.supply,
-                    amount,
-                    6,
-                )
-                .unwrap();
-                (amounts.x, amounts.y)
-            }
-        };
+        self.config.check_not_paused(PAUSE_DEPOSIT)?;
 
-        require!(x <= max_x && y <= max_y, AmmError::SlippageExceeded);
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        // Accumulate the price the reserves held until now, before they change
+        self.oracle
+            .update(reserve_x, reserve_y, Clock::get()?.unix_timestamp);
+
+        let amounts = self
+            .pool()
+            .deposit(self.mint_lp.supply, amount, max_x, max_y)?;
+
+        self.config.locked_liquidity = self
+            .config
+            .locked_liquidity
+            .checked_add(amounts.locked_liquidity)
+            .ok_or(AmmError::Overflow)?;
 
         // deposit token x
-        self.deposit_tokens(true, x)?;
+        self.deposit_tokens(true, amounts.amount_x)?;
         // deposit token y
-        self.deposit_tokens(false, y)?;
+        self.deposit_tokens(false, amounts.amount_y)?;
         // mint lp tokens
-        self.mint_lp_tokens(amount)
+        self.mint_lp_tokens(amounts.lp)?;
+
+        self.emit_liquidity_added(amounts.amount_x, amounts.amount_y, amounts.lp)?;
+
+        Ok(())
+    }
+
+    pub fn deposit_single(
+        &mut self,
+        is_x: bool,      // Whether the user deposits token X or token Y
+        amount: u64,     // Amount of the token that the user wants to deposit
+        min_lp: u64,     // Minimum amount of LP tokens that the user wants to receive
+        expiration: i64, // Unix timestamp after which the deposit must not execute
+    ) -> Result<()> {
+        require!(
+            Clock::get()?.unix_timestamp <= expiration,
+            AmmError::OfferExpired
+        );
+
+        self.config.check_not_paused(PAUSE_DEPOSIT | PAUSE_SWAP)?;
+        require!(amount != 0, AmmError::InvalidAmount);
+
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        // Accumulate the price the reserves held until now, before they change
+        self.oracle
+            .update(reserve_x, reserve_y, Clock::get()?.unix_timestamp);
+
+        let shares = self.config.lp_shares(self.mint_lp.supply)?;
+        require!(shares != 0, AmmError::NoLiquidityInPool);
+
+        let mint_in = match is_x {
+            true => &self.mint_x,
+            false => &self.mint_y,
+        };
+        let received = token::amount_after_transfer_fee(mint_in, amount)?;
+
+        // Part of the deposit is swapped through the pool, fees included, so that the rest
+        // and the swap output match the reserves. The LP minted is capped by whichever side
+        // falls short, so the swap amount is the one that balances both
+        let pricer = self
+            .config
+            .pricer(is_x, self.vault_x.amount, self.vault_y.amount)?;
+        let best = match self.config.curve_type {
+            CurveType::ConstantProduct => {
+                let swap = curve::zap_swap_amount(
+                    pricer.reserves().0,
+                    received,
+                    pricer.fee_bps(),
+                    self.config.protocol_fee,
+                )?;
+
+                // Rounding leaves the closed form a unit or so off, so its neighbours are tried
+                let mut best = self.zap(&pricer, received, swap, shares)?;
+                for swap in [swap.saturating_sub(1), (swap + 1).min(received)] {
+                    let zap = self.zap(&pricer, received, swap, shares)?;
+                    if zap.0.min(zap.1) > best.0.min(best.1) {
+                        best = zap;
+                    }
+                }
+                best
+            }
+            _ => self.search_zap(&pricer, received, shares)?,
+        };
+
+        let (lp_in, lp_out, protocol_fee) = best;
+        let lp = lp_in.min(lp_out);
+
+        require!(lp != 0, AmmError::InvalidAmount);
+        require!(lp >= min_lp, AmmError::SlippageExceeded);
+
+        // The whole deposit lands in one vault, only the swap's protocol fee is set aside
+        self.config.accrue_protocol_fee(is_x, protocol_fee)?;
+
+        self.deposit_tokens(is_x, amount)?;
+        self.mint_lp_tokens(lp)?;
+
+        // Like a swap, the internal swap's price movement feeds the dynamic fee. The reserves
+        // were taken before the protocol's cut left them, so it isn't counted as a move
+        self.record_volatility((reserve_x, reserve_y))?;
+
+        let (amount_x, amount_y) = match is_x {
+            true => (amount, 0),
+            false => (0, amount),
+        };
+        self.emit_liquidity_added(amount_x, amount_y, lp)?;
+
+        Ok(())
+    }
+
+    // Adds the price movement from the reserves `before` to the current ones to the
+    // volatility accumulator, as `Swap::settle` does
+    fn record_volatility(&mut self, before: (u64, u64)) -> Result<()> {
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let after = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.config
+            .record_volatility(before, after, Clock::get()?.unix_timestamp);
+
+        Ok(())
+    }
+
+    // Reports the deposit with the reserves and LP supply it left behind
+    fn emit_liquidity_added(&mut self, amount_x: u64, amount_y: u64, lp: u64) -> Result<()> {
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.mint_lp.reload()?;
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        emit!(LiquidityAdded {
+            config: self.config.key(),
+            user: self.user.key(),
+            amount_x,
+            amount_y,
+            lp_minted: lp,
+            lp_supply: self.mint_lp.supply,
+            reserve_x,
+            reserve_y,
+        });
+
+        Ok(())
+    }
+
+    fn pool(&self) -> Pool<'_, 'info> {
+        Pool {
+            config: &self.config,
+            mint_x: &self.mint_x,
+            mint_y: &self.mint_y,
+            vault_x: self.vault_x.amount,
+            vault_y: self.vault_y.amount,
+        }
+    }
+
+    // LP minted for each side when `swap` out of a received `amount` is swapped first,
+    // and the protocol fee of that swap
+    fn zap(
+        &self,
+        pricer: &SwapPricer,
+        amount: u64,
+        swap: u64,
+        shares: u64,
+    ) -> Result<(u64, u64, u64)> {
+        let (reserve_in, reserve_out) = pricer.reserves();
+
+        let (amount_out, protocol_fee) = match swap {
+            0 => (0, 0),
+            _ => {
+                let quote = pricer.quote_exact_in(swap)?;
+                (quote.amount_out, self.config.protocol_share(quote.fee)?)
+            }
+        };
+
+        let reserve_in = reserve_in
+            .checked_add(swap - protocol_fee)
+            .ok_or(AmmError::Overflow)?;
+        let reserve_out = reserve_out
+            .checked_sub(amount_out)
+            .ok_or(AmmError::Underflow)?;
+
+        Ok((
+            curve::mul_div(amount - swap, shares, reserve_in, false)?,
+            curve::mul_div(amount_out, shares, reserve_out, false)?,
+            protocol_fee,
+        ))
+    }
+
+    // `zap` of the swap amount minting the most LP on curves without a closed form. The gap
+    // between the two sides shrinks as more is swapped, so a false-position search brackets
+    // where it crosses zero, halving a stale end's gap to keep converging (Illinois)
+    fn search_zap(&self, pricer: &SwapPricer, amount: u64, shares: u64) -> Result<(u64, u64, u64)> {
+        let gap = |(lp_in, lp_out, _): (u64, u64, u64)| lp_in as i128 - lp_out as i128;
+
+        let mut best = self.zap(pricer, amount, 0, shares)?;
+        let (mut low, mut high) = (0, amount);
+        let (mut gap_low, mut gap_high) =
+            (gap(best), gap(self.zap(pricer, amount, amount, shares)?));
+        let mut last_low = None;
+
+        for _ in 0..MAX_ZAP_ITERATIONS {
+            if high - low <= 1 {
+                break;
+            }
+
+            let width = gap_low - gap_high;
+            let step = match width {
+                0 => (high - low) / 2,
+                _ => ((high - low) as i128 * gap_low / width) as u64,
+            };
+            let swap = (low + step).clamp(low + 1, high - 1);
+
+            let zap = self.zap(pricer, amount, swap, shares)?;
+            if zap.0.min(zap.1) > best.0.min(best.1) {
+                best = zap;
+            }
+
+            let moved_low = gap(zap) > 0;
+            match moved_low {
+                true => (low, gap_low) = (swap, gap(zap)),
+                false => (high, gap_high) = (swap, gap(zap)),
+            }
+            match (last_low == Some(moved_low), moved_low) {
+                (true, true) => gap_high /= 2,
+                (true, false) => gap_low /= 2,
+                _ => {}
+            }
+            last_low = Some(moved_low);
+        }
+
+        Ok(best)
     }
 
     pub fn deposit_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+        let (from, to, mint, token_program) = match is_x {
             true => (
-                self.user_x.to_account_info(),
-                self.vault_x.to_account_info(),
+                &self.user_x,
+                &self.vault_x,
+                &self.mint_x,
+                &self.token_program_x,
             ),
             false => (
-                self.user_y.to_account_info(),
-                self.vault_y.to_account_info(),
+                &self.user_y,
+                &self.vault_y,
+                &self.mint_y,
+                &self.token_program_y,
             ),
         };
 
-        let cpi_program = self.token_program.to_account_info();
+        // Native SOL is wrapped into the ephemeral account and sent from there instead
+        let native_sol = match &self.native_sol {
+            Some(native_sol) if token::is_native(mint) => {
+                token::open_native(
+                    &self.user,
+                    native_sol,
+                    mint,
+                    amount,
+                    token_program,
+                    &self.system_program,
+                )?;
+                Some(native_sol)
+            }
+            _ => None,
+        };
+        let from = match native_sol {
+            Some(native_sol) => native_sol.to_account_info(),
+            None => from.to_account_info(),
+        };
 
-        let cpi_accounts = Transfer {
+        let cpi_program = token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
             from,
-            to,
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
             authority: self.user.to_account_info(),
         };
 
         let ctx = CpiContext::new(cpi_program, cpi_accounts);
 
-        transfer(ctx, amount)
+        transfer_checked(ctx, amount, mint.decimals)?;
+
+        match native_sol {
+            Some(native_sol) => token::close_native(&self.user, native_sol, token_program),
+            None => Ok(()),
+        }
     }
 
     pub fn mint_lp_tokens(&self, amount: u64) -> Result<()> {
@@ -134,9 +421,13 @@ impl<'info> Deposit<'info> {
             authority: self.config.to_account_info(),
         };
 
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
         let signer_seeds: &[&[&[u8]]] = &[&[
             b"config",
-            &self.config.seed.to_le_bytes(),
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
             &[self.config.config_bump],
         ]];
 
@@ -0,0 +1,146 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::instructions::{
+        load_current_index_checked, load_instruction_at_checked,
+    },
+    Discriminator,
+};
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::{
+    errors::AmmError,
+    instruction,
+    state::{Config, CurveType, PAUSE_SWAP},
+};
+
+/// Position of `config` in the accounts of `flash_borrow` and `flash_repay`
+pub const FLASH_CONFIG_INDEX: usize = 2;
+/// Least fee a flash loan is charged, in units of the borrowed token
+pub const MIN_FLASH_FEE: u64 = 1;
+
+// Account order is relied on by `FLASH_CONFIG_INDEX`
+#[derive(Accounts)]
+pub struct FlashBorrow<'info> {
+    pub user: Signer<'info>,
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = mint.key() == config.mint_x || mint.key() == config.mint_y @ AmmError::InvalidToken,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
+    pub user_token: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Instructions sysvar, checked by address
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashBorrow<'info> {
+    pub fn flash_borrow(&mut self, amount: u64) -> Result<()> {
+        // Also rejects a borrow while another one from this pool is outstanding
+        self.config.check_not_paused(PAUSE_SWAP)?;
+        require!(amount != 0, AmmError::InvalidAmount);
+        // The fee accrues to LPs through the reserves, which positions don't share
+        require!(
+            self.config.curve_type != CurveType::Concentrated,
+            AmmError::InvalidCurve
+        );
+
+        // Only the LP-owned reserve can be lent, not the accrued protocol fees
+        let is_x = self.mint.key() == self.config.mint_x;
+        let protocol_fees = match is_x {
+            true => self.config.protocol_fees_x,
+            false => self.config.protocol_fees_y,
+        };
+        let reserve = self
+            .vault
+            .amount
+            .checked_sub(protocol_fees)
+            .ok_or(AmmError::Underflow)?;
+        require!(amount <= reserve, AmmError::InsufficientBalance);
+
+        self.check_repaid_later()?;
+
+        // The fee stays in the vault once repaid, so it accrues to LPs. A zero-fee pool
+        // still charges MIN_FLASH_FEE, so that its reserves can't be borrowed for free
+        let fee = self.config.swap_fee(amount)?.max(MIN_FLASH_FEE);
+        self.config.flash_loan_owed = amount.checked_add(fee).ok_or(AmmError::Overflow)?;
+        self.config.flash_loan_is_x = is_x;
+
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
+
+        let cpi_accounts = TransferChecked {
+            from: self.vault.to_account_info(),
+            mint: self.mint.to_account_info(),
+            to: self.user_token.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, self.mint.decimals)
+    }
+
+    // The borrow must be a top-level instruction followed by a `flash_repay` of the same
+    // pool in this transaction, with no other borrow from the pool in between
+    fn check_repaid_later(&self) -> Result<()> {
+        let instructions = self.instructions.to_account_info();
+        let current = load_current_index_checked(&instructions)? as usize;
+
+        let borrow = load_instruction_at_checked(current, &instructions)?;
+        require_keys_eq!(borrow.program_id, crate::ID, AmmError::InvalidFlashLoan);
+
+        let mut index = current + 1;
+        while let Ok(ix) = load_instruction_at_checked(index, &instructions) {
+            let same_pool = ix
+                .accounts
+                .get(FLASH_CONFIG_INDEX)
+                .is_some_and(|config| config.pubkey == self.config.key());
+
+            if ix.program_id == crate::ID && same_pool {
+                require!(
+                    !ix.data.starts_with(instruction::FlashBorrow::DISCRIMINATOR),
+                    AmmError::InvalidFlashLoan
+                );
+
+                if ix.data.starts_with(instruction::FlashRepay::DISCRIMINATOR) {
+                    return Ok(());
+                }
+            }
+
+            index += 1;
+        }
+
+        err!(AmmError::FlashLoanNotRepaid)
+    }
+}
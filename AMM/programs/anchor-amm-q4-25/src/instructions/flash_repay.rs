@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::{errors::AmmError, state::Config, token};
+
+// Account order is relied on by `FLASH_CONFIG_INDEX`
+#[derive(Accounts)]
+pub struct FlashRepay<'info> {
+    pub user: Signer<'info>,
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_token: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashRepay<'info> {
+    pub fn flash_repay(&mut self) -> Result<()> {
+        let owed = self.config.flash_loan_owed;
+        require!(owed != 0, AmmError::InvalidFlashLoan);
+
+        let loan_mint = match self.config.flash_loan_is_x {
+            true => self.config.mint_x,
+            false => self.config.mint_y,
+        };
+        require_keys_eq!(self.mint.key(), loan_mint, AmmError::InvalidFlashLoan);
+
+        self.config.flash_loan_owed = 0;
+
+        // Grossed up by any transfer fee, so the vault receives the full amount owed
+        let amount = token::amount_before_transfer_fee(&self.mint, owed)?;
+
+        let cpi_accounts = TransferChecked {
+            from: self.user_token.to_account_info(),
+            mint: self.mint.to_account_info(),
+            to: self.vault.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(ctx, amount, self.mint.decimals)
+    }
+}
@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Farm},
+    token,
+};
+
+#[derive(Accounts)]
+pub struct FundFarm<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        has_one = config,
+        has_one = reward_mint,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = authority,
+        token::token_program = token_program,
+    )]
+    pub authority_reward: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FundFarm<'info> {
+    // Tops up the rewards the farm can emit
+    pub fn fund(&mut self, amount: u64) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(amount != 0, AmmError::InvalidAmount);
+
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+
+        // Only what the vault receives after any transfer fee can be emitted
+        let received = token::amount_after_transfer_fee(&self.reward_mint, amount)?;
+        self.farm.rewards_unallocated = self
+            .farm
+            .rewards_unallocated
+            .checked_add(received)
+            .ok_or(AmmError::Overflow)?;
+
+        let cpi_accounts = TransferChecked {
+            from: self.authority_reward.to_account_info(),
+            mint: self.reward_mint.to_account_info(),
+            to: self.reward_vault.to_account_info(),
+            authority: self.authority.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(ctx, amount, self.reward_mint.decimals)
+    }
+}
@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::state::{Farm, Staker};
+
+#[derive(Accounts)]
+pub struct Harvest<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = reward_mint,
+        seeds = [b"farm", farm.config.as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        mut,
+        has_one = farm,
+        seeds = [b"staker", farm.key().as_ref(), user.key().as_ref()],
+        bump = staker.bump,
+    )]
+    pub staker: Account<'info, Staker>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = reward_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_reward: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Harvest<'info> {
+    pub fn harvest(&mut self) -> Result<()> {
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        self.staker.settle(&self.farm)?;
+
+        let amount = self.staker.rewards_owed;
+        if amount == 0 {
+            return Ok(());
+        }
+        self.staker.rewards_owed = 0;
+
+        let farm_config = self.farm.config;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"farm", farm_config.as_ref(), &[self.farm.bump]]];
+
+        let cpi_accounts = TransferChecked {
+            from: self.reward_vault.to_account_info(),
+            mint: self.reward_mint.to_account_info(),
+            to: self.user_reward.to_account_info(),
+            authority: self.farm.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, self.reward_mint.decimals)?;
+
+        Ok(())
+    }
+}
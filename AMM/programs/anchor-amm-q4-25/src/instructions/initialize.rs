@@ -1,73 +1,246 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
-use crate::state::Config;
+use crate::{
+    errors::AmmError,
+    events::PoolInitialized,
+    stable_swap::{MAX_AMP, MIN_AMP},
+    state::{Config, CurveType, Oracle, Pair, Registry, RegistryPage, MAX_BPS},
+    weighted::MIN_WEIGHT,
+};
 
 #[derive(Accounts)]
-#[instruction(seed: u64)]
+#[instruction(
+    fee: u16,
+    authority: Option<Pubkey>,
+    protocol_fee: u16,
+    treasury: Pubkey,
+    curve_type: CurveType,
+    amp: u64,
+    weight_x: u16,
+)]
 pub struct Initialize<'info> {
     #[account(mut)]
     pub initializer: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    /// Factory admin, approving the pool and the parameters the initializer gives it
+    pub admin: Signer<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
     #[account(
         init,
         payer = initializer,
+        seeds = [
+            b"pair",
+            Pair::canonical(mint_x.key(), mint_y.key()).0.as_ref(),
+            Pair::canonical(mint_x.key(), mint_y.key()).1.as_ref(),
+            fee.to_le_bytes().as_ref(),
+            [curve_type as u8].as_ref(),
+        ],
+        bump,
+        space = Pair::DISCRIMINATOR.len() + Pair::INIT_SPACE,
+        constraint = mint_x.key() != mint_y.key() @ AmmError::IdenticalMints,
+    )]
+    pub pair: Account<'info, Pair>,
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump,
+        has_one = admin @ AmmError::InvalidAdmin,
+    )]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        init_if_needed,
+        payer = initializer,
+        seeds = [b"registry_page", registry.next_page().to_le_bytes().as_ref()],
+        bump,
+        space = RegistryPage::DISCRIMINATOR.len() + RegistryPage::INIT_SPACE,
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
+    /// Already exists if a closed pool of the pair and fee tier left it behind, which must
+    /// have the same LP decimals
+    #[account(
+        init_if_needed,
+        payer = initializer,
         seeds = [b"lp", config.key.as_ref()],
         bump,
-        mint::decimals = 6,
+        mint::decimals = Config::lp_decimals(curve_type, mint_x.decimals, mint_y.decimals, weight_x),
         mint::authority = config,
+        mint::token_program = token_program,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init,
         payer = initializer,
-        seeds = [b"config", seed.to_le_bytes().as_ref()],
+        seeds = [
+            b"config",
+            Pair::canonical(mint_x.key(), mint_y.key()).0.as_ref(),
+            Pair::canonical(mint_x.key(), mint_y.key()).1.as_ref(),
+            fee.to_le_bytes().as_ref(),
+            [curve_type as u8].as_ref(),
+        ],
         bump,
         space = Config::DISCRIMINATOR.len() + Config::INIT_SPACE,
     )]
     pub config: Account<'info, Config>,
-    pub token_program: Program<'info, Token>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump,
+        space = Oracle::DISCRIMINATOR.len() + Oracle::INIT_SPACE,
+    )]
+    pub oracle: Account<'info, Oracle>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    /// Token program of the LP mint
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Initialize<'info> {
+    #[allow(clippy::too_many_arguments)]
     pub fn init(
         &mut self,
-        seed: u64,
         fee: u16,
         authority: Option<Pubkey>,
+        protocol_fee: u16,
+        treasury: Pubkey,
+        curve_type: CurveType,
+        amp: u64,      // StableSwap amplification coefficient, 0 for other curves
+        weight_x: u16, // Weighted pool weight of token X in basis points, 0 for other curves
         bumps: InitializeBumps,
     ) -> Result<()> {
+        require!(fee < MAX_BPS, AmmError::InvalidFee);
+        require!(protocol_fee <= MAX_BPS, AmmError::InvalidFee);
+        // Concentrated pools are created by `initialize_concentrated`, without an LP token
+        require!(
+            curve_type != CurveType::Concentrated,
+            AmmError::InvalidCurve
+        );
+        require!(
+            match curve_type {
+                CurveType::StableSwap => (MIN_AMP..=MAX_AMP).contains(&amp),
+                _ => amp == 0,
+            },
+            AmmError::InvalidAmp
+        );
+        require!(
+            match curve_type {
+                CurveType::Weighted => (MIN_WEIGHT..=MAX_BPS - MIN_WEIGHT).contains(&weight_x),
+                _ => weight_x == 0,
+            },
+            AmmError::InvalidWeight
+        );
+
+        // Unweighted curves split the pool evenly
+        let (weight_x, weight_y) = match curve_type {
+            CurveType::Weighted => (weight_x, MAX_BPS - weight_x),
+            _ => (MAX_BPS / 2, MAX_BPS / 2),
+        };
+
+        let now = Clock::get()?.unix_timestamp;
+
         self.config.set_inner(Config {
-            seed,
+            legacy_seed: None,
+            registered: true,
             authority,
+            pending_authority: None,
+            initializer: self.initializer.key(),
             mint_x: self.mint_x.key(),
             mint_y: self.mint_y.key(),
+            decimals_x: self.mint_x.decimals,
+            decimals_y: self.mint_y.decimals,
+            lp_decimals: self.mint_lp.decimals,
+            curve_type,
+            initial_amp: amp,
+            target_amp: amp,
+            ramp_start_ts: now,
+            ramp_stop_ts: now,
+            weight_x,
+            weight_y,
+            tick_spacing: 0,
+            sqrt_price: 0,
+            tick_current: 0,
+            liquidity: 0,
+            position_count: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
             fee,
-            locked: false,
+            fee_tier: fee,
+            dynamic_fee: false,
+            min_fee: 0,
+            max_fee: 0,
+            volatility: 0,
+            volatility_ts: now,
+            protocol_fee,
+            max_referral_fee: 0,
+            treasury,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            locked_liquidity: 0,
+            flash_loan_owed: 0,
+            flash_loan_is_x: false,
+            paused: 0,
             config_bump: bumps.config,
             lp_bump: bumps.mint_lp,
         });
 
+        self.oracle.init(self.config.key(), bumps.oracle, now);
+
+        let registry_index = self.registry.register(
+            &mut self.registry_page,
+            bumps.registry_page,
+            self.config.key(),
+        )?;
+        let (mint_a, mint_b) = Pair::canonical(self.mint_x.key(), self.mint_y.key());
+        self.pair.set_inner(Pair {
+            config: self.config.key(),
+            mint_a,
+            mint_b,
+            fee_tier: fee,
+            curve_type,
+            registry_index,
+            bump: bumps.pair,
+        });
+
+        emit!(PoolInitialized {
+            config: self.config.key(),
+            initializer: self.initializer.key(),
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            mint_lp: self.mint_lp.key(),
+            curve_type,
+            fee,
+            protocol_fee,
+            authority,
+        });
+
         Ok(())
     }
 }
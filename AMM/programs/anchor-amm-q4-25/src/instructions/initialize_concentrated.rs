@@ -0,0 +1,205 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    concentrated::{self, MAX_TICK_SPACING},
+    errors::AmmError,
+    events::PoolInitialized,
+    state::{Config, CurveType, Oracle, Pair, Registry, RegistryPage, MAX_BPS},
+};
+
+#[derive(Accounts)]
+#[instruction(fee: u16)]
+pub struct InitializeConcentrated<'info> {
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+    /// Factory admin, approving the pool and the parameters the initializer gives it
+    pub admin: Signer<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [
+            b"pair",
+            Pair::canonical(mint_x.key(), mint_y.key()).0.as_ref(),
+            Pair::canonical(mint_x.key(), mint_y.key()).1.as_ref(),
+            fee.to_le_bytes().as_ref(),
+            [CurveType::Concentrated as u8].as_ref(),
+        ],
+        bump,
+        space = Pair::DISCRIMINATOR.len() + Pair::INIT_SPACE,
+        constraint = mint_x.key() != mint_y.key() @ AmmError::IdenticalMints,
+    )]
+    pub pair: Account<'info, Pair>,
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump,
+        has_one = admin @ AmmError::InvalidAdmin,
+    )]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        init_if_needed,
+        payer = initializer,
+        seeds = [b"registry_page", registry.next_page().to_le_bytes().as_ref()],
+        bump,
+        space = RegistryPage::DISCRIMINATOR.len() + RegistryPage::INIT_SPACE,
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [
+            b"config",
+            Pair::canonical(mint_x.key(), mint_y.key()).0.as_ref(),
+            Pair::canonical(mint_x.key(), mint_y.key()).1.as_ref(),
+            fee.to_le_bytes().as_ref(),
+            [CurveType::Concentrated as u8].as_ref(),
+        ],
+        bump,
+        space = Config::DISCRIMINATOR.len() + Config::INIT_SPACE,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump,
+        space = Oracle::DISCRIMINATOR.len() + Oracle::INIT_SPACE,
+    )]
+    pub oracle: Account<'info, Oracle>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeConcentrated<'info> {
+    // Creates a pool whose liquidity is provided over price ranges by positions, starting
+    // at `sqrt_price`. It has no LP token or constant-product reserves, and its oracle
+    // tracks `sqrt_price`
+    #[allow(clippy::too_many_arguments)]
+    pub fn init(
+        &mut self,
+        fee: u16,
+        authority: Option<Pubkey>,
+        protocol_fee: u16,
+        treasury: Pubkey,
+        tick_spacing: u16, // Distance between the ticks positions may be bounded by
+        sqrt_price: u128,  // Square root of the initial price of X in Y, in Q64.64
+        bumps: InitializeConcentratedBumps,
+    ) -> Result<()> {
+        require!(fee < MAX_BPS, AmmError::InvalidFee);
+        require!(protocol_fee <= MAX_BPS, AmmError::InvalidFee);
+        require!(
+            (1..=MAX_TICK_SPACING).contains(&tick_spacing),
+            AmmError::InvalidTickRange
+        );
+
+        let tick_current = concentrated::tick_at_sqrt_price(sqrt_price)?;
+        let now = Clock::get()?.unix_timestamp;
+
+        self.config.set_inner(Config {
+            legacy_seed: None,
+            registered: true,
+            authority,
+            pending_authority: None,
+            initializer: self.initializer.key(),
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            decimals_x: self.mint_x.decimals,
+            decimals_y: self.mint_y.decimals,
+            lp_decimals: 0,
+            curve_type: CurveType::Concentrated,
+            initial_amp: 0,
+            target_amp: 0,
+            ramp_start_ts: now,
+            ramp_stop_ts: now,
+            weight_x: MAX_BPS / 2,
+            weight_y: MAX_BPS / 2,
+            tick_spacing,
+            sqrt_price,
+            tick_current,
+            liquidity: 0,
+            position_count: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
+            fee,
+            fee_tier: fee,
+            dynamic_fee: false,
+            min_fee: 0,
+            max_fee: 0,
+            volatility: 0,
+            volatility_ts: now,
+            protocol_fee,
+            max_referral_fee: 0,
+            treasury,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            locked_liquidity: 0,
+            flash_loan_owed: 0,
+            flash_loan_is_x: false,
+            paused: 0,
+            config_bump: bumps.config,
+            lp_bump: 0,
+        });
+
+        self.oracle.init(self.config.key(), bumps.oracle, now);
+
+        let registry_index = self.registry.register(
+            &mut self.registry_page,
+            bumps.registry_page,
+            self.config.key(),
+        )?;
+        let (mint_a, mint_b) = Pair::canonical(self.mint_x.key(), self.mint_y.key());
+        self.pair.set_inner(Pair {
+            config: self.config.key(),
+            mint_a,
+            mint_b,
+            fee_tier: fee,
+            curve_type: CurveType::Concentrated,
+            registry_index,
+            bump: bumps.pair,
+        });
+
+        emit!(PoolInitialized {
+            config: self.config.key(),
+            initializer: self.initializer.key(),
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            mint_lp: Pubkey::default(),
+            curve_type: CurveType::Concentrated,
+            fee,
+            protocol_fee,
+            authority,
+        });
+
+        Ok(())
+    }
+}
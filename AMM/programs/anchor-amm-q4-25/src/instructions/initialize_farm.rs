@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Farm},
+};
+
+#[derive(Accounts)]
+pub struct InitializeFarm<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = lp_token_program,
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = reward_token_program,
+        constraint = reward_mint.key() != mint_lp.key() @ AmmError::InvalidToken,
+    )]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"farm", config.key().as_ref()],
+        bump,
+        space = Farm::DISCRIMINATOR.len() + Farm::INIT_SPACE,
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = lp_token_program,
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = reward_token_program,
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub lp_token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeFarm<'info> {
+    // A pool has at most one farm, created by its authority. Emission only starts once
+    // the farm is funded with `fund_farm`
+    pub fn init(
+        &mut self,
+        reward_per_second: u64,
+        end_ts: i64,
+        bumps: InitializeFarmBumps,
+    ) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(end_ts >= now, AmmError::InvalidEmission);
+
+        self.farm.set_inner(Farm {
+            config: self.config.key(),
+            mint_lp: self.mint_lp.key(),
+            reward_mint: self.reward_mint.key(),
+            reward_per_second,
+            end_ts,
+            last_update_ts: now,
+            acc_reward_per_share: 0,
+            total_staked: 0,
+            rewards_unallocated: 0,
+            bump: bumps.farm,
+        });
+
+        Ok(())
+    }
+}
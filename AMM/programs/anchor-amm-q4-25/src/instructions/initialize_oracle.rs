@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Config, Oracle};
+
+#[derive(Accounts)]
+pub struct InitializeOracle<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump,
+        space = Oracle::DISCRIMINATOR.len() + Oracle::INIT_SPACE,
+    )]
+    pub oracle: Account<'info, Oracle>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeOracle<'info> {
+    // Pools created before the oracle existed need one before they can trade again
+    pub fn init(&mut self, bumps: InitializeOracleBumps) -> Result<()> {
+        self.oracle.init(
+            self.config.key(),
+            bumps.oracle,
+            Clock::get()?.unix_timestamp,
+        );
+
+        Ok(())
+    }
+}
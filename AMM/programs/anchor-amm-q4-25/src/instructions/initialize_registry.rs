@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Registry;
+
+#[derive(Accounts)]
+pub struct InitializeRegistry<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"registry"],
+        bump,
+        space = Registry::DISCRIMINATOR.len() + Registry::INIT_SPACE,
+    )]
+    pub registry: Account<'info, Registry>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeRegistry<'info> {
+    // Created once per program, before the first pool; every `initialize` lists its pool
+    // on the registry's last page, starting a new page when that one is full. The payer
+    // becomes the factory admin
+    pub fn init(&mut self, bumps: InitializeRegistryBumps) -> Result<()> {
+        self.registry.set_inner(Registry {
+            admin: self.payer.key(),
+            pool_count: 0,
+            bump: bumps.registry,
+        });
+
+        Ok(())
+    }
+}
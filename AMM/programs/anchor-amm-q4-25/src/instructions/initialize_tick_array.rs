@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    concentrated::{MAX_TICK, MIN_TICK},
+    errors::AmmError,
+    state::{Config, CurveType, Tick, TickArray, TICK_ARRAY_SIZE},
+};
+
+#[derive(Accounts)]
+#[instruction(start_tick: i32)]
+pub struct InitializeTickArray<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+        constraint = config.curve_type == CurveType::Concentrated @ AmmError::InvalidCurve,
+    )]
+    pub config: Account<'info, Config>,
+    /// Created if needed, and reset if left behind by a closed pool with another tick spacing
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"tick_array", config.key().as_ref(), start_tick.to_le_bytes().as_ref()],
+        bump,
+        space = TickArray::DISCRIMINATOR.len() + TickArray::INIT_SPACE,
+    )]
+    pub tick_array: Account<'info, TickArray>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeTickArray<'info> {
+    // Anyone may create the tick arrays that positions and swaps of a pool need. One left
+    // behind by a closed pool is reused as is if it has the same tick spacing, its ticks were
+    // cleared by the last position, and reset otherwise
+    pub fn init(&mut self, start_tick: i32, bumps: InitializeTickArrayBumps) -> Result<()> {
+        let tick_spacing = self.config.tick_spacing;
+        require!(
+            start_tick == TickArray::start_tick_for(start_tick, tick_spacing)
+                && (TickArray::start_tick_for(MIN_TICK, tick_spacing)..=MAX_TICK)
+                    .contains(&start_tick),
+            AmmError::InvalidTickArray
+        );
+
+        if self.tick_array.tick_spacing == tick_spacing {
+            return Ok(());
+        }
+
+        self.tick_array.set_inner(TickArray {
+            config: self.config.key(),
+            start_tick,
+            tick_spacing,
+            ticks: vec![Tick::default(); TICK_ARRAY_SIZE],
+            bump: bumps.tick_array,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+use anchor_spl::token_interface::Mint;
+
+use crate::{
+    errors::AmmError,
+    state::{Config, ConfigV0, CurveType, MAX_BPS, PAUSE_ALL},
+};
+
+/// Decimals every LP mint was created with before they were derived from the pool's tokens
+const LEGACY_LP_DECIMALS: u8 = 6;
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    /// Pays the rent of the larger layout, the legacy authority if the pool has one
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Still in the legacy layout that `Account<Config>` can't deserialize, validated in `migrate`
+    #[account(mut, owner = crate::ID)]
+    pub config: UncheckedAccount<'info>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateConfig<'info> {
+    // Rewrites a `ConfigV0` account in the current layout, a locked pool stays fully paused.
+    // A pool with an authority is migrated by it, which also picks the treasury. One without
+    // is migrated by anyone, and has neither an initializer nor a treasury to close it to
+    pub fn migrate(&mut self, treasury: Pubkey) -> Result<()> {
+        let info = self.config.to_account_info();
+
+        let legacy = {
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() == Config::DISCRIMINATOR.len() + ConfigV0::INIT_SPACE,
+                AmmError::AlreadyMigrated
+            );
+            require!(
+                data.starts_with(Config::DISCRIMINATOR),
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            ConfigV0::deserialize(&mut &data[Config::DISCRIMINATOR.len()..])?
+        };
+
+        let expected = Pubkey::create_program_address(
+            &[
+                b"config",
+                legacy.seed.to_le_bytes().as_ref(),
+                &[legacy.config_bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| AmmError::BumpError)?;
+        require_keys_eq!(expected, info.key(), ErrorCode::ConstraintSeeds);
+        require_keys_eq!(legacy.mint_x, self.mint_x.key(), AmmError::InvalidToken);
+        require_keys_eq!(legacy.mint_y, self.mint_y.key(), AmmError::InvalidToken);
+
+        let initializer = match legacy.authority {
+            Some(authority) => {
+                require_keys_eq!(authority, self.payer.key(), AmmError::InvalidAuthority);
+                require_keys_neq!(treasury, Pubkey::default(), AmmError::InvalidTreasury);
+                authority
+            }
+            None => {
+                require_keys_eq!(treasury, Pubkey::default(), AmmError::InvalidTreasury);
+                Pubkey::default()
+            }
+        };
+
+        let config = Config {
+            legacy_seed: Some(legacy.seed),
+            registered: false,
+            authority: legacy.authority,
+            pending_authority: None,
+            initializer,
+            mint_x: legacy.mint_x,
+            mint_y: legacy.mint_y,
+            decimals_x: self.mint_x.decimals,
+            decimals_y: self.mint_y.decimals,
+            lp_decimals: LEGACY_LP_DECIMALS,
+            curve_type: CurveType::ConstantProduct,
+            initial_amp: 0,
+            target_amp: 0,
+            ramp_start_ts: 0,
+            ramp_stop_ts: 0,
+            weight_x: MAX_BPS / 2,
+            weight_y: MAX_BPS / 2,
+            tick_spacing: 0,
+            sqrt_price: 0,
+            tick_current: 0,
+            liquidity: 0,
+            position_count: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
+            fee: legacy.fee,
+            fee_tier: legacy.fee,
+            dynamic_fee: false,
+            min_fee: 0,
+            max_fee: 0,
+            volatility: 0,
+            volatility_ts: 0,
+            protocol_fee: 0,
+            max_referral_fee: 0,
+            treasury,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            locked_liquidity: 0,
+            flash_loan_owed: 0,
+            flash_loan_is_x: false,
+            paused: if legacy.locked { PAUSE_ALL } else { 0 },
+            config_bump: legacy.config_bump,
+            lp_bump: legacy.lp_bump,
+        };
+
+        let space = Config::DISCRIMINATOR.len() + Config::INIT_SPACE;
+        let rent = Rent::get()?.minimum_balance(space);
+        let top_up = rent.saturating_sub(info.lamports());
+
+        if top_up != 0 {
+            let cpi_accounts = Transfer {
+                from: self.payer.to_account_info(),
+                to: info.clone(),
+            };
+            let ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+            transfer(ctx, top_up)?;
+        }
+
+        info.resize(space)?;
+
+        let mut data = info.try_borrow_mut_data()?;
+        config.try_serialize(&mut &mut data[..])
+    }
+}
@@ -1,9 +1,61 @@
+pub mod accept_authority;
+pub mod close_pool;
+pub mod close_position;
+pub mod collect_protocol_fees;
 pub mod deposit;
+pub mod flash_borrow;
+pub mod flash_repay;
+pub mod fund_farm;
+pub mod harvest;
 pub mod initialize;
+pub mod initialize_concentrated;
+pub mod initialize_farm;
+pub mod initialize_oracle;
+pub mod initialize_registry;
+pub mod initialize_tick_array;
+pub mod migrate_config;
+pub mod open_position;
+pub mod quote;
+pub mod register_pool;
+pub mod set_referrer;
+pub mod stake;
 pub mod swap;
+pub mod swap_concentrated;
+pub mod swap_route;
+pub mod twap;
+pub mod unstake;
+pub mod update_config;
+pub mod update_farm;
+pub mod update_position;
 pub mod withdraw;
 
+pub use accept_authority::*;
+pub use close_pool::*;
+pub use close_position::*;
+pub use collect_protocol_fees::*;
 pub use deposit::*;
+pub use flash_borrow::*;
+pub use flash_repay::*;
+pub use fund_farm::*;
+pub use harvest::*;
 pub use initialize::*;
+pub use initialize_concentrated::*;
+pub use initialize_farm::*;
+pub use initialize_oracle::*;
+pub use initialize_registry::*;
+pub use initialize_tick_array::*;
+pub use migrate_config::*;
+pub use open_position::*;
+pub use quote::*;
+pub use register_pool::*;
+pub use set_referrer::*;
+pub use stake::*;
 pub use swap::*;
+pub use swap_concentrated::*;
+pub use swap_route::*;
+pub use twap::*;
+pub use unstake::*;
+pub use update_config::*;
+pub use update_farm::*;
+pub use update_position::*;
 pub use withdraw::*;
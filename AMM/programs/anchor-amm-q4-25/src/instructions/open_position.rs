@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::AmmError,
+    state::{Config, CurveType, Position},
+};
+
+#[derive(Accounts)]
+#[instruction(tick_lower: i32, tick_upper: i32)]
+pub struct OpenPosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+        constraint = config.curve_type == CurveType::Concentrated @ AmmError::InvalidCurve,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            b"position",
+            config.key().as_ref(),
+            owner.key().as_ref(),
+            tick_lower.to_le_bytes().as_ref(),
+            tick_upper.to_le_bytes().as_ref(),
+        ],
+        bump,
+        space = Position::DISCRIMINATOR.len() + Position::INIT_SPACE,
+    )]
+    pub position: Account<'info, Position>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> OpenPosition<'info> {
+    // Opens an empty position over the price range `tick_lower..tick_upper`, funded
+    // with `increase_liquidity`
+    pub fn open(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        bumps: OpenPositionBumps,
+    ) -> Result<()> {
+        self.config.check_tick_range(tick_lower, tick_upper)?;
+        self.config.position_count = self
+            .config
+            .position_count
+            .checked_add(1)
+            .ok_or(AmmError::Overflow)?;
+
+        self.position.set_inner(Position {
+            config: self.config.key(),
+            owner: self.owner.key(),
+            tick_lower,
+            tick_upper,
+            liquidity: 0,
+            fee_growth_inside_x: 0,
+            fee_growth_inside_y: 0,
+            fees_owed_x: 0,
+            fees_owed_y: 0,
+            bump: bumps.position,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    errors::AmmError,
+    instructions::swap_concentrated::{load_tick_arrays, swap_across_ticks},
+    pool::{DepositAmounts, Pool, SwapAmounts, WithdrawAmounts},
+    state::{Config, CurveType, PAUSE_DEPOSIT, PAUSE_SWAP, PAUSE_WITHDRAW},
+    token,
+};
+
+#[derive(Accounts)]
+pub struct Quote<'info> {
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        has_one = mint_x,
+        has_one = mint_y,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    /// LP mint of the pool, omitted for a concentrated pool, which has none
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+    )]
+    pub mint_lp: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+// Read only, each quote runs the same checks and pricing as the instruction it mirrors
+impl<'info> Quote<'info> {
+    // A concentrated pool is quoted across the tick arrays `swap_concentrated` would take,
+    // passed as remaining accounts the same way
+    pub fn quote_swap(
+        &self,
+        accounts: &'info [AccountInfo<'info>],
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        expiration: i64,
+        referral_fee: u16, // The referrer's registered share of the swap fee, 0 without one
+    ) -> Result<SwapAmounts> {
+        self.check(expiration, PAUSE_SWAP)?;
+
+        if self.config.curve_type == CurveType::Concentrated {
+            return self.quote_concentrated(accounts, is_x, amount_in, min_amount_out);
+        }
+
+        let mut amounts = self.pool().swap_exact_in(is_x, amount_in, min_amount_out)?;
+        if referral_fee != 0 {
+            amounts.referral =
+                self.config
+                    .referral_share(amounts.fee, amounts.protocol_fee, referral_fee)?;
+        }
+
+        Ok(amounts)
+    }
+
+    pub fn quote_deposit(
+        &self,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expiration: i64,
+    ) -> Result<DepositAmounts> {
+        self.check(expiration, PAUSE_DEPOSIT)?;
+
+        self.pool().deposit(self.lp_supply()?, amount, max_x, max_y)
+    }
+
+    pub fn quote_withdraw(
+        &self,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+        expiration: i64,
+    ) -> Result<WithdrawAmounts> {
+        self.check(expiration, PAUSE_WITHDRAW)?;
+
+        self.pool()
+            .withdraw(self.lp_supply()?, amount, min_x, min_y)
+    }
+
+    // Concentrated swaps pay no referral, so a referral fee doesn't apply to them
+    fn quote_concentrated(
+        &self,
+        accounts: &'info [AccountInfo<'info>],
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<SwapAmounts> {
+        require!(amount_in != 0, AmmError::InvalidAmount);
+
+        let fee_bps = self.config.current_fee()?;
+        let (mint_in, mint_out) = match is_x {
+            true => (&self.mint_x, &self.mint_y),
+            false => (&self.mint_y, &self.mint_x),
+        };
+        let received = token::amount_after_transfer_fee(mint_in, amount_in)?;
+
+        // The tick arrays are never written back, so crossing their ticks changes nothing
+        let mut tick_arrays = load_tick_arrays(&self.config, accounts, is_x)?;
+        let swap = swap_across_ticks(&self.config, &mut tick_arrays, is_x, received, fee_bps)?;
+
+        require!(swap.amount_out != 0, AmmError::InvalidAmount);
+
+        let amount_received = token::amount_after_transfer_fee(mint_out, swap.amount_out)?;
+        require!(
+            amount_received >= min_amount_out,
+            AmmError::SlippageExceeded
+        );
+
+        Ok(SwapAmounts {
+            amount_in,
+            amount_out: swap.amount_out,
+            amount_received,
+            fee: swap.fee,
+            fee_bps,
+            protocol_fee: swap.protocol_fee,
+            referral: 0,
+        })
+    }
+
+    fn lp_supply(&self) -> Result<u64> {
+        let mint_lp = self
+            .mint_lp
+            .as_ref()
+            .ok_or(ErrorCode::ConstraintAccountIsNone)?;
+
+        Ok(mint_lp.supply)
+    }
+
+    fn check(&self, expiration: i64, flags: u8) -> Result<()> {
+        require!(
+            Clock::get()?.unix_timestamp <= expiration,
+            AmmError::OfferExpired
+        );
+
+        self.config.check_not_paused(flags)
+    }
+
+    fn pool(&self) -> Pool<'_, 'info> {
+        Pool {
+            config: &self.config,
+            mint_x: &self.mint_x,
+            mint_y: &self.mint_y,
+            vault_x: self.vault_x.amount,
+            vault_y: self.vault_y.amount,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Pair, Registry, RegistryPage},
+};
+
+#[derive(Accounts)]
+pub struct RegisterPool<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Factory admin, approving the pool's listing under its pair
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [
+            b"pair",
+            Pair::canonical(config.mint_x, config.mint_y).0.as_ref(),
+            Pair::canonical(config.mint_x, config.mint_y).1.as_ref(),
+            config.fee_tier.to_le_bytes().as_ref(),
+            [config.curve_type as u8].as_ref(),
+        ],
+        bump,
+        space = Pair::DISCRIMINATOR.len() + Pair::INIT_SPACE,
+    )]
+    pub pair: Account<'info, Pair>,
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump,
+        has_one = admin @ AmmError::InvalidAdmin,
+    )]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"registry_page", registry.next_page().to_le_bytes().as_ref()],
+        bump,
+        space = RegistryPage::DISCRIMINATOR.len() + RegistryPage::INIT_SPACE,
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RegisterPool<'info> {
+    // Pools created before the factory existed are registered under the fee tier they were
+    // migrated with. Pools created since are already registered, so their pair account can't
+    // be created again
+    pub fn register(&mut self, bumps: RegisterPoolBumps) -> Result<()> {
+        let registry_index = self.registry.register(
+            &mut self.registry_page,
+            bumps.registry_page,
+            self.config.key(),
+        )?;
+        let (mint_a, mint_b) = Pair::canonical(self.config.mint_x, self.config.mint_y);
+        self.pair.set_inner(Pair {
+            config: self.config.key(),
+            mint_a,
+            mint_b,
+            fee_tier: self.config.fee_tier,
+            curve_type: self.config.curve_type,
+            registry_index,
+            bump: bumps.pair,
+        });
+        self.config.registered = true;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::AmmError,
+    events::ReferrerSet,
+    state::{Config, Referrer},
+};
+
+#[derive(Accounts)]
+pub struct SetReferrer<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    /// CHECK: Only used as the owner of the token accounts the referral is paid to
+    pub wallet: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"referrer", config.key().as_ref(), wallet.key().as_ref()],
+        bump,
+        space = Referrer::DISCRIMINATOR.len() + Referrer::INIT_SPACE,
+    )]
+    pub referrer: Account<'info, Referrer>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetReferrer<'info> {
+    // Registers `wallet` as a referrer of the pool, or updates its share. 0 turns it off
+    pub fn set_referrer(&mut self, referral_fee: u16, bumps: SetReferrerBumps) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(
+            referral_fee <= self.config.max_referral_fee,
+            AmmError::InvalidReferralFee
+        );
+
+        self.referrer.set_inner(Referrer {
+            config: self.config.key(),
+            wallet: self.wallet.key(),
+            referral_fee,
+            bump: bumps.referrer,
+        });
+
+        emit!(ReferrerSet {
+            config: self.config.key(),
+            wallet: self.wallet.key(),
+            referral_fee,
+        });
+
+        Ok(())
+    }
+}
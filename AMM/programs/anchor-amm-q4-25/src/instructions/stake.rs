@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::{
+    errors::AmmError,
+    state::{Farm, Staker},
+};
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint_lp,
+        seeds = [b"farm", farm.config.as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"staker", farm.key().as_ref(), user.key().as_ref()],
+        bump,
+        space = Staker::DISCRIMINATOR.len() + Staker::INIT_SPACE,
+    )]
+    pub staker: Account<'info, Staker>,
+    #[account(
+        mut,
+        token::mint = mint_lp,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Stake<'info> {
+    pub fn stake(&mut self, amount: u64, bumps: StakeBumps) -> Result<()> {
+        require!(amount != 0, AmmError::InvalidAmount);
+
+        if self.staker.farm == Pubkey::default() {
+            self.staker.set_inner(Staker {
+                farm: self.farm.key(),
+                owner: self.user.key(),
+                amount: 0,
+                reward_debt: 0,
+                rewards_owed: 0,
+                bump: bumps.staker,
+            });
+        }
+
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+
+        let staked = self
+            .staker
+            .amount
+            .checked_add(amount)
+            .ok_or(AmmError::Overflow)?;
+        self.staker.restake(&mut self.farm, staked)?;
+
+        let cpi_accounts = TransferChecked {
+            from: self.user_lp.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            to: self.stake_vault.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(ctx, amount, self.mint_lp.decimals)
+    }
+}
@@ -1,130 +1,387 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{transfer, Mint, Token, TokenAccount, Transfer},
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
-use constant_product_curve::ConstantProduct;
 
-use crate::{errors::AmmError, state::Config};
+use crate::{
+    errors::AmmError,
+    events::{ReferralPaid, Swapped},
+    pool::{Pool, SwapAmounts},
+    state::{Config, Oracle, Referrer, PAUSE_SWAP},
+    token,
+};
 
 #[derive(Accounts)]
 pub struct Swap<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
     #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
-        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
-        bump = config.config_bump,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Account<'info, Oracle>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_x,
         associated_token::authority = user,
+        associated_token::token_program = token_program_x,
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_y,
         associated_token::authority = user,
+        associated_token::token_program = token_program_y,
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+    /// Integrator registered with the pool that routed the swap, paid a share of its fee. It
+    /// can't refer its own swaps
+    #[account(
+        seeds = [b"referrer", config.key().as_ref(), referrer.wallet.as_ref()],
+        bump = referrer.bump,
+        has_one = config @ AmmError::InvalidReferrer,
+        constraint = referrer.wallet != user.key() @ AmmError::InvalidReferrer,
     )]
-    pub user_y: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub referrer: Option<Account<'info, Referrer>>,
+    /// Referrer's token account of the swap input, receiving the referral. Required along
+    /// with `referrer`
+    #[account(mut)]
+    pub referrer_token: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Ephemeral wSOL account, at seeds `[b"native_sol", user]`, through which native SOL is
+    /// paid or received in place of `user_x` or `user_y`. It's created and closed
+    /// within the instruction
+    /// CHECK: the address is checked when it's created
+    #[account(mut)]
+    pub native_sol: Option<UncheckedAccount<'info>>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 impl<'info> Swap<'info> {
-    pub fn swap(&mut self, is_x: bool, amount: u64, min: u64) -> Result<()> {
-        require!(amount != 0, AmmError::InvalidAmount);
-
-        let amount_out = match is_x {
-            true => ConstantProduct::delta_y_from_x_swap_amount(
-                self.vault_x.amount,
-                self.vault_y.amount,
-                amount,
-            ).unwrap(),
-            false => ConstantProduct::delta_x_from_y_swap_amount(
-                self.vault_x.amount,
-                self.vault_y.amount,
-                amount,
-            ).unwrap(),
+    pub fn swap(&mut self, is_x: bool, amount: u64, min: u64, expiration: i64) -> Result<()> {
+        self.check_swappable(expiration)?;
+        self.update_oracle()?;
+
+        let amounts = self.pool().swap_exact_in(is_x, amount, min)?;
+
+        self.settle(is_x, amounts)
+    }
+
+    pub fn swap_exact_out(
+        &mut self,
+        is_x: bool,
+        amount_out: u64,
+        max_amount_in: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        self.check_swappable(expiration)?;
+        self.update_oracle()?;
+
+        let amounts = self
+            .pool()
+            .swap_exact_out(is_x, amount_out, max_amount_in)?;
+
+        self.settle(is_x, amounts)
+    }
+
+    fn pool(&self) -> Pool<'_, 'info> {
+        Pool {
+            config: &self.config,
+            mint_x: &self.mint_x,
+            mint_y: &self.mint_y,
+            vault_x: self.vault_x.amount,
+            vault_y: self.vault_y.amount,
+        }
+    }
+
+    fn check_swappable(&self, expiration: i64) -> Result<()> {
+        require!(
+            Clock::get()?.unix_timestamp <= expiration,
+            AmmError::OfferExpired
+        );
+
+        self.config.check_not_paused(PAUSE_SWAP)
+    }
+
+    // Accumulates the price the reserves held until now, before the swap changes them
+    fn update_oracle(&mut self) -> Result<()> {
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        self.oracle
+            .update(reserve_x, reserve_y, Clock::get()?.unix_timestamp);
+
+        Ok(())
+    }
+
+    fn settle(&mut self, is_x: bool, amounts: SwapAmounts) -> Result<()> {
+        // The referral comes out of the LPs' part of the fee, so the trade's price is unchanged.
+        // Its share is the one the pool's authority registered the referrer with
+        let referral = match &self.referrer {
+            Some(referrer) => self.config.referral_share(
+                amounts.fee,
+                amounts.protocol_fee,
+                referrer.referral_fee,
+            )?,
+            None => 0,
         };
 
-        require!(amount_out >= min, AmmError::SlippageExceeded);
+        // Taken before the protocol's cut leaves the reserves, so it isn't counted as a move
+        let before = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        // The protocol's cut of the fee is tracked separately and excluded from the reserves
+        self.config
+            .accrue_protocol_fee(is_x, amounts.protocol_fee)?;
 
         // First deposit the input tokens from user to vault
-        self.deposit_tokens(is_x, amount)?;
+        self.deposit_tokens(is_x, amounts.amount_in)?;
         // Then withdraw the output tokens from vault to user
-        self.withdraw_tokens(!is_x, amount_out)?;
+        self.withdraw_tokens(!is_x, amounts.amount_out)?;
+
+        if referral != 0 {
+            self.pay_referral(is_x, amounts.fee, referral)?;
+        }
+
+        // The price movement feeds the dynamic fee of later swaps
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let after = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.config
+            .record_volatility(before, after, Clock::get()?.unix_timestamp);
+
+        let (mint_in, mint_out) = match is_x {
+            true => (self.mint_x.key(), self.mint_y.key()),
+            false => (self.mint_y.key(), self.mint_x.key()),
+        };
+        emit!(Swapped {
+            config: self.config.key(),
+            user: self.user.key(),
+            mint_in,
+            mint_out,
+            amount_in: amounts.amount_in,
+            amount_out: amounts.amount_out,
+            fee: amounts.fee,
+            protocol_fee: amounts.protocol_fee,
+            reserve_x: after.0,
+            reserve_y: after.1,
+        });
+
+        Ok(())
+    }
+
+    fn pay_referral(&self, is_x: bool, fee: u64, amount: u64) -> Result<()> {
+        let Some(referrer) = &self.referrer else {
+            return Ok(());
+        };
+        let referrer_token = self
+            .referrer_token
+            .as_ref()
+            .ok_or(ErrorCode::ConstraintAccountIsNone)?;
+
+        let (vault, mint, token_program) = match is_x {
+            true => (&self.vault_x, &self.mint_x, &self.token_program_x),
+            false => (&self.vault_y, &self.mint_y, &self.token_program_y),
+        };
+        require_keys_eq!(referrer_token.mint, mint.key(), AmmError::InvalidToken);
+        require_keys_eq!(
+            referrer_token.owner,
+            referrer.wallet,
+            AmmError::InvalidReferrer
+        );
+
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
+
+        let cpi_accounts = TransferChecked {
+            from: vault.to_account_info(),
+            mint: mint.to_account_info(),
+            to: referrer_token.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, mint.decimals)?;
+
+        emit!(ReferralPaid {
+            config: self.config.key(),
+            referrer: referrer_token.key(),
+            mint: mint.key(),
+            fee,
+            amount,
+        });
+
         Ok(())
     }
 
     pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+        let (from, to, mint, token_program) = match is_x {
             true => (
-                self.user_x.to_account_info(),
-                self.vault_x.to_account_info(),
+                &self.user_x,
+                &self.vault_x,
+                &self.mint_x,
+                &self.token_program_x,
             ),
             false => (
-                self.user_y.to_account_info(),
-                self.vault_y.to_account_info(),
+                &self.user_y,
+                &self.vault_y,
+                &self.mint_y,
+                &self.token_program_y,
             ),
         };
 
-        let cpi_program = self.token_program.to_account_info();
+        // Native SOL is wrapped into the ephemeral account and sent from there instead
+        let native_sol = match &self.native_sol {
+            Some(native_sol) if token::is_native(mint) => {
+                token::open_native(
+                    &self.user,
+                    native_sol,
+                    mint,
+                    amount,
+                    token_program,
+                    &self.system_program,
+                )?;
+                Some(native_sol)
+            }
+            _ => None,
+        };
+        let from = match native_sol {
+            Some(native_sol) => native_sol.to_account_info(),
+            None => from.to_account_info(),
+        };
+
+        let cpi_program = token_program.to_account_info();
 
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
-            to,
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
             authority: self.user.to_account_info(),
         };
 
         let ctx = CpiContext::new(cpi_program, cpi_accounts);
 
-        transfer(ctx, amount)
+        transfer_checked(ctx, amount, mint.decimals)?;
+
+        match native_sol {
+            Some(native_sol) => token::close_native(&self.user, native_sol, token_program),
+            None => Ok(()),
+        }
     }
 
     pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+        let (from, to, mint, token_program) = match is_x {
             true => (
-                self.vault_x.to_account_info(),
-                self.user_x.to_account_info(),
+                &self.vault_x,
+                &self.user_x,
+                &self.mint_x,
+                &self.token_program_x,
             ),
             false => (
-                self.vault_y.to_account_info(),
-                self.user_y.to_account_info(),
+                &self.vault_y,
+                &self.user_y,
+                &self.mint_y,
+                &self.token_program_y,
             ),
         };
 
-        let config_seed = &self.config.seed.to_le_bytes();
-        let signer_seeds: &[&[&[u8]]] = &[&[b"config", config_seed.as_ref(), &[self.config.config_bump]]];
+        // Native SOL is received in the ephemeral account and unwrapped by closing it
+        let native_sol = match &self.native_sol {
+            Some(native_sol) if token::is_native(mint) => {
+                token::open_native(
+                    &self.user,
+                    native_sol,
+                    mint,
+                    0,
+                    token_program,
+                    &self.system_program,
+                )?;
+                Some(native_sol)
+            }
+            _ => None,
+        };
+        let to = match native_sol {
+            Some(native_sol) => native_sol.to_account_info(),
+            None => to.to_account_info(),
+        };
 
-        let cpi_program = self.token_program.to_account_info();
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
 
-        let cpi_accounts = Transfer {
-            from,
+        let cpi_program = token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
             to,
             authority: self.config.to_account_info(),
         };
 
         let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
 
-        transfer(ctx, amount)
+        transfer_checked(ctx, amount, mint.decimals)?;
+
+        match native_sol {
+            Some(native_sol) => token::close_native(&self.user, native_sol, token_program),
+            None => Ok(()),
+        }
     }
 }
@@ -0,0 +1,449 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    concentrated, dynamic_fee,
+    errors::AmmError,
+    events::Swapped,
+    state::{Config, CurveType, Oracle, TickArray, PAUSE_SWAP},
+    token,
+};
+
+#[derive(Accounts)]
+pub struct SwapConcentrated<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+        constraint = config.curve_type == CurveType::Concentrated @ AmmError::InvalidCurve,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Account<'info, Oracle>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_x,
+        associated_token::authority = user,
+        associated_token::token_program = token_program_x,
+    )]
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_y,
+        associated_token::authority = user,
+        associated_token::token_program = token_program_y,
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+    /// Ephemeral wSOL account, at seeds `[b"native_sol", user]`, through which native SOL is
+    /// paid or received in place of `user_x` or `user_y`. It's created and closed
+    /// within the instruction
+    /// CHECK: the address is checked when it's created
+    #[account(mut)]
+    pub native_sol: Option<UncheckedAccount<'info>>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> SwapConcentrated<'info> {
+    // Swaps across the pool's ticks. The tick arrays the price may move through are passed
+    // as remaining accounts in the swap's direction, starting with the one holding the current tick
+    pub fn swap(
+        &mut self,
+        accounts: &'info [AccountInfo<'info>],
+        is_x: bool,  // Whether the user sends token X, moving the price down, or token Y
+        amount: u64, // Amount of the input token that the user sends
+        min_amount_out: u64, // Minimum amount of the output token that the user wants to receive
+        expiration: i64, // Unix timestamp after which the swap must not execute
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(now <= expiration, AmmError::OfferExpired);
+
+        self.config.check_not_paused(PAUSE_SWAP)?;
+        require!(amount != 0, AmmError::InvalidAmount);
+
+        // Accumulate the price the pool held until now, before the swap moves it
+        self.oracle.update_sqrt_price(self.config.sqrt_price, now);
+
+        let fee_bps = self.config.current_fee()?;
+        let (mint_in, mint_out) = match is_x {
+            true => (&self.mint_x, &self.mint_y),
+            false => (&self.mint_y, &self.mint_x),
+        };
+        let received = token::amount_after_transfer_fee(mint_in, amount)?;
+
+        let mut tick_arrays = load_tick_arrays(&self.config, accounts, is_x)?;
+        let TickSwap {
+            amount_out,
+            fee,
+            protocol_fee,
+            sqrt_price,
+            tick_current,
+            liquidity,
+            fee_growth_global,
+        } = swap_across_ticks(&self.config, &mut tick_arrays, is_x, received, fee_bps)?;
+
+        require!(amount_out != 0, AmmError::InvalidAmount);
+        require!(
+            token::amount_after_transfer_fee(mint_out, amount_out)? >= min_amount_out,
+            AmmError::SlippageExceeded
+        );
+
+        // The price movement feeds the dynamic fee of later swaps
+        let price_move = dynamic_fee::sqrt_price_move(self.config.sqrt_price, sqrt_price);
+        self.config.record_price_move(price_move, now);
+
+        self.config.sqrt_price = sqrt_price;
+        self.config.tick_current = tick_current;
+        self.config.liquidity = liquidity;
+        (
+            self.config.fee_growth_global_x,
+            self.config.fee_growth_global_y,
+        ) = fee_growth_global;
+        self.config.accrue_protocol_fee(is_x, protocol_fee)?;
+
+        self.deposit_tokens(is_x, amount)?;
+        self.withdraw_tokens(!is_x, amount_out)?;
+
+        for array in &tick_arrays {
+            array.exit(&crate::ID)?;
+        }
+
+        self.emit_swapped(is_x, amount, amount_out, fee, protocol_fee)
+    }
+
+    fn emit_swapped(
+        &mut self,
+        is_x: bool,
+        amount_in: u64,
+        amount_out: u64,
+        fee: u64,
+        protocol_fee: u64,
+    ) -> Result<()> {
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        let (mint_in, mint_out) = match is_x {
+            true => (self.mint_x.key(), self.mint_y.key()),
+            false => (self.mint_y.key(), self.mint_x.key()),
+        };
+
+        emit!(Swapped {
+            config: self.config.key(),
+            user: self.user.key(),
+            mint_in,
+            mint_out,
+            amount_in,
+            amount_out,
+            fee,
+            protocol_fee,
+            reserve_x,
+            reserve_y,
+        });
+
+        Ok(())
+    }
+
+    pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, token_program) = match is_x {
+            true => (
+                &self.user_x,
+                &self.vault_x,
+                &self.mint_x,
+                &self.token_program_x,
+            ),
+            false => (
+                &self.user_y,
+                &self.vault_y,
+                &self.mint_y,
+                &self.token_program_y,
+            ),
+        };
+
+        // Native SOL is wrapped into the ephemeral account and sent from there instead
+        let native_sol = match &self.native_sol {
+            Some(native_sol) if token::is_native(mint) => {
+                token::open_native(
+                    &self.user,
+                    native_sol,
+                    mint,
+                    amount,
+                    token_program,
+                    &self.system_program,
+                )?;
+                Some(native_sol)
+            }
+            _ => None,
+        };
+        let from = match native_sol {
+            Some(native_sol) => native_sol.to_account_info(),
+            None => from.to_account_info(),
+        };
+
+        let cpi_program = token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer_checked(ctx, amount, mint.decimals)?;
+
+        match native_sol {
+            Some(native_sol) => token::close_native(&self.user, native_sol, token_program),
+            None => Ok(()),
+        }
+    }
+
+    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, token_program) = match is_x {
+            true => (
+                &self.vault_x,
+                &self.user_x,
+                &self.mint_x,
+                &self.token_program_x,
+            ),
+            false => (
+                &self.vault_y,
+                &self.user_y,
+                &self.mint_y,
+                &self.token_program_y,
+            ),
+        };
+
+        // Native SOL is received in the ephemeral account and unwrapped by closing it
+        let native_sol = match &self.native_sol {
+            Some(native_sol) if token::is_native(mint) => {
+                token::open_native(
+                    &self.user,
+                    native_sol,
+                    mint,
+                    0,
+                    token_program,
+                    &self.system_program,
+                )?;
+                Some(native_sol)
+            }
+            _ => None,
+        };
+        let to = match native_sol {
+            Some(native_sol) => native_sol.to_account_info(),
+            None => to.to_account_info(),
+        };
+
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
+
+        let cpi_program = token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to,
+            authority: self.config.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(ctx, amount, mint.decimals)?;
+
+        match native_sol {
+            Some(native_sol) => token::close_native(&self.user, native_sol, token_program),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Amounts of a swap across a concentrated pool's ticks, and the state it leaves the pool in
+pub struct TickSwap {
+    pub amount_out: u64,
+    pub fee: u64,
+    pub protocol_fee: u64,
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+    pub liquidity: u64,
+    pub fee_growth_global: (u128, u128),
+}
+
+/// Tick arrays of the pool, each following the previous one in the swap's direction
+pub fn load_tick_arrays<'info>(
+    config: &Account<'info, Config>,
+    accounts: &'info [AccountInfo<'info>],
+    is_x: bool,
+) -> Result<Vec<Account<'info, TickArray>>> {
+    let span = TickArray::span(config.tick_spacing);
+    let mut start_tick = TickArray::start_tick_for(config.tick_current, config.tick_spacing);
+
+    accounts
+        .iter()
+        .map(|info| {
+            let array = Account::<TickArray>::try_from(info)?;
+            require_keys_eq!(array.config, config.key(), AmmError::InvalidTickArray);
+            require!(
+                array.start_tick == start_tick && array.tick_spacing == config.tick_spacing,
+                AmmError::InvalidTickArray
+            );
+
+            start_tick += match is_x {
+                true => -span,
+                false => span,
+            };
+
+            Ok(array)
+        })
+        .collect()
+}
+
+/// Swaps `received` of the input token across the pool's ticks. Crossed ticks only change
+/// in `tick_arrays` and the pool is left as is, so a quote can run it without writing
+pub fn swap_across_ticks(
+    config: &Config,
+    tick_arrays: &mut [Account<TickArray>],
+    is_x: bool,
+    received: u64, // Input that reaches the vault, net of any transfer fee
+    fee_bps: u16,
+) -> Result<TickSwap> {
+    let spacing = config.tick_spacing;
+    let span = TickArray::span(spacing);
+    let (min_sqrt_price, max_sqrt_price) = concentrated::sqrt_price_bounds()?;
+
+    let mut sqrt_price = config.sqrt_price;
+    let mut tick_current = config.tick_current;
+    let mut liquidity = config.liquidity;
+    let mut fee_growth_global = config.fee_growth_global();
+
+    let mut remaining = received;
+    let mut amount_out = 0u64;
+    let mut fee = 0u64;
+    let mut protocol_fee = 0u64;
+    let mut array_index = 0;
+
+    while remaining != 0 {
+        require!(
+            match is_x {
+                true => sqrt_price > min_sqrt_price,
+                false => sqrt_price < max_sqrt_price,
+            },
+            AmmError::NoLiquidityInPool
+        );
+
+        let array = tick_arrays
+            .get_mut(array_index)
+            .ok_or(AmmError::InvalidTickArray)?;
+
+        // Without an initialized tick left in the array, the step ends at its edge
+        let (tick_next, initialized) = match array.next_initialized(tick_current, spacing, is_x) {
+            Some(tick) => (tick, true),
+            None if is_x => (array.start_tick, false),
+            None => (array.start_tick + span, false),
+        };
+        let tick_next = tick_next.clamp(concentrated::MIN_TICK, concentrated::MAX_TICK);
+        let sqrt_price_target = concentrated::sqrt_price_at_tick(tick_next)?;
+
+        let step =
+            concentrated::swap_step(sqrt_price, sqrt_price_target, liquidity, remaining, fee_bps)?;
+        remaining -= step.amount_in + step.fee;
+        amount_out = amount_out
+            .checked_add(step.amount_out)
+            .ok_or(AmmError::Overflow)?;
+
+        // Fees go to the liquidity in range, net of the protocol's share, all of it
+        // to the protocol when no liquidity is in range
+        let step_protocol_fee = match liquidity {
+            0 => step.fee,
+            _ => config.protocol_share(step.fee)?,
+        };
+        if liquidity != 0 {
+            let growth = (((step.fee - step_protocol_fee) as u128) << 64) / liquidity as u128;
+            match is_x {
+                true => fee_growth_global.0 = fee_growth_global.0.wrapping_add(growth),
+                false => fee_growth_global.1 = fee_growth_global.1.wrapping_add(growth),
+            }
+        }
+        fee += step.fee;
+        protocol_fee += step_protocol_fee;
+
+        sqrt_price = step.sqrt_price;
+        if sqrt_price != sqrt_price_target {
+            // The input ran out between two ticks
+            tick_current = concentrated::tick_at_sqrt_price(sqrt_price)?;
+            continue;
+        }
+
+        if initialized {
+            let liquidity_net = array.tick_mut(tick_next, spacing)?.cross(fee_growth_global);
+            let delta = match is_x {
+                true => liquidity_net.checked_neg().ok_or(AmmError::Overflow)?,
+                false => liquidity_net,
+            };
+            liquidity = concentrated::add_liquidity_delta(liquidity, delta)?;
+        } else {
+            array_index += 1;
+        }
+
+        // Moving down, the price sits on the crossed tick but below the liquidity it starts
+        tick_current = match is_x {
+            true => tick_next - 1,
+            false => tick_next,
+        };
+    }
+
+    Ok(TickSwap {
+        amount_out,
+        fee,
+        protocol_fee,
+        sqrt_price,
+        tick_current,
+        liquidity,
+        fee_growth_global,
+    })
+}
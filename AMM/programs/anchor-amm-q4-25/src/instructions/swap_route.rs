@@ -0,0 +1,253 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    errors::AmmError,
+    events::Swapped,
+    state::{Config, Oracle, PAUSE_SWAP},
+    token,
+};
+
+/// Remaining accounts per hop: `config`, `oracle`, `vault_in`, `vault_out`, `mint_out` and
+/// the token program of `mint_out`
+pub const ACCOUNTS_PER_HOP: usize = 6;
+
+#[derive(Accounts)]
+pub struct SwapRoute<'info> {
+    pub user: Signer<'info>,
+    #[account(
+        mint::token_program = token_program_in,
+    )]
+    pub mint_in: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = mint_in,
+        token::authority = user,
+        token::token_program = token_program_in,
+    )]
+    pub user_in: InterfaceAccount<'info, TokenAccount>,
+    /// Owned by the token program of the last hop's `mint_out`
+    #[account(mut)]
+    pub user_out: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_in: Interface<'info, TokenInterface>,
+}
+
+struct Hop<'info> {
+    config: Account<'info, Config>,
+    oracle: Account<'info, Oracle>,
+    vault_in: InterfaceAccount<'info, TokenAccount>,
+    vault_out: InterfaceAccount<'info, TokenAccount>,
+    mint_out: InterfaceAccount<'info, Mint>,
+    token_program_out: Interface<'info, TokenInterface>,
+    amount_out: u64,
+}
+
+impl<'info> SwapRoute<'info> {
+    pub fn swap_route(
+        &mut self,
+        accounts: &'info [AccountInfo<'info>],
+        amount_in: u64,
+        min_amount_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        require!(
+            Clock::get()?.unix_timestamp <= expiration,
+            AmmError::OfferExpired
+        );
+        require!(amount_in != 0, AmmError::InvalidAmount);
+        require!(
+            !accounts.is_empty() && accounts.len() % ACCOUNTS_PER_HOP == 0,
+            AmmError::InvalidRoute
+        );
+
+        // Price every hop against the balances before any transfer, so a pool may
+        // only appear once in the route
+        let mut route: Vec<Hop<'info>> = Vec::with_capacity(accounts.len() / ACCOUNTS_PER_HOP);
+        let mut mint = self.mint_in.key();
+        let mut token_program = self.token_program_in.key();
+        let mut amount = token::amount_after_transfer_fee(&self.mint_in, amount_in)?;
+
+        for hop_accounts in accounts.chunks(ACCOUNTS_PER_HOP) {
+            let mut hop = Hop::load(hop_accounts, mint, &token_program)?;
+            require!(
+                route.iter().all(|h| h.config.key() != hop.config.key()),
+                AmmError::InvalidRoute
+            );
+
+            hop.swap(self.user.key(), amount)?;
+
+            // The next hop, or the user, receives the output net of any transfer fee
+            mint = hop.mint_out.key();
+            token_program = hop.token_program_out.key();
+            amount = token::amount_after_transfer_fee(&hop.mint_out, hop.amount_out)?;
+            route.push(hop);
+        }
+
+        require_keys_eq!(mint, self.user_out.mint, AmmError::InvalidToken);
+        require!(amount >= min_amount_out, AmmError::SlippageExceeded);
+
+        // Intermediate amounts go straight from one pool's vault into the next one
+        let ctx = CpiContext::new(
+            self.token_program_in.to_account_info(),
+            TransferChecked {
+                from: self.user_in.to_account_info(),
+                mint: self.mint_in.to_account_info(),
+                to: route[0].vault_in.to_account_info(),
+                authority: self.user.to_account_info(),
+            },
+        );
+        transfer_checked(ctx, amount_in, self.mint_in.decimals)?;
+
+        for (i, hop) in route.iter().enumerate() {
+            let to = match route.get(i + 1) {
+                Some(next) => next.vault_in.to_account_info(),
+                None => self.user_out.to_account_info(),
+            };
+            hop.withdraw_tokens(to)?;
+            hop.config.exit(&crate::ID)?;
+            hop.oracle.exit(&crate::ID)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'info> Hop<'info> {
+    fn load(
+        accounts: &'info [AccountInfo<'info>],
+        mint_in: Pubkey,
+        token_program_in: &Pubkey,
+    ) -> Result<Self> {
+        let config = Account::<Config>::try_from(&accounts[0])?;
+        let oracle = Account::<Oracle>::try_from(&accounts[1])?;
+        let vault_in = InterfaceAccount::<TokenAccount>::try_from(&accounts[2])?;
+        let vault_out = InterfaceAccount::<TokenAccount>::try_from(&accounts[3])?;
+        let mint_out = InterfaceAccount::<Mint>::try_from(&accounts[4])?;
+        let token_program_out = Interface::<TokenInterface>::try_from(&accounts[5])?;
+
+        require!(config.is_pda(&config.key()), ErrorCode::ConstraintSeeds);
+        require_keys_eq!(oracle.config, config.key(), AmmError::InvalidRoute);
+
+        let expected_mint_out = match mint_in {
+            mint if mint == config.mint_x => config.mint_y,
+            mint if mint == config.mint_y => config.mint_x,
+            _ => return err!(AmmError::InvalidToken),
+        };
+        require_keys_eq!(mint_out.key(), expected_mint_out, AmmError::InvalidToken);
+        require_keys_eq!(
+            *mint_out.to_account_info().owner,
+            token_program_out.key(),
+            AmmError::InvalidToken
+        );
+
+        require_keys_eq!(
+            vault_in.key(),
+            get_associated_token_address_with_program_id(&config.key(), &mint_in, token_program_in,),
+            AmmError::InvalidToken
+        );
+        require_keys_eq!(
+            vault_out.key(),
+            get_associated_token_address_with_program_id(
+                &config.key(),
+                &expected_mint_out,
+                &token_program_out.key(),
+            ),
+            AmmError::InvalidToken
+        );
+
+        Ok(Self {
+            config,
+            oracle,
+            vault_in,
+            vault_out,
+            mint_out,
+            token_program_out,
+            amount_out: 0,
+        })
+    }
+
+    fn swap(&mut self, user: Pubkey, amount: u64) -> Result<()> {
+        self.config.check_not_paused(PAUSE_SWAP)?;
+
+        let is_x = self.vault_in.mint == self.config.mint_x;
+        let (vault_x, vault_y) = match is_x {
+            true => (self.vault_in.amount, self.vault_out.amount),
+            false => (self.vault_out.amount, self.vault_in.amount),
+        };
+
+        let now = Clock::get()?.unix_timestamp;
+        let (reserve_x, reserve_y) = self.config.reserves(vault_x, vault_y)?;
+        self.oracle.update(reserve_x, reserve_y, now);
+
+        let quote = self.config.quote_exact_in(is_x, amount, vault_x, vault_y)?;
+        require!(quote.amount_out != 0, AmmError::InvalidAmount);
+
+        let protocol_fee = self.config.protocol_share(quote.fee)?;
+        self.config.accrue_protocol_fee(is_x, protocol_fee)?;
+
+        // The reserves gain the net input and lose the output, fed to the dynamic fee
+        let (reserve_in, reserve_out) = match is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+        let reserve_in = reserve_in
+            .checked_add(amount - protocol_fee)
+            .ok_or(AmmError::Overflow)?;
+        let reserve_out = reserve_out
+            .checked_sub(quote.amount_out)
+            .ok_or(AmmError::Underflow)?;
+        let after = match is_x {
+            true => (reserve_in, reserve_out),
+            false => (reserve_out, reserve_in),
+        };
+        self.config
+            .record_volatility((reserve_x, reserve_y), after, now);
+
+        emit!(Swapped {
+            config: self.config.key(),
+            user,
+            mint_in: self.vault_in.mint,
+            mint_out: self.mint_out.key(),
+            amount_in: amount,
+            amount_out: quote.amount_out,
+            fee: quote.fee,
+            protocol_fee,
+            reserve_x: after.0,
+            reserve_y: after.1,
+        });
+
+        self.amount_out = quote.amount_out;
+
+        Ok(())
+    }
+
+    fn withdraw_tokens(&self, to: AccountInfo<'info>) -> Result<()> {
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
+
+        let cpi_accounts = TransferChecked {
+            from: self.vault_out.to_account_info(),
+            mint: self.mint_out.to_account_info(),
+            to,
+            authority: self.config.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program_out.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, self.amount_out, self.mint_out.decimals)
+    }
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Config, Oracle, TwapPrice};
+
+#[derive(Accounts)]
+pub struct Twap<'info> {
+    #[account(
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Account<'info, Oracle>,
+}
+
+impl<'info> Twap<'info> {
+    // Read only, the average runs up to the oracle's last update and ignores the live
+    // reserves, which a transaction could move right before reading it
+    pub fn twap(&self, window: i64) -> Result<TwapPrice> {
+        self.oracle.twap(window)
+    }
+}
@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::{
+    errors::AmmError,
+    state::{Farm, Staker},
+};
+
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    pub user: Signer<'info>,
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint_lp,
+        seeds = [b"farm", farm.config.as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+    #[account(
+        mut,
+        has_one = farm,
+        seeds = [b"staker", farm.key().as_ref(), user.key().as_ref()],
+        bump = staker.bump,
+    )]
+    pub staker: Account<'info, Staker>,
+    #[account(
+        mut,
+        token::mint = mint_lp,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> Unstake<'info> {
+    // The rewards accrued so far stay owed to the staker until they `harvest`
+    pub fn unstake(&mut self, amount: u64) -> Result<()> {
+        require!(amount != 0, AmmError::InvalidAmount);
+
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+
+        let staked = self
+            .staker
+            .amount
+            .checked_sub(amount)
+            .ok_or(AmmError::InsufficientBalance)?;
+        self.staker.restake(&mut self.farm, staked)?;
+
+        let farm_config = self.farm.config;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"farm", farm_config.as_ref(), &[self.farm.bump]]];
+
+        let cpi_accounts = TransferChecked {
+            from: self.stake_vault.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            to: self.user_lp.to_account_info(),
+            authority: self.farm.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, self.mint_lp.decimals)
+    }
+}
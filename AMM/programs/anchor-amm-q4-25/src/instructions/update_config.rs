@@ -0,0 +1,176 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::AmmError,
+    events::ConfigUpdated,
+    stable_swap::{MAX_AMP, MAX_AMP_CHANGE, MIN_AMP, MIN_RAMP_DURATION},
+    state::{Config, CurveType, MAX_BPS, PAUSE_ALL},
+};
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    pub fn lock(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        self.config.paused = PAUSE_ALL;
+
+        self.emit_update();
+
+        Ok(())
+    }
+
+    pub fn unlock(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        self.config.paused = 0;
+
+        self.emit_update();
+
+        Ok(())
+    }
+
+    // e.g. `PAUSE_SWAP | PAUSE_DEPOSIT` leaves the pool in withdraw-only mode
+    pub fn set_paused(&mut self, paused: u8) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(paused & !PAUSE_ALL == 0, AmmError::InvalidPauseFlags);
+        self.config.paused = paused;
+
+        self.emit_update();
+
+        Ok(())
+    }
+
+    pub fn update_fee(&mut self, fee: u16, protocol_fee: u16) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(fee < MAX_BPS, AmmError::InvalidFee);
+        require!(protocol_fee <= MAX_BPS, AmmError::InvalidFee);
+
+        self.config.fee = fee;
+        self.config.protocol_fee = protocol_fee;
+
+        self.emit_update();
+
+        Ok(())
+    }
+
+    // 0 turns referrals off
+    pub fn set_max_referral_fee(&mut self, max_referral_fee: u16) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(max_referral_fee <= MAX_BPS, AmmError::InvalidFee);
+
+        self.config.max_referral_fee = max_referral_fee;
+
+        self.emit_update();
+
+        Ok(())
+    }
+
+    // While enabled, the swap fee moves between `min_fee` and `max_fee` with the recent
+    // volatility, and `fee` only applies again once it's disabled
+    pub fn set_dynamic_fee(&mut self, enabled: bool, min_fee: u16, max_fee: u16) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(
+            min_fee <= max_fee && max_fee < MAX_BPS,
+            AmmError::InvalidFee
+        );
+
+        self.config.dynamic_fee = enabled;
+        self.config.min_fee = min_fee;
+        self.config.max_fee = max_fee;
+
+        self.emit_update();
+
+        Ok(())
+    }
+
+    // Moves a StableSwap pool's amplification linearly to `target_amp` by `ramp_stop_ts`,
+    // by at most MAX_AMP_CHANGE times and over at least MIN_RAMP_DURATION
+    pub fn ramp_amp(&mut self, target_amp: u64, ramp_stop_ts: i64) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(
+            self.config.curve_type == CurveType::StableSwap,
+            AmmError::InvalidCurve
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= self.config.ramp_start_ts.saturating_add(MIN_RAMP_DURATION)
+                && ramp_stop_ts >= now.saturating_add(MIN_RAMP_DURATION),
+            AmmError::InvalidRamp
+        );
+        require!(
+            (MIN_AMP..=MAX_AMP).contains(&target_amp),
+            AmmError::InvalidAmp
+        );
+
+        let current_amp = self.config.amp(now);
+        require!(
+            target_amp <= current_amp.saturating_mul(MAX_AMP_CHANGE)
+                && current_amp <= target_amp.saturating_mul(MAX_AMP_CHANGE),
+            AmmError::InvalidAmp
+        );
+
+        self.config.initial_amp = current_amp;
+        self.config.target_amp = target_amp;
+        self.config.ramp_start_ts = now;
+        self.config.ramp_stop_ts = ramp_stop_ts;
+
+        self.emit_update();
+
+        Ok(())
+    }
+
+    // Freezes the amplification at its current value
+    pub fn stop_ramp_amp(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(
+            self.config.curve_type == CurveType::StableSwap,
+            AmmError::InvalidCurve
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let current_amp = self.config.amp(now);
+
+        self.config.initial_amp = current_amp;
+        self.config.target_amp = current_amp;
+        self.config.ramp_start_ts = now;
+        self.config.ramp_stop_ts = now;
+
+        self.emit_update();
+
+        Ok(())
+    }
+
+    // The new authority only takes over once it signs `accept_authority`
+    pub fn propose_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        self.config.pending_authority = Some(new_authority);
+
+        self.emit_update();
+
+        Ok(())
+    }
+
+    // Leaves the pool without an authority, so its settings can never change again
+    pub fn renounce_authority(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        self.config.authority = None;
+        self.config.pending_authority = None;
+
+        self.emit_update();
+
+        Ok(())
+    }
+
+    // Reports the pool's settings as the admin instruction left them
+    fn emit_update(&self) {
+        emit!(ConfigUpdated::new(self.config.key(), &self.config));
+    }
+}
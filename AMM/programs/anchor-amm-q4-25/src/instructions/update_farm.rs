@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::AmmError,
+    state::{Config, Farm},
+};
+
+#[derive(Accounts)]
+pub struct UpdateFarm<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        has_one = config,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+}
+
+impl<'info> UpdateFarm<'info> {
+    // Rewards accrued so far keep the old rate, the new one applies from now until `end_ts`
+    pub fn set_emission(&mut self, reward_per_second: u64, end_ts: i64) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(end_ts >= now, AmmError::InvalidEmission);
+
+        self.farm.update(now)?;
+        self.farm.reward_per_second = reward_per_second;
+        self.farm.end_ts = end_ts;
+        // No emission is owed for any gap after a previous schedule ended
+        self.farm.last_update_ts = now;
+
+        Ok(())
+    }
+}
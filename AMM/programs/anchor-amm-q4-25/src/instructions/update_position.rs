@@ -0,0 +1,354 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::{
+    concentrated,
+    errors::AmmError,
+    events::{PositionFeesCollected, PositionUpdated},
+    state::{Config, CurveType, Position, TickArray, PAUSE_DEPOSIT, PAUSE_WITHDRAW},
+    token,
+};
+
+#[derive(Accounts)]
+pub struct UpdatePosition<'info> {
+    pub owner: Signer<'info>,
+    #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
+        constraint = config.curve_type == CurveType::Concentrated @ AmmError::InvalidCurve,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = config,
+    )]
+    pub position: Account<'info, Position>,
+    /// Tick array holding the position's lower tick
+    #[account(
+        mut,
+        has_one = config @ AmmError::InvalidTickArray,
+        constraint = tick_array_lower.tick_spacing == config.tick_spacing @ AmmError::InvalidTickArray,
+    )]
+    pub tick_array_lower: Account<'info, TickArray>,
+    /// Tick array holding the position's upper tick, omitted when it's the lower one's
+    #[account(
+        mut,
+        has_one = config @ AmmError::InvalidTickArray,
+        constraint = tick_array_upper.tick_spacing == config.tick_spacing @ AmmError::InvalidTickArray,
+    )]
+    pub tick_array_upper: Option<Account<'info, TickArray>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program_x,
+    )]
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program_y,
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> UpdatePosition<'info> {
+    pub fn increase_liquidity(
+        &mut self,
+        liquidity: u64,  // Liquidity to add to the position
+        max_x: u64,      // Maximum amount of token X that the owner is willing to send
+        max_y: u64,      // Maximum amount of token Y that the owner is willing to send
+        expiration: i64, // Unix timestamp after which the deposit must not execute
+    ) -> Result<()> {
+        require!(
+            Clock::get()?.unix_timestamp <= expiration,
+            AmmError::OfferExpired
+        );
+
+        self.config.check_not_paused(PAUSE_DEPOSIT)?;
+        require!(liquidity != 0, AmmError::InvalidAmount);
+
+        let delta = i64::try_from(liquidity).map_err(|_| AmmError::Overflow)?;
+        let (amount_x, amount_y) = self.modify(delta)?;
+
+        // The vaults must receive the full amounts backing the liquidity
+        let send_x = token::amount_before_transfer_fee(&self.mint_x, amount_x)?;
+        let send_y = token::amount_before_transfer_fee(&self.mint_y, amount_y)?;
+        require!(
+            send_x <= max_x && send_y <= max_y,
+            AmmError::SlippageExceeded
+        );
+
+        self.deposit_tokens(true, send_x)?;
+        self.deposit_tokens(false, send_y)?;
+
+        self.emit_position_updated(delta, amount_x, amount_y);
+
+        Ok(())
+    }
+
+    pub fn decrease_liquidity(
+        &mut self,
+        liquidity: u64,  // Liquidity to remove from the position
+        min_x: u64,      // Minimum amount of token X that the owner wants to receive
+        min_y: u64,      // Minimum amount of token Y that the owner wants to receive
+        expiration: i64, // Unix timestamp after which the withdrawal must not execute
+    ) -> Result<()> {
+        require!(
+            Clock::get()?.unix_timestamp <= expiration,
+            AmmError::OfferExpired
+        );
+
+        self.config.check_not_paused(PAUSE_WITHDRAW)?;
+        require!(liquidity != 0, AmmError::InvalidAmount);
+
+        let delta = i64::try_from(liquidity)
+            .map_err(|_| AmmError::Overflow)?
+            .checked_neg()
+            .ok_or(AmmError::Overflow)?;
+        let (amount_x, amount_y) = self.modify(delta)?;
+        require!(
+            amount_x >= min_x && amount_y >= min_y,
+            AmmError::SlippageExceeded
+        );
+
+        self.withdraw_tokens(true, amount_x)?;
+        self.withdraw_tokens(false, amount_y)?;
+
+        self.emit_position_updated(delta, amount_x, amount_y);
+
+        Ok(())
+    }
+
+    // Pays out the fees the position accrued
+    pub fn collect_fees(&mut self) -> Result<()> {
+        self.config.check_not_paused(PAUSE_WITHDRAW)?;
+
+        self.modify(0)?;
+
+        let (amount_x, amount_y) = (self.position.fees_owed_x, self.position.fees_owed_y);
+        self.position.fees_owed_x = 0;
+        self.position.fees_owed_y = 0;
+
+        self.withdraw_tokens(true, amount_x)?;
+        self.withdraw_tokens(false, amount_y)?;
+
+        emit!(PositionFeesCollected {
+            config: self.config.key(),
+            position: self.position.key(),
+            owner: self.owner.key(),
+            amount_x,
+            amount_y,
+        });
+
+        Ok(())
+    }
+
+    // Applies `delta` of liquidity to the position, its ticks and the pool's in-range
+    // liquidity, and returns the amounts of X and Y it's backed by, rounded in the pool's favour
+    fn modify(&mut self, delta: i64) -> Result<(u64, u64)> {
+        if let Some(upper) = &self.tick_array_upper {
+            require_keys_neq!(
+                upper.key(),
+                self.tick_array_lower.key(),
+                AmmError::InvalidTickArray
+            );
+        }
+
+        let (tick_lower, tick_upper) = (self.position.tick_lower, self.position.tick_upper);
+        let spacing = self.config.tick_spacing;
+        let tick_current = self.config.tick_current;
+        let fee_growth_global = self.config.fee_growth_global();
+
+        if delta != 0 {
+            self.tick_array_mut(tick_lower)
+                .tick_mut(tick_lower, spacing)?
+                .update(tick_lower, tick_current, fee_growth_global, delta, false)?;
+            self.tick_array_mut(tick_upper)
+                .tick_mut(tick_upper, spacing)?
+                .update(tick_upper, tick_current, fee_growth_global, delta, true)?;
+        }
+
+        let lower = self
+            .tick_array_mut(tick_lower)
+            .tick(tick_lower, spacing)?
+            .clone();
+        let upper = self
+            .tick_array_mut(tick_upper)
+            .tick(tick_upper, spacing)?
+            .clone();
+        let fee_growth_inside = self
+            .config
+            .fee_growth_inside(tick_lower, &lower, tick_upper, &upper);
+        self.position.update(delta, fee_growth_inside)?;
+
+        if delta < 0 {
+            self.tick_array_mut(tick_lower)
+                .tick_mut(tick_lower, spacing)?
+                .clear_if_unused();
+            self.tick_array_mut(tick_upper)
+                .tick_mut(tick_upper, spacing)?
+                .clear_if_unused();
+        }
+
+        let in_range = (tick_lower..tick_upper).contains(&tick_current);
+        if in_range {
+            self.config.liquidity =
+                concentrated::add_liquidity_delta(self.config.liquidity, delta)?;
+        }
+
+        let liquidity = delta.unsigned_abs();
+        let round_up = delta > 0;
+        let sqrt_lower = concentrated::sqrt_price_at_tick(tick_lower)?;
+        let sqrt_upper = concentrated::sqrt_price_at_tick(tick_upper)?;
+        let sqrt_price = self.config.sqrt_price;
+
+        // Below the range the position only holds X, above it only Y
+        Ok(match tick_current {
+            _ if tick_current < tick_lower => (
+                concentrated::amount_x_delta(sqrt_lower, sqrt_upper, liquidity, round_up)?,
+                0,
+            ),
+            _ if !in_range => (
+                0,
+                concentrated::amount_y_delta(sqrt_lower, sqrt_upper, liquidity, round_up)?,
+            ),
+            _ => (
+                concentrated::amount_x_delta(sqrt_price, sqrt_upper, liquidity, round_up)?,
+                concentrated::amount_y_delta(sqrt_lower, sqrt_price, liquidity, round_up)?,
+            ),
+        })
+    }
+
+    // Tick array holding `tick`: the upper one when it's passed and covers the tick
+    fn tick_array_mut(&mut self, tick: i32) -> &mut TickArray {
+        let span = TickArray::span(self.config.tick_spacing);
+
+        match &mut self.tick_array_upper {
+            Some(upper) if (upper.start_tick..upper.start_tick + span).contains(&tick) => upper,
+            _ => &mut self.tick_array_lower,
+        }
+    }
+
+    fn emit_position_updated(&self, delta: i64, amount_x: u64, amount_y: u64) {
+        emit!(PositionUpdated {
+            config: self.config.key(),
+            position: self.position.key(),
+            owner: self.owner.key(),
+            tick_lower: self.position.tick_lower,
+            tick_upper: self.position.tick_upper,
+            liquidity_delta: delta,
+            amount_x,
+            amount_y,
+            liquidity: self.position.liquidity,
+        });
+    }
+
+    pub fn deposit_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let (from, to, mint, token_program) = match is_x {
+            true => (
+                &self.user_x,
+                &self.vault_x,
+                &self.mint_x,
+                &self.token_program_x,
+            ),
+            false => (
+                &self.user_y,
+                &self.vault_y,
+                &self.mint_y,
+                &self.token_program_y,
+            ),
+        };
+
+        let cpi_program = token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.owner.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer_checked(ctx, amount, mint.decimals)
+    }
+
+    pub fn withdraw_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let (from, to, mint, token_program) = match is_x {
+            true => (
+                &self.vault_x,
+                &self.user_x,
+                &self.mint_x,
+                &self.token_program_x,
+            ),
+            false => (
+                &self.vault_y,
+                &self.user_y,
+                &self.mint_y,
+                &self.token_program_y,
+            ),
+        };
+
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
+
+        let cpi_program = token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        transfer_checked(ctx, amount, mint.decimals)
+    }
+}
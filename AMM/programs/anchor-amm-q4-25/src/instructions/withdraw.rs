@@ -1,62 +1,99 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{burn, transfer, Burn, Mint, Token, TokenAccount, Transfer},
+    token_interface::{
+        burn, transfer_checked, Burn, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::{
+    curve,
+    errors::AmmError,
+    events::LiquidityRemoved,
+    pool::Pool,
+    state::{Config, Oracle, PAUSE_SWAP, PAUSE_WITHDRAW},
+    token,
 };
-use constant_product_curve::ConstantProduct;
-use crate::{errors::AmmError, state::Config};
 
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
     #[account(
+        mint::token_program = token_program_x,
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program_y,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
-        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
-        bump = config.config_bump,
+        constraint = config.is_pda(&config.key()) @ ErrorCode::ConstraintSeeds,
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.bump,
+    )]
+    pub oracle: Account<'info, Oracle>,
     #[account(
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_x,
         associated_token::authority = user,
+        associated_token::token_program = token_program_x,
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = user,
         associated_token::mint = mint_y,
         associated_token::authority = user,
+        associated_token::token_program = token_program_y,
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_lp,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
-    pub user_lp: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
+    /// Ephemeral wSOL account, at seeds `[b"native_sol", user]`, through which native SOL is
+    /// received in place of `user_x` or `user_y`. It's created and closed
+    /// within the instruction
+    /// CHECK: the address is checked when it's created
+    #[account(mut)]
+    pub native_sol: Option<UncheckedAccount<'info>>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    /// Token program of the LP mint
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
@@ -64,65 +101,223 @@ pub struct Withdraw<'info> {
 impl<'info> Withdraw<'info> {
     pub fn withdraw(
         &mut self,
-        amount: u64, // Amount of LP tokens that the user wants to "burn"
-        min_x: u64,  // Minimum amount of token X that the user wants to receive
-        min_y: u64,  // Minimum amount of token Y that the user wants to receive
+        amount: u64,     // Amount of LP tokens that the user wants to "burn"
+        min_x: u64,      // Minimum amount of token X that the user wants to receive
+        min_y: u64,      // Minimum amount of token Y that the user wants to receive
+        expiration: i64, // Unix timestamp after which the withdrawal must not execute
     ) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
+        require!(
+            Clock::get()?.unix_timestamp <= expiration,
+            AmmError::OfferExpired
+        );
+
+        self.config.check_not_paused(PAUSE_WITHDRAW)?;
+
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        // Accumulate the price the reserves held until now, before they change
+        self.oracle
+            .update(reserve_x, reserve_y, Clock::get()?.unix_timestamp);
+
+        let amounts = self
+            .pool()
+            .withdraw(self.mint_lp.supply, amount, min_x, min_y)?;
+
+        self.withdraw_tokens(true, amounts.amount_x)?;
+        self.withdraw_tokens(false, amounts.amount_y)?;
+        self.burn_lp_tokens(amount)?;
+
+        self.emit_liquidity_removed(amounts.amount_x, amounts.amount_y, amount)?;
+
+        Ok(())
+    }
+
+    pub fn withdraw_single(
+        &mut self,
+        is_x: bool,      // Whether the user receives token X or token Y
+        amount: u64,     // Amount of LP tokens that the user wants to "burn"
+        min_out: u64,    // Minimum amount of the token that the user wants to receive
+        expiration: i64, // Unix timestamp after which the withdrawal must not execute
+    ) -> Result<()> {
+        require!(
+            Clock::get()?.unix_timestamp <= expiration,
+            AmmError::OfferExpired
+        );
+
+        self.config.check_not_paused(PAUSE_WITHDRAW | PAUSE_SWAP)?;
         require!(amount != 0, AmmError::InvalidAmount);
 
-        let (x, y) = match self.mint_lp.supply == 0
-            && self.vault_x.amount == 0
-            && self.vault_y.amount == 0
-        {
-            true => (min_x, min_y),
-            false => {
-                let amounts = ConstantProduct::xy_withdraw_amounts_from_l(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
-                    self.mint_lp.supply,
-                    amount,
-                    self.mint_x.decimals.into(),
-                )
-                .unwrap();
-                (amounts.x, amounts.y)
-            }
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        // Accumulate the price the reserves held until now, before they change
+        self.oracle
+            .update(reserve_x, reserve_y, Clock::get()?.unix_timestamp);
+
+        let (x, y) = curve::withdraw_amounts(
+            reserve_x,
+            reserve_y,
+            self.config.lp_shares(self.mint_lp.supply)?,
+            amount,
+        )?;
+
+        // The other token's share is swapped through the pool, fees included, against
+        // the reserves left after the proportional withdrawal, and never leaves its vault
+        let (amount_out, amount_swapped) = match is_x {
+            true => (x, y),
+            false => (y, x),
+        };
+        let quote = self.config.quote_exact_in(
+            !is_x,
+            amount_swapped,
+            self.vault_x.amount - x,
+            self.vault_y.amount - y,
+        )?;
+        let protocol_fee = self.config.protocol_share(quote.fee)?;
+        self.config.accrue_protocol_fee(!is_x, protocol_fee)?;
+
+        let amount_out = amount_out
+            .checked_add(quote.amount_out)
+            .ok_or(AmmError::Overflow)?;
+
+        let mint_out = match is_x {
+            true => &self.mint_x,
+            false => &self.mint_y,
+        };
+        require!(
+            token::amount_after_transfer_fee(mint_out, amount_out)? >= min_out,
+            AmmError::SlippageExceeded
+        );
+
+        self.withdraw_tokens(is_x, amount_out)?;
+        self.burn_lp_tokens(amount)?;
+
+        // Like a swap, the internal swap's price movement feeds the dynamic fee. The reserves
+        // were taken before the protocol's cut left them, so it isn't counted as a move
+        self.record_volatility((reserve_x, reserve_y))?;
+
+        let (amount_x, amount_y) = match is_x {
+            true => (amount_out, 0),
+            false => (0, amount_out),
         };
+        self.emit_liquidity_removed(amount_x, amount_y, amount)?;
 
-        require!(x >= min_x && y >= min_y, AmmError::SlippageExceeded);
+        Ok(())
+    }
+
+    // Adds the price movement from the reserves `before` to the current ones to the
+    // volatility accumulator, as `Swap::settle` does
+    fn record_volatility(&mut self, before: (u64, u64)) -> Result<()> {
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let after = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.config
+            .record_volatility(before, after, Clock::get()?.unix_timestamp);
 
-        self.withdraw_tokens(true, x)?;
-        self.withdraw_tokens(false, y)?;
-        self.burn_lp_tokens(amount)
+        Ok(())
+    }
+
+    // Reports the withdrawal with the reserves and LP supply it left behind
+    fn emit_liquidity_removed(&mut self, amount_x: u64, amount_y: u64, lp: u64) -> Result<()> {
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.mint_lp.reload()?;
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        emit!(LiquidityRemoved {
+            config: self.config.key(),
+            user: self.user.key(),
+            amount_x,
+            amount_y,
+            lp_burned: lp,
+            lp_supply: self.mint_lp.supply,
+            reserve_x,
+            reserve_y,
+        });
+
+        Ok(())
+    }
+
+    fn pool(&self) -> Pool<'_, 'info> {
+        Pool {
+            config: &self.config,
+            mint_x: &self.mint_x,
+            mint_y: &self.mint_y,
+            vault_x: self.vault_x.amount,
+            vault_y: self.vault_y.amount,
+        }
     }
 
     pub fn withdraw_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+        let (from, to, mint, token_program) = match is_x {
             true => (
-                self.vault_x.to_account_info(),
-                self.user_x.to_account_info(),
+                &self.vault_x,
+                &self.user_x,
+                &self.mint_x,
+                &self.token_program_x,
             ),
             false => (
-                self.vault_y.to_account_info(),
-                self.user_y.to_account_info(),
+                &self.vault_y,
+                &self.user_y,
+                &self.mint_y,
+                &self.token_program_y,
             ),
         };
 
-        let config_seed = &self.config.seed.to_le_bytes();
-        let signer_seeds: &[&[&[u8]]] = &[&[b"config", config_seed.as_ref(), &[self.config.config_bump]]];
+        // Native SOL is received in the ephemeral account and unwrapped by closing it
+        let native_sol = match &self.native_sol {
+            Some(native_sol) if token::is_native(mint) => {
+                token::open_native(
+                    &self.user,
+                    native_sol,
+                    mint,
+                    0,
+                    token_program,
+                    &self.system_program,
+                )?;
+                Some(native_sol)
+            }
+            _ => None,
+        };
+        let to = match native_sol {
+            Some(native_sol) => native_sol.to_account_info(),
+            None => to.to_account_info(),
+        };
+
+        let [seed_a, seed_b, seed_c, seed_d] = self.config.seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &seed_a,
+            &seed_b,
+            &seed_c,
+            &seed_d,
+            &[self.config.config_bump],
+        ]];
 
-        let cpi_program = self.token_program.to_account_info();
+        let cpi_program = token_program.to_account_info();
 
-        let cpi_accounts = Transfer {
-            from,
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
             to,
             authority: self.config.to_account_info(),
         };
 
         let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
 
-        transfer(ctx, amount)
+        transfer_checked(ctx, amount, mint.decimals)?;
 
+        match native_sol {
+            Some(native_sol) => token::close_native(&self.user, native_sol, token_program),
+            None => Ok(()),
+        }
     }
 
     pub fn burn_lp_tokens(&self, amount: u64) -> Result<()> {
@@ -131,7 +326,7 @@ impl<'info> Withdraw<'info> {
         let cpi_accounts = Burn {
             authority: self.user.to_account_info(),
             from: self.user_lp.to_account_info(),
-            mint: self.mint_lp.to_account_info()
+            mint: self.mint_lp.to_account_info(),
         };
 
         let ctx = CpiContext::new(cpi_program, cpi_accounts);
@@ -1,34 +1,360 @@
 use anchor_lang::prelude::*;
 
+mod concentrated;
+mod curve;
+mod dynamic_fee;
 mod errors;
+mod events;
 mod instructions;
+mod pool;
+mod stable_swap;
 mod state;
+mod token;
+mod weighted;
 
 use instructions::*;
+#[cfg(feature = "test-utils")]
+pub use state::{Config, CurveType};
 declare_id!("C4Joom7WvZPRBhFYk6VJzi1juZECbMHPzTjrpzCfAZuZ");
 
 #[program]
 pub mod anchor_amm_q4_25 {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         ctx: Context<Initialize>,
-        seed: u64,
         fee: u16,
         authority: Option<Pubkey>,
+        protocol_fee: u16,
+        treasury: Pubkey,
+        curve_type: state::CurveType,
+        amp: u64,
+        weight_x: u16,
     ) -> Result<()> {
-        ctx.accounts.init(seed, fee, authority, ctx.bumps)
+        ctx.accounts.init(
+            fee,
+            authority,
+            protocol_fee,
+            treasury,
+            curve_type,
+            amp,
+            weight_x,
+            ctx.bumps,
+        )
     }
 
-    pub fn deposit(ctx: Context<Deposit>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
-        ctx.accounts.deposit(amount, max_x, max_y)
+    pub fn initialize_concentrated(
+        ctx: Context<InitializeConcentrated>,
+        fee: u16,
+        authority: Option<Pubkey>,
+        protocol_fee: u16,
+        treasury: Pubkey,
+        tick_spacing: u16,
+        sqrt_price: u128,
+    ) -> Result<()> {
+        ctx.accounts.init(
+            fee,
+            authority,
+            protocol_fee,
+            treasury,
+            tick_spacing,
+            sqrt_price,
+            ctx.bumps,
+        )
+    }
+
+    pub fn initialize_tick_array(ctx: Context<InitializeTickArray>, start_tick: i32) -> Result<()> {
+        ctx.accounts.init(start_tick, ctx.bumps)
+    }
+
+    pub fn open_position(
+        ctx: Context<OpenPosition>,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<()> {
+        ctx.accounts.open(tick_lower, tick_upper, ctx.bumps)
+    }
+
+    pub fn increase_liquidity(
+        ctx: Context<UpdatePosition>,
+        liquidity: u64,
+        max_x: u64,
+        max_y: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .increase_liquidity(liquidity, max_x, max_y, expiration)
+    }
+
+    pub fn decrease_liquidity(
+        ctx: Context<UpdatePosition>,
+        liquidity: u64,
+        min_x: u64,
+        min_y: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .decrease_liquidity(liquidity, min_x, min_y, expiration)
+    }
+
+    pub fn collect_fees(ctx: Context<UpdatePosition>) -> Result<()> {
+        ctx.accounts.collect_fees()
+    }
+
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        ctx.accounts.close()
+    }
+
+    pub fn swap_concentrated<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SwapConcentrated<'info>>,
+        is_x: bool,
+        amount: u64,
+        min_amount_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts.swap(
+            ctx.remaining_accounts,
+            is_x,
+            amount,
+            min_amount_out,
+            expiration,
+        )
+    }
+
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts.deposit(amount, max_x, max_y, expiration)
+    }
+
+    pub fn withdraw(
+        ctx: Context<Withdraw>,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts.withdraw(amount, max_x, max_y, expiration)
+    }
+
+    pub fn deposit_single(
+        ctx: Context<Deposit>,
+        is_x: bool,
+        amount: u64,
+        min_lp: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .deposit_single(is_x, amount, min_lp, expiration)
+    }
+
+    pub fn withdraw_single(
+        ctx: Context<Withdraw>,
+        is_x: bool,
+        amount: u64,
+        min_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .withdraw_single(is_x, amount, min_out, expiration)
+    }
+
+    pub fn swap(
+        ctx: Context<Swap>,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts.swap(is_x, amount_in, min_amount_out, expiration)
+    }
+
+    pub fn swap_exact_out(
+        ctx: Context<Swap>,
+        is_x: bool,
+        amount_out: u64,
+        max_amount_in: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .swap_exact_out(is_x, amount_out, max_amount_in, expiration)
+    }
+
+    pub fn swap_route<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        expiration: i64,
+    ) -> Result<()> {
+        ctx.accounts.swap_route(
+            ctx.remaining_accounts,
+            amount_in,
+            min_amount_out,
+            expiration,
+        )
+    }
+
+    pub fn quote_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Quote<'info>>,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        expiration: i64,
+        referral_fee: u16,
+    ) -> Result<pool::SwapAmounts> {
+        ctx.accounts.quote_swap(
+            ctx.remaining_accounts,
+            is_x,
+            amount_in,
+            min_amount_out,
+            expiration,
+            referral_fee,
+        )
+    }
+
+    pub fn quote_deposit(
+        ctx: Context<Quote>,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expiration: i64,
+    ) -> Result<pool::DepositAmounts> {
+        ctx.accounts.quote_deposit(amount, max_x, max_y, expiration)
+    }
+
+    pub fn quote_withdraw(
+        ctx: Context<Quote>,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+        expiration: i64,
+    ) -> Result<pool::WithdrawAmounts> {
+        ctx.accounts.quote_withdraw(amount, min_x, min_y, expiration)
+    }
+
+    pub fn twap(ctx: Context<Twap>, window: i64) -> Result<state::TwapPrice> {
+        ctx.accounts.twap(window)
+    }
+
+    pub fn initialize_oracle(ctx: Context<InitializeOracle>) -> Result<()> {
+        ctx.accounts.init(ctx.bumps)
+    }
+
+    pub fn initialize_registry(ctx: Context<InitializeRegistry>) -> Result<()> {
+        ctx.accounts.init(ctx.bumps)
+    }
+
+    pub fn register_pool(ctx: Context<RegisterPool>) -> Result<()> {
+        ctx.accounts.register(ctx.bumps)
+    }
+
+    pub fn flash_borrow(ctx: Context<FlashBorrow>, amount: u64) -> Result<()> {
+        ctx.accounts.flash_borrow(amount)
+    }
+
+    pub fn flash_repay(ctx: Context<FlashRepay>) -> Result<()> {
+        ctx.accounts.flash_repay()
+    }
+
+    pub fn initialize_farm(
+        ctx: Context<InitializeFarm>,
+        reward_per_second: u64,
+        end_ts: i64,
+    ) -> Result<()> {
+        ctx.accounts.init(reward_per_second, end_ts, ctx.bumps)
+    }
+
+    pub fn fund_farm(ctx: Context<FundFarm>, amount: u64) -> Result<()> {
+        ctx.accounts.fund(amount)
+    }
+
+    pub fn set_emission(
+        ctx: Context<UpdateFarm>,
+        reward_per_second: u64,
+        end_ts: i64,
+    ) -> Result<()> {
+        ctx.accounts.set_emission(reward_per_second, end_ts)
+    }
+
+    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        ctx.accounts.stake(amount, ctx.bumps)
+    }
+
+    pub fn unstake(ctx: Context<Unstake>, amount: u64) -> Result<()> {
+        ctx.accounts.unstake(amount)
+    }
+
+    pub fn harvest(ctx: Context<Harvest>) -> Result<()> {
+        ctx.accounts.harvest()
+    }
+
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        ctx.accounts.collect()
+    }
+
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        ctx.accounts.close()
+    }
+
+    pub fn lock(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.lock()
+    }
+
+    pub fn unlock(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.unlock()
+    }
+
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: u8) -> Result<()> {
+        ctx.accounts.set_paused(paused)
+    }
+
+    pub fn update_fee(ctx: Context<UpdateConfig>, fee: u16, protocol_fee: u16) -> Result<()> {
+        ctx.accounts.update_fee(fee, protocol_fee)
+    }
+
+    pub fn set_dynamic_fee(
+        ctx: Context<UpdateConfig>,
+        enabled: bool,
+        min_fee: u16,
+        max_fee: u16,
+    ) -> Result<()> {
+        ctx.accounts.set_dynamic_fee(enabled, min_fee, max_fee)
+    }
+
+    pub fn set_max_referral_fee(ctx: Context<UpdateConfig>, max_referral_fee: u16) -> Result<()> {
+        ctx.accounts.set_max_referral_fee(max_referral_fee)
+    }
+
+    pub fn set_referrer(ctx: Context<SetReferrer>, referral_fee: u16) -> Result<()> {
+        ctx.accounts.set_referrer(referral_fee, ctx.bumps)
+    }
+
+    pub fn ramp_amp(ctx: Context<UpdateConfig>, target_amp: u64, ramp_stop_ts: i64) -> Result<()> {
+        ctx.accounts.ramp_amp(target_amp, ramp_stop_ts)
+    }
+
+    pub fn stop_ramp_amp(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.stop_ramp_amp()
+    }
+
+    pub fn propose_authority(ctx: Context<UpdateConfig>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.propose_authority(new_authority)
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        ctx.accounts.accept_authority()
     }
 
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
-        ctx.accounts.withdraw(amount, max_x, max_y)
+    pub fn renounce_authority(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.renounce_authority()
     }
 
-    pub fn swap(ctx: Context<Swap>, is_x: bool, amount_in: u64, min_amount_out: u64) -> Result<()> {
-        ctx.accounts.swap(is_x, amount_in, min_amount_out)
+    pub fn migrate_config(ctx: Context<MigrateConfig>, treasury: Pubkey) -> Result<()> {
+        ctx.accounts.migrate(treasury)
     }
 }
@@ -0,0 +1,223 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::{
+    curve,
+    errors::AmmError,
+    state::{Config, MINIMUM_LIQUIDITY},
+    token,
+};
+
+/// Amounts of a swap as `swap` / `swap_exact_out` execute it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct SwapAmounts {
+    pub amount_in: u64,       // Sent by the user, including the swap fee and any transfer fee
+    pub amount_out: u64,      // Sent by the pool
+    pub amount_received: u64, // Received by the user, net of any transfer fee
+    pub fee: u64,             // Swap fee charged on the input
+    pub fee_bps: u16,         // Swap fee rate in basis points, dynamic on a dynamic-fee pool
+    pub protocol_fee: u64,    // Protocol share of the swap fee
+    pub referral: u64,        // Referrer share of the swap fee, out of the LPs' part
+}
+
+/// Amounts of a deposit as `deposit` executes it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct DepositAmounts {
+    pub amount_x: u64,         // Token X sent by the user, including any transfer fee
+    pub amount_y: u64,         // Token Y sent by the user, including any transfer fee
+    pub lp: u64,               // LP tokens minted to the user
+    pub locked_liquidity: u64, // LP shares locked forever, only by the first deposit
+}
+
+/// Amounts of a withdrawal as `withdraw` executes it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct WithdrawAmounts {
+    pub amount_x: u64,   // Token X sent by the pool
+    pub amount_y: u64,   // Token Y sent by the pool
+    pub received_x: u64, // Token X received by the user, net of any transfer fee
+    pub received_y: u64, // Token Y received by the user, net of any transfer fee
+}
+
+/// Read-only view of a pool, so that the instructions and their quotes price alike
+pub struct Pool<'a, 'info> {
+    pub config: &'a Config,
+    pub mint_x: &'a InterfaceAccount<'info, Mint>,
+    pub mint_y: &'a InterfaceAccount<'info, Mint>,
+    pub vault_x: u64,
+    pub vault_y: u64,
+}
+
+impl<'a, 'info> Pool<'a, 'info> {
+    fn mints(
+        &self,
+        is_x: bool,
+    ) -> (
+        &'a InterfaceAccount<'info, Mint>,
+        &'a InterfaceAccount<'info, Mint>,
+    ) {
+        match is_x {
+            true => (self.mint_x, self.mint_y),
+            false => (self.mint_y, self.mint_x),
+        }
+    }
+
+    /// Swap of exactly `amount` of X (or Y if `!is_x`) for at least `min` of the other token
+    pub fn swap_exact_in(&self, is_x: bool, amount: u64, min: u64) -> Result<SwapAmounts> {
+        require!(amount != 0, AmmError::InvalidAmount);
+
+        let (mint_in, mint_out) = self.mints(is_x);
+
+        // Transfer fee mints deliver less than is sent, so price what the vault receives
+        // and check slippage on what the user receives
+        let received = token::amount_after_transfer_fee(mint_in, amount)?;
+        let quote = self
+            .config
+            .quote_exact_in(is_x, received, self.vault_x, self.vault_y)?;
+
+        require!(quote.amount_out != 0, AmmError::InvalidAmount);
+
+        let amount_received = token::amount_after_transfer_fee(mint_out, quote.amount_out)?;
+        require!(amount_received >= min, AmmError::SlippageExceeded);
+
+        Ok(SwapAmounts {
+            amount_in: amount,
+            amount_out: quote.amount_out,
+            amount_received,
+            fee: quote.fee,
+            fee_bps: self.config.current_fee()?,
+            protocol_fee: self.config.protocol_share(quote.fee)?,
+            referral: 0,
+        })
+    }
+
+    /// Swap of at most `max_amount_in` of X (or Y if `!is_x`) for exactly `amount_out`
+    pub fn swap_exact_out(
+        &self,
+        is_x: bool,
+        amount_out: u64,
+        max_amount_in: u64,
+    ) -> Result<SwapAmounts> {
+        require!(amount_out != 0, AmmError::InvalidAmount);
+
+        let (mint_in, mint_out) = self.mints(is_x);
+
+        // Both sides are grossed up by any transfer fee, so exactly `amount_out` arrives
+        let sent = token::amount_before_transfer_fee(mint_out, amount_out)?;
+        let quote = self
+            .config
+            .quote_exact_out(is_x, sent, self.vault_x, self.vault_y)?;
+        let amount_in = token::amount_before_transfer_fee(mint_in, quote.amount_in)?;
+
+        require!(amount_in <= max_amount_in, AmmError::SlippageExceeded);
+
+        Ok(SwapAmounts {
+            amount_in,
+            amount_out: sent,
+            amount_received: amount_out,
+            fee: quote.fee,
+            fee_bps: self.config.current_fee()?,
+            protocol_fee: self.config.protocol_share(quote.fee)?,
+            referral: 0,
+        })
+    }
+
+    /// Deposit minting `amount` LP out of `lp_supply` (at least `amount` on the first deposit)
+    pub fn deposit(
+        &self,
+        lp_supply: u64,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+    ) -> Result<DepositAmounts> {
+        require!(amount != 0, AmmError::InvalidAmount);
+
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x, self.vault_y)?;
+        let shares = self.config.lp_shares(lp_supply)?;
+
+        let amounts = match shares == 0 {
+            // The first deposit mints the liquidity of the reserves it creates (their
+            // geometric mean, weighted on a weighted pool, or D on a StableSwap pool) and
+            // locks MINIMUM_LIQUIDITY of it forever, so the LP share price can't be inflated
+            // against later depositors. `amount` is the minimum LP accepted
+            true => {
+                let liquidity = self.config.initial_liquidity(
+                    reserve_x
+                        .checked_add(token::amount_after_transfer_fee(self.mint_x, max_x)?)
+                        .ok_or(AmmError::Overflow)?,
+                    reserve_y
+                        .checked_add(token::amount_after_transfer_fee(self.mint_y, max_y)?)
+                        .ok_or(AmmError::Overflow)?,
+                )?;
+                let lp = liquidity
+                    .checked_sub(MINIMUM_LIQUIDITY)
+                    .filter(|lp| *lp != 0)
+                    .ok_or(AmmError::LiquidityLessThanMinimum)?;
+
+                require!(lp >= amount, AmmError::SlippageExceeded);
+
+                DepositAmounts {
+                    amount_x: max_x,
+                    amount_y: max_y,
+                    lp,
+                    locked_liquidity: MINIMUM_LIQUIDITY,
+                }
+            }
+            false => {
+                // Rounded up, so a depositor always pays at least what the LP is worth
+                let (x, y) = curve::deposit_amounts(reserve_x, reserve_y, shares, amount)?;
+
+                // Transfer fee mints deliver less than is sent, so gross the amounts
+                // up until the vaults receive what the LP amount is worth
+                DepositAmounts {
+                    amount_x: token::amount_before_transfer_fee(self.mint_x, x)?,
+                    amount_y: token::amount_before_transfer_fee(self.mint_y, y)?,
+                    lp: amount,
+                    locked_liquidity: 0,
+                }
+            }
+        };
+
+        require!(
+            amounts.amount_x <= max_x && amounts.amount_y <= max_y,
+            AmmError::SlippageExceeded
+        );
+
+        Ok(amounts)
+    }
+
+    /// Withdrawal burning `amount` LP out of `lp_supply`
+    pub fn withdraw(
+        &self,
+        lp_supply: u64,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+    ) -> Result<WithdrawAmounts> {
+        require!(amount != 0, AmmError::InvalidAmount);
+
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x, self.vault_y)?;
+
+        // Rounded down, so a withdrawal never takes more than the burned LP is worth
+        let (x, y) = curve::withdraw_amounts(
+            reserve_x,
+            reserve_y,
+            self.config.lp_shares(lp_supply)?,
+            amount,
+        )?;
+
+        // Slippage is checked on what the user receives after any transfer fee
+        let received_x = token::amount_after_transfer_fee(self.mint_x, x)?;
+        let received_y = token::amount_after_transfer_fee(self.mint_y, y)?;
+        require!(
+            received_x >= min_x && received_y >= min_y,
+            AmmError::SlippageExceeded
+        );
+
+        Ok(WithdrawAmounts {
+            amount_x: x,
+            amount_y: y,
+            received_x,
+            received_y,
+        })
+    }
+}
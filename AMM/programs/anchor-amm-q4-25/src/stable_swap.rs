@@ -0,0 +1,178 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AmmError;
+
+/// Bounds of the amplification coefficient
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+/// Largest factor the amplification may be ramped up or down by at once
+pub const MAX_AMP_CHANGE: u64 = 10;
+/// Minimum duration of an amplification ramp, and between the starts of two ramps
+pub const MIN_RAMP_DURATION: i64 = 24 * 60 * 60;
+
+// A two-token pool, `Ann` in the invariant is `amp * n^n`
+const N_COINS: u128 = 2;
+const MAX_ITERATIONS: usize = 255;
+
+/// StableSwap invariant `D` of the reserves:
+/// `Ann * (x + y) + D = Ann * D + D^3 / (4 * x * y)`
+pub fn compute_d(amp: u64, reserve_x: u64, reserve_y: u64) -> Result<u128> {
+    let ann = amp as u128 * N_COINS * N_COINS;
+    let (x, y) = (reserve_x as u128, reserve_y as u128);
+    let sum = x + y;
+
+    if sum == 0 {
+        return Ok(0);
+    }
+    require!(x != 0 && y != 0, AmmError::ZeroBalance);
+
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        // D^3 / (4 * x * y)
+        let d_p = mul_div(mul_div(d, d, x * N_COINS)?, d, y * N_COINS)?;
+        let previous = d;
+
+        let numerator = ann
+            .checked_mul(sum)
+            .and_then(|n| n.checked_add(d_p.checked_mul(N_COINS)?))
+            .ok_or(AmmError::Overflow)?;
+        let denominator = (ann - 1)
+            .checked_mul(d)
+            .and_then(|n| n.checked_add(d_p.checked_mul(N_COINS + 1)?))
+            .ok_or(AmmError::Overflow)?;
+
+        d = mul_div(numerator, d, denominator)?;
+
+        if d.abs_diff(previous) <= 1 {
+            return Ok(d);
+        }
+    }
+
+    err!(AmmError::CurveError)
+}
+
+/// Reserve of one token that keeps the invariant at `d` when the other one is `reserve`
+fn compute_y(amp: u64, reserve: u128, d: u128) -> Result<u128> {
+    require!(reserve != 0, AmmError::ZeroBalance);
+
+    let ann = amp as u128 * N_COINS * N_COINS;
+
+    // c = D^3 / (4 * x * Ann), b = x + D / Ann
+    let c = mul_div(mul_div(d, d, reserve * N_COINS)?, d, ann * N_COINS)?;
+    let b = reserve + d / ann;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let previous = y;
+
+        // y = (y^2 + c) / (2y + b - D)
+        let (hi, lo) = widening_mul(y, y);
+        let (lo, carry) = lo.overflowing_add(c);
+        let denominator = (y * N_COINS + b)
+            .checked_sub(d)
+            .filter(|n| *n != 0)
+            .ok_or(AmmError::CurveError)?;
+        y = div_wide(hi + carry as u128, lo, denominator)
+            .ok_or(AmmError::Overflow)?
+            .0;
+
+        if y.abs_diff(previous) <= 1 {
+            return Ok(y);
+        }
+    }
+
+    err!(AmmError::CurveError)
+}
+
+/// Output of swapping a net `amount_in` against the reserves, rounded down. Takes the
+/// invariant `d` of the reserves, so that swaps priced against them compute it once
+pub fn amount_out(
+    amp: u64,
+    d: u128,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_in: u64,
+) -> Result<u64> {
+    let y = compute_y(amp, reserve_in as u128 + amount_in as u128, d)?;
+
+    // One unit is held back, so the approximations of D and y never favor the trader
+    let amount_out = (reserve_out as u128)
+        .checked_sub(y)
+        .ok_or(AmmError::Underflow)?
+        .saturating_sub(1);
+
+    u64::try_from(amount_out).map_err(|_| AmmError::Overflow.into())
+}
+
+/// Net input needed to take exactly `amount_out` from the reserves, rounded up
+pub fn amount_in_for_exact_out(
+    amp: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_out: u64,
+) -> Result<u64> {
+    require!(amount_out < reserve_out, AmmError::InsufficientBalance);
+
+    let d = compute_d(amp, reserve_in, reserve_out)?;
+    let x = compute_y(amp, (reserve_out - amount_out) as u128, d)?;
+
+    let amount_in = x
+        .checked_sub(reserve_in as u128)
+        .ok_or(AmmError::Underflow)?
+        + 1;
+
+    u64::try_from(amount_in).map_err(|_| AmmError::Overflow.into())
+}
+
+// `a * b / c` with a 256-bit intermediate, rounded down
+fn mul_div(a: u128, b: u128, c: u128) -> Result<u128> {
+    require!(c != 0, AmmError::ZeroBalance);
+
+    let (hi, lo) = widening_mul(a, b);
+
+    Ok(div_wide(hi, lo, c).ok_or(AmmError::Overflow)?.0)
+}
+
+// Full 256-bit product of `a` and `b` as (high, low) halves
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+
+    let (a1, a0) = (a >> 64, a & MASK);
+    let (b1, b0) = (b >> 64, b & MASK);
+
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+
+    let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+    let lo = (p00 & MASK) | (mid << 64);
+    let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+
+    (hi, lo)
+}
+
+// Divides the 256-bit `hi:lo` by `d`, `None` if the quotient doesn't fit in a u128
+fn div_wide(hi: u128, lo: u128, d: u128) -> Option<(u128, u128)> {
+    if hi == 0 {
+        return Some((lo / d, lo % d));
+    }
+    if hi >= d {
+        return None;
+    }
+
+    // Restoring long division, one bit of `lo` at a time
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= d {
+            remainder = remainder.wrapping_sub(d);
+            quotient |= 1;
+        }
+    }
+
+    Some((quotient, remainder))
+}
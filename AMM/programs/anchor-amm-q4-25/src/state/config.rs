@@ -1,14 +1,584 @@
 use anchor_lang::prelude::*;
 
+use constant_product_curve::ConstantProduct;
+
+use crate::{
+    concentrated,
+    curve::{self, SwapQuote},
+    dynamic_fee,
+    errors::AmmError,
+    stable_swap,
+    state::{Pair, Tick},
+    weighted,
+};
+
+/// Basis points denominator (100%)
+pub const MAX_BPS: u16 = 10_000;
+
+/// LP shares locked forever by a pool's first deposit
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+/// Pause flags stored in `Config::paused`
+pub const PAUSE_SWAP: u8 = 1 << 0;
+pub const PAUSE_DEPOSIT: u8 = 1 << 1;
+pub const PAUSE_WITHDRAW: u8 = 1 << 2;
+pub const PAUSE_ALL: u8 = PAUSE_SWAP | PAUSE_DEPOSIT | PAUSE_WITHDRAW;
+
+/// Invariant a pool prices its swaps with, chosen at `initialize`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum CurveType {
+    ConstantProduct, // x * y = k
+    StableSwap,      // Curve-style invariant with an amplification coefficient, for pegged pairs
+    Weighted,        // x^w_x * y^w_y = k, for pools that aren't split 50/50
+    Concentrated,    // Liquidity provided over price ranges by positions, instead of LP tokens
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
-    pub seed: u64,                 // Seed to be able to create different pools / configs
-    pub authority: Option<Pubkey>, // If we want an authority to lock the config account
-    pub mint_x: Pubkey,            // Token X
-    pub mint_y: Pubkey,            // Token Y
-    pub fee: u16,                  // Swap fee in basis points
-    pub locked: bool,              // If the pool is locked
-    pub config_bump: u8,           // Bump seed for the config account
-    pub lp_bump: u8,               // Bump seed for the LP token
+    pub legacy_seed: Option<u64>,          // Seed keying a pool migrated from the legacy layout, instead of its pair
+    pub registered: bool,                  // Whether the pool has a pair account and a registry entry
+    pub authority: Option<Pubkey>,         // If we want an authority to lock the config account
+    pub pending_authority: Option<Pubkey>, // Authority proposed by the current one, until it accepts
+    pub initializer: Pubkey,               // Payer of the pool's accounts, refunded their rent when it's closed
+    pub mint_x: Pubkey,                    // Token X
+    pub mint_y: Pubkey,                    // Token Y
+    pub decimals_x: u8,                    // Decimals of token X
+    pub decimals_y: u8,                    // Decimals of token Y
+    pub lp_decimals: u8,                   // Decimals of the LP token, derived from those of X and Y
+    pub curve_type: CurveType,             // Invariant used to price swaps
+    pub initial_amp: u64,                  // StableSwap amplification at the start of the ramp
+    pub target_amp: u64,                   // StableSwap amplification at the end of the ramp
+    pub ramp_start_ts: i64,                // Unix timestamp the amplification ramp started at
+    pub ramp_stop_ts: i64,                 // Unix timestamp the amplification ramp ends at
+    pub weight_x: u16,                     // Weight of token X in basis points
+    pub weight_y: u16,                     // Weight of token Y in basis points, MAX_BPS - weight_x
+    pub tick_spacing: u16,                 // Concentrated pool distance between usable ticks
+    pub sqrt_price: u128,                  // Concentrated pool square root of the price of X in Y, in Q64.64
+    pub tick_current: i32,                 // Concentrated pool largest tick at or below the price
+    pub liquidity: u64,                    // Concentrated pool liquidity of the positions in range
+    pub position_count: u64,               // Concentrated pool positions open, which must all be closed before it is
+    pub fee_growth_global_x: u128,         // Concentrated pool fees of X per unit of liquidity, in Q64.64
+    pub fee_growth_global_y: u128,         // Concentrated pool fees of Y per unit of liquidity, in Q64.64
+    pub fee: u16,                          // Swap fee in basis points
+    pub fee_tier: u16,                     // Swap fee the pool was created with, keying its config and pair, never updated
+    pub dynamic_fee: bool,                 // Whether the swap fee follows the volatility instead of `fee`
+    pub min_fee: u16,                      // Dynamic fee in basis points when the market is calm
+    pub max_fee: u16,                      // Upper bound of the dynamic fee, in basis points
+    pub volatility: u64,                   // Recent price movement in basis points, decaying over time
+    pub volatility_ts: i64,                // Unix timestamp the volatility was last updated at
+    pub protocol_fee: u16,                 // Protocol share of the swap fee, in basis points
+    pub max_referral_fee: u16,             // Largest share of the swap fee a referrer may take, in basis points
+    pub treasury: Pubkey,                  // Recipient of the protocol fees
+    pub protocol_fees_x: u64,              // Accrued protocol fees held in vault X
+    pub protocol_fees_y: u64,              // Accrued protocol fees held in vault Y
+    pub locked_liquidity: u64,             // LP shares locked forever by the first deposit
+    pub flash_loan_owed: u64,              // Amount owed by the outstanding flash loan, 0 if none
+    pub flash_loan_is_x: bool,             // Whether the outstanding flash loan is in token X
+    pub paused: u8,                        // Bitmask of paused operations (PAUSE_*)
+    pub config_bump: u8,                   // Bump seed for the config account
+    pub lp_bump: u8,                       // Bump seed for the LP token
+}
+
+impl Config {
+    /// Seeds of the config PDA between its `b"config"` prefix and bump: the canonical pair,
+    /// fee tier and curve, or the seed of a pool migrated from the legacy layout. Unused seeds
+    /// are empty
+    pub fn seeds(&self) -> [Vec<u8>; 4] {
+        match self.legacy_seed {
+            Some(seed) => [
+                seed.to_le_bytes().to_vec(),
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+            ],
+            None => {
+                let (mint_a, mint_b) = Pair::canonical(self.mint_x, self.mint_y);
+                [
+                    mint_a.to_bytes().to_vec(),
+                    mint_b.to_bytes().to_vec(),
+                    self.fee_tier.to_le_bytes().to_vec(),
+                    vec![self.curve_type as u8],
+                ]
+            }
+        }
+    }
+
+    /// Whether `key` is the config PDA, checked instead of a `seeds` constraint since the
+    /// seeds depend on the pool's layout
+    pub fn is_pda(&self, key: &Pubkey) -> bool {
+        let [seed_a, seed_b, seed_c, seed_d] = self.seeds();
+
+        Pubkey::create_program_address(
+            &[
+                b"config",
+                &seed_a,
+                &seed_b,
+                &seed_c,
+                &seed_d,
+                &[self.config_bump],
+            ],
+            &crate::ID,
+        )
+        .is_ok_and(|pda| pda == *key)
+    }
+
+    /// Fails with `PoolLocked` if any of the operations in `flags` is paused, and with
+    /// `FlashLoanActive` while the reserves are lent out
+    pub fn check_not_paused(&self, flags: u8) -> Result<()> {
+        require!(self.paused & flags == 0, AmmError::PoolLocked);
+        require!(self.flash_loan_owed == 0, AmmError::FlashLoanActive);
+
+        Ok(())
+    }
+
+    /// Swap fee in basis points, `fee` unless the dynamic fee is enabled
+    pub fn current_fee(&self) -> Result<u16> {
+        Ok(match self.dynamic_fee {
+            true => dynamic_fee::fee(
+                self.volatility_at(Clock::get()?.unix_timestamp),
+                self.min_fee,
+                self.max_fee,
+            ),
+            false => self.fee,
+        })
+    }
+
+    /// Volatility accumulator decayed to `now`
+    pub fn volatility_at(&self, now: i64) -> u64 {
+        dynamic_fee::decay(self.volatility, now.saturating_sub(self.volatility_ts))
+    }
+
+    /// Adds the price movement of a swap from the reserves `before` to `after` to the
+    /// volatility accumulator. Tracked whether or not the dynamic fee is enabled
+    pub fn record_volatility(&mut self, before: (u64, u64), after: (u64, u64), now: i64) {
+        self.record_price_move(dynamic_fee::price_move(before, after), now);
+    }
+
+    /// Adds a price movement in basis points to the volatility accumulator
+    pub fn record_price_move(&mut self, price_move: u64, now: i64) {
+        self.volatility = self.volatility_at(now).saturating_add(price_move);
+        self.volatility_ts = now;
+    }
+
+    /// Fee charged on a swap input of `amount`, rounded up in the pool's favor
+    pub fn swap_fee(&self, amount: u64) -> Result<u64> {
+        fee_at(amount, self.current_fee()?)
+    }
+
+    /// Gross input whose net amount after `swap_fee` is at least `amount`
+    pub fn amount_with_fee(&self, amount: u64) -> Result<u64> {
+        let fee = self.current_fee()?;
+        require!(fee < MAX_BPS, AmmError::InvalidFee);
+
+        let gross = (amount as u128)
+            .checked_mul(MAX_BPS as u128)
+            .ok_or(AmmError::Overflow)?
+            .div_ceil((MAX_BPS - fee) as u128);
+
+        u64::try_from(gross).map_err(|_| AmmError::Overflow.into())
+    }
+
+    /// Part of a swap `fee` that goes to the protocol instead of the LPs
+    pub fn protocol_share(&self, fee: u64) -> Result<u64> {
+        let share = (fee as u128)
+            .checked_mul(self.protocol_fee as u128)
+            .ok_or(AmmError::Overflow)?
+            / MAX_BPS as u128;
+
+        u64::try_from(share).map_err(|_| AmmError::Overflow.into())
+    }
+
+    /// Part of a swap `fee` paid to a referrer taking `referral_fee` basis points of it,
+    /// out of what's left after the protocol share
+    pub fn referral_share(&self, fee: u64, protocol_fee: u64, referral_fee: u16) -> Result<u64> {
+        require!(
+            referral_fee <= self.max_referral_fee,
+            AmmError::InvalidReferralFee
+        );
+
+        let share = (fee as u128)
+            .checked_mul(referral_fee as u128)
+            .ok_or(AmmError::Overflow)?
+            / MAX_BPS as u128;
+
+        Ok((share as u64).min(fee.saturating_sub(protocol_fee)))
+    }
+
+    /// Credits the protocol share of a swap fee paid in token X or Y
+    pub fn accrue_protocol_fee(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let accrued = match is_x {
+            true => &mut self.protocol_fees_x,
+            false => &mut self.protocol_fees_y,
+        };
+        *accrued = accrued.checked_add(amount).ok_or(AmmError::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Total LP shares, the minted supply plus the locked minimum liquidity
+    pub fn lp_shares(&self, supply: u64) -> Result<u64> {
+        Ok(supply
+            .checked_add(self.locked_liquidity)
+            .ok_or(AmmError::Overflow)?)
+    }
+
+    /// LP-owned reserves, i.e. the vault balances minus the accrued protocol fees
+    pub fn reserves(&self, vault_x: u64, vault_y: u64) -> Result<(u64, u64)> {
+        Ok((
+            vault_x
+                .checked_sub(self.protocol_fees_x)
+                .ok_or(AmmError::Underflow)?,
+            vault_y
+                .checked_sub(self.protocol_fees_y)
+                .ok_or(AmmError::Underflow)?,
+        ))
+    }
+
+    /// StableSwap amplification at `now`, moving linearly from `initial_amp` to `target_amp`
+    pub fn amp(&self, now: i64) -> u64 {
+        if now >= self.ramp_stop_ts {
+            return self.target_amp;
+        }
+
+        let elapsed = now.saturating_sub(self.ramp_start_ts).max(0) as u128;
+        let duration = (self.ramp_stop_ts - self.ramp_start_ts) as u128;
+        let (initial, target) = (self.initial_amp as u128, self.target_amp as u128);
+
+        let amp = match target >= initial {
+            true => initial + (target - initial) * elapsed / duration,
+            false => initial - (initial - target) * elapsed / duration,
+        };
+
+        amp as u64
+    }
+
+    /// Decimals of the LP token of a pool, those of the liquidity its first deposit mints:
+    /// the mean of the token decimals, weighted on a weighted pool, and the larger of the
+    /// two on a StableSwap pool, whose invariant sums reserves scaled to the same decimals
+    pub fn lp_decimals(curve_type: CurveType, decimals_x: u8, decimals_y: u8, weight_x: u16) -> u8 {
+        let (decimals_x, decimals_y) = (decimals_x as u32, decimals_y as u32);
+
+        let decimals = match curve_type {
+            CurveType::ConstantProduct => (decimals_x + decimals_y) / 2,
+            CurveType::StableSwap => decimals_x.max(decimals_y),
+            // Positions take the place of LP tokens
+            CurveType::Concentrated => 0,
+            CurveType::Weighted => {
+                let weight_x = weight_x.min(MAX_BPS) as u32;
+                let weight_y = MAX_BPS as u32 - weight_x;
+
+                (decimals_x * weight_x + decimals_y * weight_y + MAX_BPS as u32 / 2)
+                    / MAX_BPS as u32
+            }
+        };
+
+        decimals as u8
+    }
+
+    /// Factors scaling amounts of X and Y to the larger of their decimals, so the
+    /// StableSwap invariant compares whole tokens rather than base units
+    fn precision_multipliers(&self) -> Result<(u64, u64)> {
+        let decimals = self.decimals_x.max(self.decimals_y);
+        let multiplier = |d: u8| {
+            10u64
+                .checked_pow((decimals - d) as u32)
+                .ok_or(AmmError::Overflow)
+        };
+
+        Ok((multiplier(self.decimals_x)?, multiplier(self.decimals_y)?))
+    }
+
+    /// Precision multipliers of the input and output side of a swap
+    fn stable_multipliers(&self, is_x: bool) -> Result<(u64, u64)> {
+        let (x, y) = self.precision_multipliers()?;
+
+        Ok(match is_x {
+            true => (x, y),
+            false => (y, x),
+        })
+    }
+
+    /// LP minted by the first deposit for the reserves it creates
+    pub fn initial_liquidity(&self, reserve_x: u64, reserve_y: u64) -> Result<u64> {
+        match self.curve_type {
+            CurveType::ConstantProduct => curve::initial_liquidity(reserve_x, reserve_y),
+            CurveType::StableSwap => {
+                let amp = self.amp(Clock::get()?.unix_timestamp);
+                let (multiplier_x, multiplier_y) = self.precision_multipliers()?;
+                let d = stable_swap::compute_d(
+                    amp,
+                    scale(reserve_x, multiplier_x)?,
+                    scale(reserve_y, multiplier_y)?,
+                )?;
+
+                u64::try_from(d).map_err(|_| AmmError::Overflow.into())
+            }
+            CurveType::Weighted => {
+                weighted::initial_liquidity(reserve_x, self.weight_x, reserve_y, self.weight_y)
+            }
+            CurveType::Concentrated => err!(AmmError::InvalidCurve),
+        }
+    }
+
+    /// Weights of the input and output side of a swap
+    pub fn weights(&self, is_x: bool) -> (u16, u16) {
+        match is_x {
+            true => (self.weight_x, self.weight_y),
+            false => (self.weight_y, self.weight_x),
+        }
+    }
+
+    /// Prices an exact-input swap of `amount` of X (or Y if `!is_x`) against the vault balances
+    pub fn quote_exact_in(
+        &self,
+        is_x: bool,
+        amount: u64,
+        vault_x: u64,
+        vault_y: u64,
+    ) -> Result<SwapQuote> {
+        self.pricer(is_x, vault_x, vault_y)?.quote_exact_in(amount)
+    }
+
+    /// Prices exact-input swaps of X (or Y if `!is_x`) against the vault balances, reading the
+    /// fee rate and amplification and computing the StableSwap invariant once for all of them
+    pub fn pricer(&self, is_x: bool, vault_x: u64, vault_y: u64) -> Result<SwapPricer<'_>> {
+        let (x, y) = self.reserves(vault_x, vault_y)?;
+        let (reserve_in, reserve_out) = match is_x {
+            true => (x, y),
+            false => (y, x),
+        };
+
+        let (amp, d) = match self.curve_type {
+            CurveType::StableSwap => {
+                let amp = self.amp(Clock::get()?.unix_timestamp);
+                let (multiplier_in, multiplier_out) = self.stable_multipliers(is_x)?;
+                let d = stable_swap::compute_d(
+                    amp,
+                    scale(reserve_in, multiplier_in)?,
+                    scale(reserve_out, multiplier_out)?,
+                )?;
+
+                (amp, d)
+            }
+            _ => (0, 0),
+        };
+
+        Ok(SwapPricer {
+            config: self,
+            is_x,
+            fee_bps: self.current_fee()?,
+            reserve_in,
+            reserve_out,
+            amp,
+            d,
+        })
+    }
+
+    /// Prices a swap paying out exactly `amount_out` of the other token
+    pub fn quote_exact_out(
+        &self,
+        is_x: bool,
+        amount_out: u64,
+        vault_x: u64,
+        vault_y: u64,
+    ) -> Result<SwapQuote> {
+        let (x, y) = self.reserves(vault_x, vault_y)?;
+
+        let (reserve_in, reserve_out) = match is_x {
+            true => (x, y),
+            false => (y, x),
+        };
+
+        // Both steps round up, so the pool never gives out more than the input pays for
+        let amount_in = match self.curve_type {
+            CurveType::ConstantProduct => {
+                curve::amount_in_for_exact_out(reserve_in, reserve_out, amount_out)?
+            }
+            CurveType::StableSwap => {
+                let (multiplier_in, multiplier_out) = self.stable_multipliers(is_x)?;
+
+                stable_swap::amount_in_for_exact_out(
+                    self.amp(Clock::get()?.unix_timestamp),
+                    scale(reserve_in, multiplier_in)?,
+                    scale(reserve_out, multiplier_out)?,
+                    scale(amount_out, multiplier_out)?,
+                )?
+                .div_ceil(multiplier_in)
+            }
+            CurveType::Weighted => {
+                let (weight_in, weight_out) = self.weights(is_x);
+
+                weighted::amount_in_for_exact_out(
+                    reserve_in,
+                    weight_in,
+                    reserve_out,
+                    weight_out,
+                    amount_out,
+                )?
+            }
+            CurveType::Concentrated => return err!(AmmError::InvalidCurve),
+        };
+        let amount_in = self.amount_with_fee(amount_in)?;
+        let fee = self.swap_fee(amount_in)?;
+
+        Ok(SwapQuote {
+            amount_in,
+            amount_out,
+            fee,
+        })
+    }
+
+    /// Fee growth per unit of liquidity of a concentrated pool, for X and Y
+    pub fn fee_growth_global(&self) -> (u128, u128) {
+        (self.fee_growth_global_x, self.fee_growth_global_y)
+    }
+
+    /// Fails unless `tick_lower..tick_upper` is a non-empty range of usable ticks
+    pub fn check_tick_range(&self, tick_lower: i32, tick_upper: i32) -> Result<()> {
+        let spacing = self.tick_spacing as i32;
+        require!(
+            tick_lower < tick_upper
+                && tick_lower >= concentrated::MIN_TICK
+                && tick_upper <= concentrated::MAX_TICK
+                && tick_lower % spacing == 0
+                && tick_upper % spacing == 0,
+            AmmError::InvalidTickRange
+        );
+
+        Ok(())
+    }
+
+    /// Fee growth per unit of liquidity inside a price range, for X and Y: the global
+    /// growth minus the growth below the lower tick and above the upper one
+    pub fn fee_growth_inside(
+        &self,
+        tick_lower: i32,
+        lower: &Tick,
+        tick_upper: i32,
+        upper: &Tick,
+    ) -> (u128, u128) {
+        let (global_x, global_y) = self.fee_growth_global();
+
+        let (below_x, below_y) = match self.tick_current >= tick_lower {
+            true => (lower.fee_growth_outside_x, lower.fee_growth_outside_y),
+            false => (
+                global_x.wrapping_sub(lower.fee_growth_outside_x),
+                global_y.wrapping_sub(lower.fee_growth_outside_y),
+            ),
+        };
+        let (above_x, above_y) = match self.tick_current < tick_upper {
+            true => (upper.fee_growth_outside_x, upper.fee_growth_outside_y),
+            false => (
+                global_x.wrapping_sub(upper.fee_growth_outside_x),
+                global_y.wrapping_sub(upper.fee_growth_outside_y),
+            ),
+        };
+
+        (
+            global_x.wrapping_sub(below_x).wrapping_sub(above_x),
+            global_y.wrapping_sub(below_y).wrapping_sub(above_y),
+        )
+    }
+
+    /// Fails unless `signer` is the pool's update authority
+    pub fn check_authority(&self, signer: &Pubkey) -> Result<()> {
+        let authority = self.authority.ok_or(AmmError::NoAuthoritySet)?;
+        require_keys_eq!(authority, *signer, AmmError::InvalidAuthority);
+
+        Ok(())
+    }
+}
+
+/// Exact-input swaps in one direction against fixed reserves, see `Config::pricer`
+pub struct SwapPricer<'a> {
+    config: &'a Config,
+    is_x: bool,
+    fee_bps: u16,     // Swap fee rate in basis points
+    reserve_in: u64,  // LP-owned reserve of the input token
+    reserve_out: u64, // LP-owned reserve of the output token
+    amp: u64,         // StableSwap amplification, 0 for other curves
+    d: u128,          // StableSwap invariant of the reserves, 0 for other curves
+}
+
+impl SwapPricer<'_> {
+    pub fn fee_bps(&self) -> u16 {
+        self.fee_bps
+    }
+
+    /// LP-owned reserves of the input and output token
+    pub fn reserves(&self) -> (u64, u64) {
+        (self.reserve_in, self.reserve_out)
+    }
+
+    /// Prices an exact-input swap of `amount`
+    pub fn quote_exact_in(&self, amount: u64) -> Result<SwapQuote> {
+        // The fee is taken out of the input before pricing and stays in the vault,
+        // so it accrues to LPs through a growing k
+        let fee = fee_at(amount, self.fee_bps)?;
+        let amount_in = amount.checked_sub(fee).ok_or(AmmError::Underflow)?;
+
+        let (reserve_in, reserve_out) = (self.reserve_in, self.reserve_out);
+        let amount_out = match self.config.curve_type {
+            CurveType::ConstantProduct => {
+                ConstantProduct::delta_y_from_x_swap_amount(reserve_in, reserve_out, amount_in)
+                    .map_err(AmmError::from)?
+            }
+            CurveType::StableSwap => {
+                let (multiplier_in, multiplier_out) = self.config.stable_multipliers(self.is_x)?;
+
+                // Rounds down to the output's precision, in favor of the pool
+                stable_swap::amount_out(
+                    self.amp,
+                    self.d,
+                    scale(reserve_in, multiplier_in)?,
+                    scale(reserve_out, multiplier_out)?,
+                    scale(amount_in, multiplier_in)?,
+                )? / multiplier_out
+            }
+            CurveType::Weighted => {
+                let (weight_in, weight_out) = self.config.weights(self.is_x);
+
+                weighted::amount_out(reserve_in, weight_in, reserve_out, weight_out, amount_in)?
+            }
+            // Swapped by `swap_concentrated`, across the pool's ticks
+            CurveType::Concentrated => return err!(AmmError::InvalidCurve),
+        };
+
+        Ok(SwapQuote {
+            amount_in: amount,
+            amount_out,
+            fee,
+        })
+    }
+}
+
+/// Layout of `Config` before the pause flags, kept so old accounts can be migrated
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct ConfigV0 {
+    pub seed: u64,
+    pub authority: Option<Pubkey>,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub locked: bool,
+    pub config_bump: u8,
+    pub lp_bump: u8,
+}
+
+// Swap fee on `amount` at `fee_bps`, rounded up
+fn fee_at(amount: u64, fee_bps: u16) -> Result<u64> {
+    let fee = (amount as u128)
+        .checked_mul(fee_bps as u128)
+        .ok_or(AmmError::Overflow)?
+        .div_ceil(MAX_BPS as u128);
+
+    u64::try_from(fee).map_err(|_| AmmError::Overflow.into())
+}
+
+// `amount` in the base units of a token with more decimals
+fn scale(amount: u64, multiplier: u64) -> Result<u64> {
+    amount
+        .checked_mul(multiplier)
+        .ok_or(AmmError::Overflow.into())
 }
@@ -0,0 +1,101 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AmmError;
+
+/// Fixed-point scale of `Farm::acc_reward_per_share`
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Farm {
+    pub config: Pubkey,             // Pool whose LP tokens are staked
+    pub mint_lp: Pubkey,            // LP token of the pool
+    pub reward_mint: Pubkey,        // Token paid out as rewards
+    pub reward_per_second: u64,     // Emission rate, shared by all stakers
+    pub end_ts: i64,                // Unix timestamp the emission stops at
+    pub last_update_ts: i64,        // Unix timestamp rewards were last accrued at
+    pub acc_reward_per_share: u128, // Rewards accrued per staked LP token, scaled by REWARD_PRECISION
+    pub total_staked: u64,          // LP tokens staked in the farm
+    pub rewards_unallocated: u64,   // Funded rewards not yet accrued to stakers
+    pub bump: u8,                   // Bump seed for the farm account
+}
+
+/// A user's stake in a farm, at seeds `[b"staker", farm, owner]`
+#[account]
+#[derive(InitSpace)]
+pub struct Staker {
+    pub farm: Pubkey,      // Farm the LP tokens are staked in
+    pub owner: Pubkey,     // Owner of the stake
+    pub amount: u64,       // LP tokens staked
+    pub reward_debt: u128, // Rewards per share already accounted for, times `amount`
+    pub rewards_owed: u64, // Accrued rewards not yet harvested
+    pub bump: u8,          // Bump seed for the staker account
+}
+
+impl Farm {
+    /// Accrues the emission since the last update to the staked LP tokens. Nothing is
+    /// emitted while nothing is staked, and never more than the farm has been funded with
+    pub fn update(&mut self, now: i64) -> Result<()> {
+        let now = now.min(self.end_ts);
+        if now <= self.last_update_ts {
+            return Ok(());
+        }
+
+        if self.total_staked != 0 {
+            let elapsed = (now - self.last_update_ts) as u128;
+            let reward =
+                (elapsed * self.reward_per_second as u128).min(self.rewards_unallocated as u128);
+
+            self.acc_reward_per_share = reward
+                .checked_mul(REWARD_PRECISION)
+                .map(|n| n / self.total_staked as u128)
+                .and_then(|n| self.acc_reward_per_share.checked_add(n))
+                .ok_or(AmmError::Overflow)?;
+            self.rewards_unallocated -= reward as u64;
+        }
+
+        self.last_update_ts = now;
+
+        Ok(())
+    }
+}
+
+impl Staker {
+    fn accrued(&self, acc_reward_per_share: u128) -> Result<u128> {
+        Ok((self.amount as u128)
+            .checked_mul(acc_reward_per_share)
+            .ok_or(AmmError::Overflow)?
+            / REWARD_PRECISION)
+    }
+
+    /// Moves the rewards accrued since the last settlement into `rewards_owed`.
+    /// The farm must be updated first
+    pub fn settle(&mut self, farm: &Farm) -> Result<()> {
+        let pending = self
+            .accrued(farm.acc_reward_per_share)?
+            .checked_sub(self.reward_debt)
+            .ok_or(AmmError::Underflow)?;
+
+        self.rewards_owed = u64::try_from(pending)
+            .ok()
+            .and_then(|pending| self.rewards_owed.checked_add(pending))
+            .ok_or(AmmError::Overflow)?;
+        self.reward_debt = self.accrued(farm.acc_reward_per_share)?;
+
+        Ok(())
+    }
+
+    /// Sets the staked amount to `amount`, after settling the rewards of the old amount
+    pub fn restake(&mut self, farm: &mut Farm, amount: u64) -> Result<()> {
+        self.settle(farm)?;
+
+        farm.total_staked = (farm.total_staked as u128 + amount as u128)
+            .checked_sub(self.amount as u128)
+            .and_then(|n| u64::try_from(n).ok())
+            .ok_or(AmmError::Overflow)?;
+        self.amount = amount;
+        self.reward_debt = self.accrued(farm.acc_reward_per_share)?;
+
+        Ok(())
+    }
+}
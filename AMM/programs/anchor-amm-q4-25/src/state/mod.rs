@@ -1,3 +1,15 @@
 pub mod config;
+pub mod farm;
+pub mod oracle;
+pub mod position;
+pub mod referrer;
+pub mod registry;
+pub mod tick_array;
 
 pub use config::*;
+pub use farm::*;
+pub use oracle::*;
+pub use position::*;
+pub use referrer::*;
+pub use registry::*;
+pub use tick_array::*;
@@ -0,0 +1,147 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    concentrated::{self, Q64},
+    errors::AmmError,
+};
+
+/// Number of observations kept in the ring buffer
+pub const OBSERVATIONS: usize = 32;
+/// Minimum number of seconds between two stored observations
+pub const OBSERVATION_PERIOD: i64 = 60;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct Observation {
+    pub timestamp: i64,           // Unix timestamp of the observation
+    pub price_x_cumulative: u128, // Sum of the price of X in Y (Q64.64) times the seconds it held
+    pub price_y_cumulative: u128, // Sum of the price of Y in X (Q64.64) times the seconds it held
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Oracle {
+    pub config: Pubkey,                            // Pool the observations belong to
+    pub latest: Observation,                       // Accumulators as of the last update
+    pub index: u8,                                 // Slot of the newest stored observation
+    pub observations: [Observation; OBSERVATIONS], // Ring buffer of stored observations
+    pub bump: u8,                                  // Bump seed for the oracle account
+}
+
+/// Time-weighted average prices in Q64.64, returned by the `twap` instruction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct TwapPrice {
+    pub price_x: u128, // Average price of X in Y
+    pub price_y: u128, // Average price of Y in X
+    pub window: i64,   // Seconds actually covered, at least the requested window
+    pub end: i64,      // Unix timestamp the average runs up to, the oracle's last update
+}
+
+impl Oracle {
+    pub fn init(&mut self, config: Pubkey, bump: u8, now: i64) {
+        let latest = Observation {
+            timestamp: now,
+            ..Default::default()
+        };
+
+        self.config = config;
+        self.latest = latest;
+        self.index = 0;
+        self.observations = [Observation::default(); OBSERVATIONS];
+        self.observations[0] = latest;
+        self.bump = bump;
+    }
+
+    /// Accumulates the prices of the reserves as they stood since the last update.
+    /// Must run before a trade changes the reserves, so a price only counts once it
+    /// has survived until a later transaction
+    pub fn update(&mut self, reserve_x: u64, reserve_y: u64, now: i64) {
+        let prices = match reserve_x != 0 && reserve_y != 0 {
+            true => Some((
+                ((reserve_y as u128) << 64) / reserve_x as u128,
+                ((reserve_x as u128) << 64) / reserve_y as u128,
+            )),
+            false => None,
+        };
+
+        self.record(prices, now);
+    }
+
+    /// `update` for a concentrated liquidity pool, whose price is its Q64.64 `sqrt_price`
+    /// rather than the ratio of its reserves
+    pub fn update_sqrt_price(&mut self, sqrt_price: u128, now: i64) {
+        // Saturates at the far ends of the tick range, where a price no longer fits
+        let square =
+            |sqrt: u128| concentrated::mul_div(sqrt, sqrt, Q64, false).unwrap_or(u128::MAX);
+        let prices = concentrated::mul_div(Q64, Q64, sqrt_price, false)
+            .ok()
+            .map(|inverse| (square(sqrt_price), square(inverse)));
+
+        self.record(prices, now);
+    }
+
+    // Accumulates `prices` of X in Y and Y in X, in Q64.64, as they stood since the last update
+    fn record(&mut self, prices: Option<(u128, u128)>, now: i64) {
+        if now <= self.latest.timestamp {
+            return;
+        }
+
+        self.latest = Self::accumulate(&self.latest, prices, now);
+
+        let newest = &self.observations[self.index as usize];
+        if now - newest.timestamp >= OBSERVATION_PERIOD {
+            self.index = ((self.index as usize + 1) % OBSERVATIONS) as u8;
+            self.observations[self.index as usize] = self.latest;
+        }
+    }
+
+    /// Average prices over at least the `window` seconds before the last update, from the
+    /// stored accumulators only
+    pub fn twap(&self, window: i64) -> Result<TwapPrice> {
+        require!(window > 0, AmmError::InvalidAmount);
+
+        let current = &self.latest;
+        let target = current
+            .timestamp
+            .checked_sub(window)
+            .ok_or(AmmError::Underflow)?;
+
+        // Newest stored observation old enough to cover the window
+        let start = (0..OBSERVATIONS)
+            .map(|i| &self.observations[(self.index as usize + OBSERVATIONS - i) % OBSERVATIONS])
+            .find(|o| o.timestamp != 0 && o.timestamp <= target)
+            .ok_or(AmmError::OracleWindowTooLong)?;
+
+        let elapsed = current.timestamp - start.timestamp;
+
+        Ok(TwapPrice {
+            price_x: current
+                .price_x_cumulative
+                .wrapping_sub(start.price_x_cumulative)
+                / elapsed as u128,
+            price_y: current
+                .price_y_cumulative
+                .wrapping_sub(start.price_y_cumulative)
+                / elapsed as u128,
+            window: elapsed,
+            end: current.timestamp,
+        })
+    }
+
+    // Cumulative prices wrap on overflow, only differences between two observations matter
+    fn accumulate(from: &Observation, prices: Option<(u128, u128)>, now: i64) -> Observation {
+        let elapsed = now.saturating_sub(from.timestamp).max(0) as u128;
+        let mut next = *from;
+        next.timestamp = now.max(from.timestamp);
+
+        if let Some((price_x, price_y)) = prices {
+            next.price_x_cumulative = from
+                .price_x_cumulative
+                .wrapping_add(price_x.wrapping_mul(elapsed));
+            next.price_y_cumulative = from
+                .price_y_cumulative
+                .wrapping_add(price_y.wrapping_mul(elapsed));
+        }
+
+        next
+    }
+}
@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use crate::{concentrated, errors::AmmError};
+
+/// Liquidity provided to a concentrated liquidity pool over a price range, at seeds
+/// `[b"position", config, owner, tick_lower, tick_upper]`
+#[account]
+#[derive(InitSpace)]
+pub struct Position {
+    pub config: Pubkey,            // Pool the liquidity is provided to
+    pub owner: Pubkey,             // Owner of the position
+    pub tick_lower: i32,           // Lower bound of the price range
+    pub tick_upper: i32,           // Upper bound of the price range, excluded
+    pub liquidity: u64,            // Liquidity provided over the range
+    pub fee_growth_inside_x: u128, // Fee growth of X inside the range as of the last update
+    pub fee_growth_inside_y: u128, // Fee growth of Y inside the range as of the last update
+    pub fees_owed_x: u64,          // Fees of X accrued and not yet collected
+    pub fees_owed_y: u64,          // Fees of Y accrued and not yet collected
+    pub bump: u8,                  // Bump seed for the position account
+}
+
+impl Position {
+    /// Accrues the fees grown inside the range since the last update, then adds `delta`
+    /// to the liquidity
+    pub fn update(&mut self, delta: i64, fee_growth_inside: (u128, u128)) -> Result<()> {
+        let (inside_x, inside_y) = fee_growth_inside;
+
+        let owed_x = concentrated::fees_for_growth(
+            inside_x.wrapping_sub(self.fee_growth_inside_x),
+            self.liquidity,
+        )?;
+        let owed_y = concentrated::fees_for_growth(
+            inside_y.wrapping_sub(self.fee_growth_inside_y),
+            self.liquidity,
+        )?;
+
+        self.fees_owed_x = self
+            .fees_owed_x
+            .checked_add(owed_x)
+            .ok_or(AmmError::Overflow)?;
+        self.fees_owed_y = self
+            .fees_owed_y
+            .checked_add(owed_y)
+            .ok_or(AmmError::Overflow)?;
+        (self.fee_growth_inside_x, self.fee_growth_inside_y) = fee_growth_inside;
+
+        self.liquidity = concentrated::add_liquidity_delta(self.liquidity, delta)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+/// Integrator the pool's authority lets take a share of the swap fees of the swaps it routes,
+/// at seeds `[b"referrer", config, wallet]`
+#[account]
+#[derive(InitSpace)]
+pub struct Referrer {
+    pub config: Pubkey,    // Pool the referrer is registered with
+    pub wallet: Pubkey,    // Owner of the token accounts the referral is paid to
+    pub referral_fee: u16, // Share of the swap fee paid to the referrer, in basis points
+    pub bump: u8,          // Bump seed for the referrer account
+}
@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::AmmError, state::CurveType};
+
+/// Number of pools listed by each registry page
+pub const REGISTRY_PAGE_SIZE: usize = 64;
+
+/// Enumerable index of every pool created through the factory, at seeds `[b"registry"]`.
+/// The pools are listed in fixed-size pages, so no account grows with their number
+#[account]
+#[derive(InitSpace)]
+pub struct Registry {
+    pub admin: Pubkey,   // Factory admin, who must approve every pool created or registered
+    pub pool_count: u64, // Pools registered so far, closed ones included
+    pub bump: u8,        // Bump seed for the registry account
+}
+
+impl Registry {
+    /// Page the next registered pool is listed in
+    pub fn next_page(&self) -> u64 {
+        self.pool_count / REGISTRY_PAGE_SIZE as u64
+    }
+
+    /// Lists `config` on `page`, which must be `next_page`, and returns its position in
+    /// the registry
+    pub fn register(
+        &mut self,
+        page: &mut RegistryPage,
+        page_bump: u8,
+        config: Pubkey,
+    ) -> Result<u64> {
+        // A page is created by the pool that starts it
+        if page.pools.is_empty() {
+            page.index = self.next_page();
+            page.bump = page_bump;
+        }
+        require!(
+            page.index == self.next_page(),
+            AmmError::InvalidRegistryPage
+        );
+
+        let index = self.pool_count;
+        page.pools.push(config);
+        self.pool_count = index.checked_add(1).ok_or(AmmError::Overflow)?;
+
+        Ok(index)
+    }
+}
+
+/// Page `index` of the registry, at seeds `[b"registry_page", index]`, listing the configs of
+/// the pools registered from position `index * REGISTRY_PAGE_SIZE` on, in registration order.
+/// A closed pool's entry is reset to the default key so the positions don't move
+#[account]
+#[derive(InitSpace)]
+pub struct RegistryPage {
+    pub index: u64, // Position of the page in the registry
+    #[max_len(REGISTRY_PAGE_SIZE)]
+    pub pools: Vec<Pubkey>, // Configs of the pools listed on the page
+    pub bump: u8,           // Bump seed for the registry page account
+}
+
+impl RegistryPage {
+    /// Removes the pool at `position` in the registry from the page holding it
+    pub fn unregister(&mut self, position: u64) -> Result<()> {
+        require!(
+            position / REGISTRY_PAGE_SIZE as u64 == self.index,
+            AmmError::InvalidRegistryPage
+        );
+
+        let slot = (position % REGISTRY_PAGE_SIZE as u64) as usize;
+        let entry = self
+            .pools
+            .get_mut(slot)
+            .ok_or(AmmError::InvalidRegistryPage)?;
+        *entry = Pubkey::default();
+
+        Ok(())
+    }
+}
+
+/// The pool of a mint pair, fee tier and curve, at seeds
+/// `[b"pair", mint_a, mint_b, fee_tier, curve_type]` so that each pair has at most one pool per
+/// fee tier and curve and clients can derive its address
+#[account]
+#[derive(InitSpace)]
+pub struct Pair {
+    pub config: Pubkey,        // Config of the pool
+    pub mint_a: Pubkey,        // Lower of the pool's two mints
+    pub mint_b: Pubkey,        // Higher of the pool's two mints
+    pub fee_tier: u16,         // Fee tier in basis points, the swap fee the pool was created with
+    pub curve_type: CurveType, // Invariant the pool prices swaps with
+    pub registry_index: u64,   // Position of the pool in the registry
+    pub bump: u8,              // Bump seed for the pair account
+}
+
+impl Pair {
+    /// The two mints of a pool in canonical (ascending) order
+    pub fn canonical(mint_x: Pubkey, mint_y: Pubkey) -> (Pubkey, Pubkey) {
+        match mint_x < mint_y {
+            true => (mint_x, mint_y),
+            false => (mint_y, mint_x),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+
+use crate::{concentrated, errors::AmmError};
+
+/// Number of usable ticks in a tick array
+pub const TICK_ARRAY_SIZE: usize = 64;
+
+/// Liquidity and fee accounting of a tick, uninitialized while no position is bounded by it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct Tick {
+    pub liquidity_net: i64,         // Liquidity added when the price crosses the tick upwards
+    pub liquidity_gross: u64,       // Liquidity of the positions bounded by the tick
+    pub fee_growth_outside_x: u128, // Fee growth of X on the other side of the tick from the price
+    pub fee_growth_outside_y: u128, // Fee growth of Y on the other side of the tick from the price
+}
+
+/// `TICK_ARRAY_SIZE` consecutive usable ticks of a concentrated liquidity pool, at seeds
+/// `[b"tick_array", config, start_tick]`. Outlives a closed pool, so it's only used by one
+/// with the `tick_spacing` it was initialized for
+#[account]
+#[derive(InitSpace)]
+pub struct TickArray {
+    pub config: Pubkey,    // Pool the ticks belong to
+    pub start_tick: i32,   // First tick of the array, a multiple of its span
+    pub tick_spacing: u16, // Tick spacing of the pool the ticks were initialized for
+    #[max_len(TICK_ARRAY_SIZE)]
+    pub ticks: Vec<Tick>, // Ticks `start_tick + i * tick_spacing`
+    pub bump: u8,         // Bump seed for the tick array account
+}
+
+impl Tick {
+    pub fn is_initialized(&self) -> bool {
+        self.liquidity_gross != 0
+    }
+
+    /// Adds `delta` of liquidity bounded by the tick, below it if `upper` or above it otherwise.
+    /// A tick getting initialized at or below the price counts all fees so far as grown below it
+    pub fn update(
+        &mut self,
+        tick: i32,
+        tick_current: i32,
+        fee_growth_global: (u128, u128),
+        delta: i64,
+        upper: bool,
+    ) -> Result<()> {
+        if !self.is_initialized() && tick <= tick_current {
+            (self.fee_growth_outside_x, self.fee_growth_outside_y) = fee_growth_global;
+        }
+
+        self.liquidity_gross = concentrated::add_liquidity_delta(self.liquidity_gross, delta)?;
+        self.liquidity_net = match upper {
+            true => self.liquidity_net.checked_sub(delta),
+            false => self.liquidity_net.checked_add(delta),
+        }
+        .ok_or(AmmError::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Resets a tick no position is bounded by anymore
+    pub fn clear_if_unused(&mut self) {
+        if !self.is_initialized() {
+            *self = Tick::default();
+        }
+    }
+
+    /// Flips the fee growth outside the tick as the price crosses it, and returns the
+    /// liquidity added when crossing upwards
+    pub fn cross(&mut self, fee_growth_global: (u128, u128)) -> i64 {
+        self.fee_growth_outside_x = fee_growth_global.0.wrapping_sub(self.fee_growth_outside_x);
+        self.fee_growth_outside_y = fee_growth_global.1.wrapping_sub(self.fee_growth_outside_y);
+
+        self.liquidity_net
+    }
+}
+
+impl TickArray {
+    /// Ticks covered by an array of a pool with `tick_spacing`
+    pub fn span(tick_spacing: u16) -> i32 {
+        TICK_ARRAY_SIZE as i32 * tick_spacing as i32
+    }
+
+    /// First tick of the array holding `tick`
+    pub fn start_tick_for(tick: i32, tick_spacing: u16) -> i32 {
+        let span = Self::span(tick_spacing);
+
+        tick.div_euclid(span) * span
+    }
+
+    fn index(&self, tick: i32, tick_spacing: u16) -> Result<usize> {
+        let offset = tick - self.start_tick;
+        require!(
+            (0..Self::span(tick_spacing)).contains(&offset) && offset % tick_spacing as i32 == 0,
+            AmmError::InvalidTickArray
+        );
+
+        Ok((offset / tick_spacing as i32) as usize)
+    }
+
+    pub fn tick(&self, tick: i32, tick_spacing: u16) -> Result<&Tick> {
+        let index = self.index(tick, tick_spacing)?;
+
+        Ok(&self.ticks[index])
+    }
+
+    pub fn tick_mut(&mut self, tick: i32, tick_spacing: u16) -> Result<&mut Tick> {
+        let index = self.index(tick, tick_spacing)?;
+
+        Ok(&mut self.ticks[index])
+    }
+
+    /// Next initialized tick of the array that a swap from `tick_current` reaches: the largest
+    /// one at or below it when the price moves down, the smallest one above it otherwise
+    pub fn next_initialized(
+        &self,
+        tick_current: i32,
+        tick_spacing: u16,
+        down: bool,
+    ) -> Option<i32> {
+        let spacing = tick_spacing as i32;
+        // Index of the largest usable tick at or below the current one
+        let index = (tick_current - self.start_tick).div_euclid(spacing);
+
+        let initialized = |i: &i32| self.ticks[*i as usize].is_initialized();
+        let found = match down {
+            true => (0..=index.min(TICK_ARRAY_SIZE as i32 - 1))
+                .rev()
+                .find(initialized),
+            false => ((index + 1).max(0)..TICK_ARRAY_SIZE as i32).find(initialized),
+        };
+
+        found.map(|i| self.start_tick + i * spacing)
+    }
+}
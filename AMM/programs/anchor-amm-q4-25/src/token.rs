@@ -0,0 +1,133 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{allocate, assign, transfer, Allocate, Assign, Transfer},
+};
+use anchor_spl::{
+    token::spl_token,
+    token_2022::spl_token_2022::{self, extension::transfer_fee::TransferFeeConfig},
+    token_interface::{
+        close_account, get_mint_extension_data, initialize_account3, CloseAccount,
+        InitializeAccount3, Mint, TokenInterface,
+    },
+};
+
+use crate::errors::AmmError;
+
+fn transfer_fee_config(mint: &InterfaceAccount<Mint>) -> Option<TransferFeeConfig> {
+    let info = mint.to_account_info();
+    if *info.owner != spl_token_2022::ID {
+        return None;
+    }
+
+    get_mint_extension_data::<TransferFeeConfig>(&info).ok()
+}
+
+/// Part of a transfer of `amount` withheld by the mint's transfer fee extension, if any
+pub fn transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let Some(config) = transfer_fee_config(mint) else {
+        return Ok(0);
+    };
+
+    Ok(config
+        .calculate_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(AmmError::Overflow)?)
+}
+
+/// Amount that arrives when `amount` is sent
+pub fn amount_after_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    Ok(amount
+        .checked_sub(transfer_fee(mint, amount)?)
+        .ok_or(AmmError::Underflow)?)
+}
+
+/// Amount to send so that `amount` arrives
+pub fn amount_before_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let Some(config) = transfer_fee_config(mint) else {
+        return Ok(amount);
+    };
+
+    Ok(config
+        .get_epoch_fee(Clock::get()?.epoch)
+        .calculate_pre_fee_amount(amount)
+        .ok_or(AmmError::Overflow)?)
+}
+
+/// Whether `mint` is the native SOL mint of either token program
+pub fn is_native(mint: &InterfaceAccount<Mint>) -> bool {
+    mint.key() == spl_token::native_mint::ID || mint.key() == spl_token_2022::native_mint::ID
+}
+
+/// Seed prefix of the ephemeral wSOL account, at seeds `[NATIVE_SOL_SEED, owner]`, that native
+/// SOL is paid and received through
+pub const NATIVE_SOL_SEED: &[u8] = b"native_sol";
+
+/// Size of a token account without extensions, which native-mint accounts never have
+const NATIVE_ACCOUNT_LEN: usize = 165;
+
+/// Creates the ephemeral wSOL account of `owner` holding `amount` of its SOL, so SOL can be
+/// sent like any token. It must be closed with `close_native` before the instruction ends
+pub fn open_native<'info>(
+    owner: &Signer<'info>,
+    account: &UncheckedAccount<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    amount: u64,
+    token_program: &Interface<'info, TokenInterface>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let (address, bump) =
+        Pubkey::find_program_address(&[NATIVE_SOL_SEED, owner.key.as_ref()], &crate::ID);
+    require_keys_eq!(account.key(), address, ErrorCode::ConstraintSeeds);
+    let signer_seeds: &[&[&[u8]]] = &[&[NATIVE_SOL_SEED, owner.key.as_ref(), &[bump]]];
+
+    // Lamports sent to the address beforehand end up wrapped too, and returned on close
+    let lamports = Rent::get()?
+        .minimum_balance(NATIVE_ACCOUNT_LEN)
+        .checked_add(amount)
+        .ok_or(AmmError::Overflow)?;
+    let top_up = lamports.saturating_sub(account.lamports());
+    if top_up != 0 {
+        let cpi_accounts = Transfer {
+            from: owner.to_account_info(),
+            to: account.to_account_info(),
+        };
+        let ctx = CpiContext::new(system_program.to_account_info(), cpi_accounts);
+        transfer(ctx, top_up)?;
+    }
+
+    let cpi_accounts = Allocate {
+        account_to_allocate: account.to_account_info(),
+    };
+    let ctx =
+        CpiContext::new_with_signer(system_program.to_account_info(), cpi_accounts, signer_seeds);
+    allocate(ctx, NATIVE_ACCOUNT_LEN as u64)?;
+
+    let cpi_accounts = Assign {
+        account_to_assign: account.to_account_info(),
+    };
+    let ctx =
+        CpiContext::new_with_signer(system_program.to_account_info(), cpi_accounts, signer_seeds);
+    assign(ctx, token_program.key)?;
+
+    let cpi_accounts = InitializeAccount3 {
+        account: account.to_account_info(),
+        mint: mint.to_account_info(),
+        authority: owner.to_account_info(),
+    };
+    let ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    initialize_account3(ctx)
+}
+
+/// Closes the ephemeral wSOL account, unwrapping its whole balance and rent to its owner
+pub fn close_native<'info>(
+    owner: &Signer<'info>,
+    account: &UncheckedAccount<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let cpi_accounts = CloseAccount {
+        account: account.to_account_info(),
+        destination: owner.to_account_info(),
+        authority: owner.to_account_info(),
+    };
+    let ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    close_account(ctx)
+}
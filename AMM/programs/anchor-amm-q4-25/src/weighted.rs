@@ -0,0 +1,163 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::AmmError, state::MAX_BPS};
+
+/// Smallest weight of either side of a weighted pool, in basis points
+pub const MIN_WEIGHT: u16 = 100;
+
+// Q64.64 fixed point
+const ONE: u128 = 1 << 64;
+const FRACTION_MASK: u128 = ONE - 1;
+// ln(2) in Q64.64
+const LN_2: u128 = 12_786_308_645_202_655_660;
+// Bound on the relative error of `pow`, which is rounded against the trader by it
+const POW_ERROR_SHIFT: u32 = 40;
+
+/// Output of swapping a net `amount_in` against a weighted-product pool, rounded down:
+/// `out = B_out * (1 - (B_in / (B_in + A_in))^(w_in / w_out))`
+pub fn amount_out(
+    reserve_in: u64,
+    weight_in: u16,
+    reserve_out: u64,
+    weight_out: u16,
+    amount_in: u64,
+) -> Result<u64> {
+    require!(reserve_in != 0 && reserve_out != 0, AmmError::ZeroBalance);
+
+    let base = ratio(
+        reserve_in as u128 + amount_in as u128,
+        reserve_in as u128,
+        false,
+    )?;
+    let power = pow(base, weight_in, weight_out)?;
+    let power = power
+        .saturating_sub(power >> POW_ERROR_SHIFT)
+        .saturating_sub(1)
+        .max(ONE);
+
+    // 1 / power rounded up, so the share of the reserve paid out is rounded down
+    let inverse = u128::MAX / power + 1;
+    let share = ONE.saturating_sub(inverse);
+
+    Ok(((reserve_out as u128 * share) >> 64) as u64)
+}
+
+/// Net input needed to take exactly `amount_out` from a weighted-product pool, rounded up:
+/// `in = B_in * ((B_out / (B_out - A_out))^(w_out / w_in) - 1)`
+pub fn amount_in_for_exact_out(
+    reserve_in: u64,
+    weight_in: u16,
+    reserve_out: u64,
+    weight_out: u16,
+    amount_out: u64,
+) -> Result<u64> {
+    require!(reserve_in != 0, AmmError::ZeroBalance);
+    require!(amount_out < reserve_out, AmmError::InsufficientBalance);
+
+    let base = ratio(
+        reserve_out as u128,
+        (reserve_out - amount_out) as u128,
+        true,
+    )?;
+    let power = pow(base, weight_out, weight_in)?;
+    let power = power
+        .checked_add((power >> POW_ERROR_SHIFT) + 1)
+        .ok_or(AmmError::Overflow)?;
+
+    // B_in * (power - 1), split so that the product can't overflow
+    let growth = power - ONE;
+    let amount_in = (reserve_in as u128)
+        .checked_mul(growth >> 64)
+        .and_then(|n| n.checked_add((reserve_in as u128 * (growth & FRACTION_MASK)).div_ceil(ONE)))
+        .ok_or(AmmError::Overflow)?;
+
+    u64::try_from(amount_in).map_err(|_| AmmError::Overflow.into())
+}
+
+/// LP minted by the first deposit, the weighted geometric mean `x^w_x * y^w_y` of the reserves
+pub fn initial_liquidity(
+    reserve_x: u64,
+    weight_x: u16,
+    reserve_y: u64,
+    weight_y: u16,
+) -> Result<u64> {
+    if reserve_x == 0 || reserve_y == 0 {
+        return Ok(0);
+    }
+
+    let log = (log2((reserve_x as u128) << 64) * weight_x as u128
+        + log2((reserve_y as u128) << 64) * weight_y as u128)
+        / MAX_BPS as u128;
+    let liquidity = exp2(log).ok_or(AmmError::Overflow)? >> 64;
+
+    u64::try_from(liquidity).map_err(|_| AmmError::Overflow.into())
+}
+
+// `num / den` in Q64.64, for `num >= den` and `den` no wider than 64 bits
+fn ratio(num: u128, den: u128, round_up: bool) -> Result<u128> {
+    require!(den != 0 && den <= u64::MAX as u128, AmmError::ZeroBalance);
+
+    let integer = num / den;
+    let remainder = num % den;
+    require!(integer < ONE, AmmError::Overflow);
+
+    let fraction = match round_up {
+        true => (remainder << 64).div_ceil(den),
+        false => (remainder << 64) / den,
+    };
+
+    Ok((integer << 64) + fraction)
+}
+
+// `base^(num / den)` in Q64.64, for `base >= 1`
+fn pow(base: u128, num: u16, den: u16) -> Result<u128> {
+    require!(den != 0, AmmError::InvalidWeight);
+
+    let log = log2(base) * num as u128 / den as u128;
+
+    Ok(exp2(log).ok_or(AmmError::Overflow)?)
+}
+
+// log2 of a Q64.64 `x >= 1`, in Q64.64
+fn log2(x: u128) -> u128 {
+    if x < ONE {
+        return 0;
+    }
+
+    let integer = (127 - x.leading_zeros()) - 64;
+    let mut result = (integer as u128) << 64;
+
+    // Normalized to [1, 2) in Q1.63, so squaring it fits in a u128
+    let mut y = (x >> integer) >> 1;
+    for bit in (0..64).rev() {
+        y = (y * y) >> 63;
+        if y >= 1 << 64 {
+            y >>= 1;
+            result |= 1 << bit;
+        }
+    }
+
+    result
+}
+
+// 2^x for a Q64.64 `x`, in Q64.64, `None` if it doesn't fit
+fn exp2(x: u128) -> Option<u128> {
+    let integer = x >> 64;
+    if integer >= 63 {
+        return None;
+    }
+
+    // 2^f = e^(f * ln 2), summed as a Taylor series
+    let z = ((x & FRACTION_MASK) * LN_2) >> 64;
+    let mut term = ONE;
+    let mut sum = ONE;
+    for i in 1..=32 {
+        term = ((term * z) >> 64) / i;
+        if term == 0 {
+            break;
+        }
+        sum += term;
+    }
+
+    Some(sum << integer)
+}
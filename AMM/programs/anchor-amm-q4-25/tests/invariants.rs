@@ -0,0 +1,543 @@
+//! Property-based invariant tests of the constant-product, StableSwap and weighted pools,
+//! driving randomized sequences of deposits, swaps and withdrawals against the compiled
+//! program in LiteSVM.
+//!
+//! They need the program built first, so they're ignored by a plain `cargo test`. Run them
+//! from `AMM/` with `anchor test`, or with
+//!
+//! ```sh
+//! anchor build
+//! cargo test -p anchor-amm-q4-25 --test invariants -- --ignored
+//! ```
+
+use anchor_amm_q4_25::{accounts, instruction, Config, CurveType};
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::{
+    associated_token::get_associated_token_address,
+    token::{Mint, TokenAccount},
+};
+use litesvm::{types::TransactionResult, LiteSVM};
+use litesvm_token::{CreateAssociatedTokenAccount, CreateMint, MintTo};
+use proptest::{prelude::*, test_runner::TestCaseError};
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+
+const PROGRAM_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../target/deploy/anchor_amm_q4_25.so"
+);
+
+const USERS: usize = 3;
+// Token balance each user starts with, above any initial reserve
+const BALANCE: u64 = 10_000_000_000_000_000;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Deposit {
+        user: usize,
+        lp: u64,
+    },
+    Swap {
+        user: usize,
+        is_x: bool,
+        amount: u64,
+    },
+    Withdraw {
+        user: usize,
+        share_bps: u16,
+    },
+}
+
+struct Harness {
+    svm: LiteSVM,
+    curve: CurveType,
+    users: Vec<Keypair>,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    config: Pubkey,
+    oracle: Pubkey,
+    mint_lp: Pubkey,
+    vault_x: Pubkey,
+    vault_y: Pubkey,
+}
+
+impl Harness {
+    // A pool on `curve`, with its amplification or weight of X, seeded with `reserve_x` and
+    // `reserve_y` by the first user. Fails the case's assumptions if the curve can't hold them
+    fn new(
+        (curve, amp, weight_x): (CurveType, u64, u16),
+        fee: u16,
+        protocol_fee: u16,
+        reserve_x: u64,
+        reserve_y: u64,
+    ) -> Result<Self, TestCaseError> {
+        let mut svm = LiteSVM::new();
+        svm.add_program_from_file(anchor_amm_q4_25::ID, PROGRAM_PATH)
+            .expect("the program should be built with `anchor build` first");
+
+        let payer = Keypair::new();
+        svm.airdrop(&payer.pubkey(), 100 * LAMPORTS_PER_SOL)
+            .unwrap();
+
+        let mint_x = CreateMint::new(&mut svm, &payer)
+            .decimals(6)
+            .send()
+            .unwrap();
+        let mint_y = CreateMint::new(&mut svm, &payer)
+            .decimals(6)
+            .send()
+            .unwrap();
+
+        let users: Vec<Keypair> = (0..USERS).map(|_| Keypair::new()).collect();
+        for user in &users {
+            svm.airdrop(&user.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+            for mint in [mint_x, mint_y] {
+                let ata = CreateAssociatedTokenAccount::new(&mut svm, &payer, &mint)
+                    .owner(&user.pubkey())
+                    .send()
+                    .unwrap();
+                MintTo::new(&mut svm, &payer, &mint, &ata, BALANCE)
+                    .send()
+                    .unwrap();
+            }
+        }
+
+        let program_id = anchor_amm_q4_25::ID;
+        let pda = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &program_id).0;
+        let (mint_a, mint_b) = match mint_x < mint_y {
+            true => (mint_x, mint_y),
+            false => (mint_y, mint_x),
+        };
+        let config = pda(&[
+            b"config",
+            mint_a.as_ref(),
+            mint_b.as_ref(),
+            &fee.to_le_bytes(),
+            &[curve as u8],
+        ]);
+
+        let mut harness = Self {
+            svm,
+            curve,
+            users,
+            mint_x,
+            mint_y,
+            config,
+            oracle: pda(&[b"oracle", config.as_ref()]),
+            mint_lp: pda(&[b"lp", config.as_ref()]),
+            vault_x: get_associated_token_address(&config, &mint_x),
+            vault_y: get_associated_token_address(&config, &mint_y),
+        };
+
+        let registry = pda(&[b"registry"]);
+        let initialize_registry = Instruction {
+            program_id,
+            accounts: accounts::InitializeRegistry {
+                payer: payer.pubkey(),
+                registry,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::InitializeRegistry {}.data(),
+        };
+        harness.send(&payer, initialize_registry).unwrap();
+
+        let initialize = Instruction {
+            program_id,
+            accounts: accounts::Initialize {
+                initializer: payer.pubkey(),
+                admin: payer.pubkey(),
+                mint_x,
+                mint_y,
+                pair: pda(&[
+                    b"pair",
+                    mint_a.as_ref(),
+                    mint_b.as_ref(),
+                    &fee.to_le_bytes(),
+                    &[curve as u8],
+                ]),
+                registry,
+                registry_page: pda(&[b"registry_page", &0u64.to_le_bytes()]),
+                mint_lp: harness.mint_lp,
+                vault_x: harness.vault_x,
+                vault_y: harness.vault_y,
+                config,
+                oracle: harness.oracle,
+                token_program_x: anchor_spl::token::ID,
+                token_program_y: anchor_spl::token::ID,
+                token_program: anchor_spl::token::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::Initialize {
+                fee,
+                authority: None,
+                protocol_fee,
+                treasury: payer.pubkey(),
+                curve_type: curve,
+                amp,
+                weight_x,
+            }
+            .data(),
+        };
+        harness.send(&payer, initialize).unwrap();
+
+        // The first deposit sends `max_x` and `max_y` as they are
+        let seed_liquidity = harness.deposit_ix(0, 1, reserve_x, reserve_y);
+        let seeded = harness.run(0, seed_liquidity)?;
+        prop_assume!(seeded, "the {:?} curve can't hold these reserves", curve);
+
+        Ok(harness)
+    }
+
+    fn send(&mut self, signer: &Keypair, ix: Instruction) -> TransactionResult {
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&signer.pubkey()),
+            &[signer],
+            self.svm.latest_blockhash(),
+        );
+        let result = self.svm.send_transaction(tx);
+        // Identical transactions would otherwise be rejected as already processed
+        self.svm.expire_blockhash();
+
+        result
+    }
+
+    fn config(&self) -> Config {
+        let account = self.svm.get_account(&self.config).unwrap();
+        Config::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    fn token_amount(&self, address: &Pubkey) -> u64 {
+        self.svm
+            .get_account(address)
+            .map(|account| {
+                TokenAccount::try_deserialize(&mut account.data.as_slice())
+                    .unwrap()
+                    .amount
+            })
+            .unwrap_or(0)
+    }
+
+    fn vaults(&self) -> (u64, u64) {
+        (
+            self.token_amount(&self.vault_x),
+            self.token_amount(&self.vault_y),
+        )
+    }
+
+    // LP-owned reserves, net of the protocol fees
+    fn reserves(&self) -> Result<(u64, u64), TestCaseError> {
+        let config = self.config();
+        let (vault_x, vault_y) = self.vaults();
+        prop_assert!(
+            vault_x >= config.protocol_fees_x && vault_y >= config.protocol_fees_y,
+            "the vaults should always cover the protocol fees"
+        );
+
+        Ok((
+            vault_x - config.protocol_fees_x,
+            vault_y - config.protocol_fees_y,
+        ))
+    }
+
+    // LP shares, the minted supply plus the locked minimum liquidity
+    fn lp_shares(&self) -> u64 {
+        let account = self.svm.get_account(&self.mint_lp).unwrap();
+        let supply = Mint::try_deserialize(&mut account.data.as_slice())
+            .unwrap()
+            .supply;
+
+        supply + self.config().locked_liquidity
+    }
+
+    fn user_token(&self, user: usize, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address(&self.users[user].pubkey(), mint)
+    }
+
+    fn deposit_ix(&self, user: usize, lp: u64, max_x: u64, max_y: u64) -> Instruction {
+        Instruction {
+            program_id: anchor_amm_q4_25::ID,
+            accounts: accounts::Deposit {
+                user: self.users[user].pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                oracle: self.oracle,
+                mint_lp: self.mint_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                user_x: self.user_token(user, &self.mint_x),
+                user_y: self.user_token(user, &self.mint_y),
+                user_lp: self.user_token(user, &self.mint_lp),
+                native_sol: None,
+                token_program_x: anchor_spl::token::ID,
+                token_program_y: anchor_spl::token::ID,
+                token_program: anchor_spl::token::ID,
+                system_program: anchor_lang::system_program::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::Deposit {
+                amount: lp,
+                max_x,
+                max_y,
+                expiration: i64::MAX,
+            }
+            .data(),
+        }
+    }
+
+    fn swap_ix(&self, user: usize, is_x: bool, amount: u64) -> Instruction {
+        Instruction {
+            program_id: anchor_amm_q4_25::ID,
+            accounts: accounts::Swap {
+                user: self.users[user].pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                oracle: self.oracle,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                user_x: self.user_token(user, &self.mint_x),
+                user_y: self.user_token(user, &self.mint_y),
+                referrer: None,
+                referrer_token: None,
+                native_sol: None,
+                token_program_x: anchor_spl::token::ID,
+                token_program_y: anchor_spl::token::ID,
+                system_program: anchor_lang::system_program::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::Swap {
+                is_x,
+                amount_in: amount,
+                min_amount_out: 0,
+                expiration: i64::MAX,
+            }
+            .data(),
+        }
+    }
+
+    fn withdraw_ix(&self, user: usize, lp: u64) -> Instruction {
+        Instruction {
+            program_id: anchor_amm_q4_25::ID,
+            accounts: accounts::Withdraw {
+                user: self.users[user].pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                oracle: self.oracle,
+                mint_lp: self.mint_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                user_x: self.user_token(user, &self.mint_x),
+                user_y: self.user_token(user, &self.mint_y),
+                user_lp: self.user_token(user, &self.mint_lp),
+                native_sol: None,
+                token_program_x: anchor_spl::token::ID,
+                token_program_y: anchor_spl::token::ID,
+                token_program: anchor_spl::token::ID,
+                system_program: anchor_lang::system_program::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::Withdraw {
+                amount: lp,
+                max_x: 0,
+                max_y: 0,
+                expiration: i64::MAX,
+            }
+            .data(),
+        }
+    }
+
+    // Sends `ix` for `user` and returns whether it succeeded. A failure must be a program
+    // error, never a panic such as an `unwrap()` or an arithmetic overflow
+    fn run(&mut self, user: usize, ix: Instruction) -> Result<bool, TestCaseError> {
+        let signer = self.users[user].insecure_clone();
+
+        match self.send(&signer, ix) {
+            Ok(_) => Ok(true),
+            Err(failed) => {
+                prop_assert!(
+                    !failed.meta.logs.iter().any(|log| log.contains("panicked")),
+                    "the program panicked: {:#?}",
+                    failed.meta.logs
+                );
+                prop_assert!(
+                    matches!(
+                        failed.err,
+                        TransactionError::InstructionError(_, InstructionError::Custom(_))
+                    ),
+                    "the instruction failed without a program error: {:?}",
+                    failed.err
+                );
+
+                Ok(false)
+            }
+        }
+    }
+
+    fn apply(&mut self, op: &Op) -> Result<(), TestCaseError> {
+        let (reserve_x, reserve_y) = self.reserves()?;
+        let shares = self.lp_shares();
+
+        match *op {
+            Op::Deposit { user, lp } => {
+                let (vault_x, vault_y) = self.vaults();
+                let ix = self.deposit_ix(user, lp, u64::MAX, u64::MAX);
+                if self.run(user, ix)? {
+                    let (after_x, after_y) = self.vaults();
+                    // The LP minted is worth no more than what was paid for it
+                    prop_assert!(
+                        (after_x - vault_x) as u128 * shares as u128
+                            >= reserve_x as u128 * lp as u128,
+                        "deposit paid too little X for its LP"
+                    );
+                    prop_assert!(
+                        (after_y - vault_y) as u128 * shares as u128
+                            >= reserve_y as u128 * lp as u128,
+                        "deposit paid too little Y for its LP"
+                    );
+                }
+            }
+            Op::Swap { user, is_x, amount } => {
+                let (user_in, user_out) = match is_x {
+                    true => (
+                        self.user_token(user, &self.mint_x),
+                        self.user_token(user, &self.mint_y),
+                    ),
+                    false => (
+                        self.user_token(user, &self.mint_y),
+                        self.user_token(user, &self.mint_x),
+                    ),
+                };
+                let (in_before, out_before) =
+                    (self.token_amount(&user_in), self.token_amount(&user_out));
+
+                let ix = self.swap_ix(user, is_x, amount);
+                if self.run(user, ix)? {
+                    let (after_x, after_y) = self.reserves()?;
+                    if self.curve == CurveType::ConstantProduct {
+                        prop_assert!(
+                            after_x as u128 * after_y as u128
+                                >= reserve_x as u128 * reserve_y as u128,
+                            "swap decreased k from {} * {} to {} * {}",
+                            reserve_x,
+                            reserve_y,
+                            after_x,
+                            after_y
+                        );
+                    }
+
+                    // On any curve, swapping the output straight back returns no more than
+                    // was paid for it
+                    let in_between = self.token_amount(&user_in);
+                    let paid = in_before - in_between;
+                    let received = self.token_amount(&user_out) - out_before;
+                    let ix = self.swap_ix(user, !is_x, received);
+                    if received != 0 && self.run(user, ix)? {
+                        let returned = self.token_amount(&user_in) - in_between;
+                        prop_assert!(
+                            returned <= paid,
+                            "a round trip through the {:?} curve paid out {} for {}",
+                            self.curve,
+                            returned,
+                            paid
+                        );
+                    }
+                }
+            }
+            Op::Withdraw { user, share_bps } => {
+                let balance = self.token_amount(&self.user_token(user, &self.mint_lp));
+                let lp = (balance as u128 * share_bps as u128 / 10_000) as u64;
+
+                let (vault_x, vault_y) = self.vaults();
+                let ix = self.withdraw_ix(user, lp);
+                if self.run(user, ix)? {
+                    let (after_x, after_y) = self.vaults();
+                    // The LP burned is worth at least what was paid out for it
+                    prop_assert!(
+                        (vault_x - after_x) as u128 * shares as u128
+                            <= reserve_x as u128 * lp as u128,
+                        "withdrawal took more X than its share"
+                    );
+                    prop_assert!(
+                        (vault_y - after_y) as u128 * shares as u128
+                            <= reserve_y as u128 * lp as u128,
+                        "withdrawal took more Y than its share"
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Amounts from dust to overflowing, so both the curve and its error paths are exercised
+fn amount() -> impl Strategy<Value = u64> {
+    prop_oneof![1u64..1_000, 1_000u64..1_000_000_000_000, any::<u64>(),]
+}
+
+// The curves pools can be created with, over the amplifications and weights of X the
+// program accepts
+fn curve() -> impl Strategy<Value = (CurveType, u64, u16)> {
+    prop_oneof![
+        Just((CurveType::ConstantProduct, 0, 0)),
+        (1u64..=1_000_000).prop_map(|amp| (CurveType::StableSwap, amp, 0)),
+        (100u16..=9_900).prop_map(|weight_x| (CurveType::Weighted, 0, weight_x)),
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (0..USERS, amount()).prop_map(|(user, lp)| Op::Deposit { user, lp }),
+        (0..USERS, any::<bool>(), amount()).prop_map(|(user, is_x, amount)| Op::Swap {
+            user,
+            is_x,
+            amount
+        }),
+        (0..USERS, 1u16..=10_000).prop_map(|(user, share_bps)| Op::Withdraw { user, share_bps }),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    #[ignore = "needs the program built with `anchor build`"]
+    fn pool_invariants_hold(
+        curve in curve(),
+        fee in 0u16..=1_000,
+        protocol_fee in 0u16..=10_000,
+        reserve_x in 1_000_000u64..1_000_000_000_000_000,
+        reserve_y in 1_000_000u64..1_000_000_000_000_000,
+        ops in prop::collection::vec(op(), 1..50),
+    ) {
+        let mut harness = Harness::new(curve, fee, protocol_fee, reserve_x, reserve_y)?;
+
+        for op in &ops {
+            harness.apply(op)?;
+        }
+
+        // Every LP can still exit, together taking no more than the LP-owned reserves
+        let (reserve_x, reserve_y) = harness.reserves()?;
+        let (vault_x, vault_y) = harness.vaults();
+        for user in 0..USERS {
+            harness.apply(&Op::Withdraw { user, share_bps: 10_000 })?;
+        }
+        let (after_x, after_y) = harness.vaults();
+        prop_assert!(vault_x - after_x <= reserve_x && vault_y - after_y <= reserve_y);
+    }
+}
@@ -1,10 +1,10 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { AnchorAmmQ425 } from "../target/types/anchor_amm_q4_25";
-import { Account, createMint, getOrCreateAssociatedTokenAccount, mintTo, TOKEN_PROGRAM_ID, getAccount, transfer } from '@solana/spl-token'
+import { Account, createMint, getOrCreateAssociatedTokenAccount, mintTo, TOKEN_PROGRAM_ID, getAccount, transfer, TOKEN_2022_PROGRAM_ID, ExtensionType, getMintLen, createInitializeTransferFeeConfigInstruction, createInitializeMintInstruction, getAssociatedTokenAddressSync, NATIVE_MINT, getMint, createSyncNativeInstruction } from '@solana/spl-token'
 import { BN } from "bn.js";
 import { ASSOCIATED_PROGRAM_ID } from "@coral-xyz/anchor/dist/cjs/utils/token";
-import { Keypair, PublicKey, sendAndConfirmTransaction, SystemProgram, Transaction } from "@solana/web3.js";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, sendAndConfirmTransaction, SystemProgram, Transaction } from "@solana/web3.js";
 import assert from "assert";
 
 describe("AMM Instruction Tests", () => {
@@ -32,23 +32,96 @@ describe("AMM Instruction Tests", () => {
   }
 
   // accounts
-  const seed = new BN(Date.now());
   const fee = 500 // basis points
+  const protocol_fee = 2_000 // basis points of the swap fee
+
+  // swaps, deposits and withdrawals must land before this unix timestamp
+  const expiration = new BN(Math.floor(Date.now() / 1000) + 3600);
+
+  // curve types
+  const constantProduct = { constantProduct: {} };
+  const stableSwap = { stableSwap: {} };
+  const weighted = { weighted: {} };
+  const concentrated = { concentrated: {} };
+  // pools are keyed by their curve's position in the program's `CurveType`
+  const curveSeed = (curve: object) => Buffer.from([
+    ["constantProduct", "stableSwap", "weighted", "concentrated"].indexOf(Object.keys(curve)[0]),
+  ]);
+
+  // LP shares locked forever by the first deposit
+  const MINIMUM_LIQUIDITY = 1_000;
+
+  // pause flags
+  const PAUSE_SWAP = 1 << 0;
+  const PAUSE_DEPOSIT = 1 << 1;
+  const PAUSE_WITHDRAW = 1 << 2;
+  const PAUSE_ALL = PAUSE_SWAP | PAUSE_DEPOSIT | PAUSE_WITHDRAW;
 
   const initializer = Keypair.generate();
   const user1 = Keypair.generate();
+  const treasury = Keypair.generate();
 
   const mint_x = anchor.web3.Keypair.generate();
   const mint_y = anchor.web3.Keypair.generate();
 
-  const [config] = anchor.web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("config", "utf-8"), seed.toArrayLike(Buffer, "le", 8)],
-    program.programId,
-  );
+  // pair and config accounts are keyed by the canonically (byte-wise ascending) ordered mints,
+  // the fee tier and the curve
+  const pairSeeds = (mint_a: PublicKey, mint_b: PublicKey, fee_tier: number, curve: object) => {
+    const [lower, higher] = [mint_a, mint_b].sort((a, b) => a.toBuffer().compare(b.toBuffer()));
+    return [lower.toBuffer(), higher.toBuffer(), new BN(fee_tier).toArrayLike(Buffer, "le", 2), curveSeed(curve)];
+  }
+  const getPair = (mint_a: PublicKey, mint_b: PublicKey, fee_tier: number, curve: object = constantProduct) => {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("pair", "utf-8"), ...pairSeeds(mint_a, mint_b, fee_tier, curve)],
+      program.programId,
+    );
+  }
+  const getConfig = (mint_a: PublicKey, mint_b: PublicKey, fee_tier: number, curve: object = constantProduct) => {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("config", "utf-8"), ...pairSeeds(mint_a, mint_b, fee_tier, curve)],
+      program.programId,
+    );
+  }
+
+  const [config] = getConfig(mint_x.publicKey, mint_y.publicKey, fee);
   const [mint_lp] = anchor.web3.PublicKey.findProgramAddressSync(
     [Buffer.from("lp", "utf-8"), config.toBuffer()],
     program.programId,
   );
+  const [oracle] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("oracle", "utf-8"), config.toBuffer()],
+    program.programId,
+  );
+
+  const [registry] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("registry", "utf-8")],
+    program.programId,
+  );
+  // registry pages hold 64 pools each, and new pools are listed on the last one
+  const REGISTRY_PAGE_SIZE = 64;
+  const getRegistryPage = (page: number) => {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("registry_page", "utf-8"), new BN(page).toArrayLike(Buffer, "le", 8)],
+      program.programId,
+    );
+  }
+  const nextRegistryPage = async () => {
+    const registryAccount = await program.account.registry.fetch(registry);
+    return getRegistryPage(Math.floor(registryAccount.poolCount.toNumber() / REGISTRY_PAGE_SIZE))[0];
+  }
+
+  // events emitted by a confirmed transaction
+  const getEvents = async (tx: string) => {
+    const txInfo = await provider.connection.getTransaction(tx, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+    const parser = new anchor.EventParser(program.programId, new anchor.BorshCoder(program.idl));
+    return [...parser.parseLogs(txInfo.meta.logMessages)];
+  }
+  const findEvent = (events: anchor.Event[], name: string): any => {
+    const event = events.find((e) => e.name.toLowerCase() === name.toLowerCase());
+    assert(event, `${name} should be emitted`);
+    return event.data;
+  }
+  const [pair] = getPair(mint_x.publicKey, mint_y.publicKey, fee);
 
   const [vault_x] = getAta(config, mint_x.publicKey);
   const [vault_y] = getAta(config, mint_y.publicKey);
@@ -88,25 +161,95 @@ describe("AMM Instruction Tests", () => {
         mint_y
       );
 
+      // the registry is created once per program, so it may exist on a reused validator
+      if (!(await program.account.registry.fetchNullable(registry))) {
+        // the provider's wallet pays for it and becomes the factory admin
+        await program.methods.initializeRegistry()
+          .accountsStrict({
+            payer: provider.wallet.publicKey,
+            registry: registry,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
     } catch (e) {
       console.log(e);
     }
   });
 
   describe("Initialize Pool", () => {
+    it("Fail initialize when fee is above 100%", async () => {
+      const [badConfig] = getConfig(mint_x.publicKey, mint_y.publicKey, 10_001);
+      const [badMintLp] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("lp", "utf-8"), badConfig.toBuffer()],
+        program.programId,
+      );
+      const [badOracle] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("oracle", "utf-8"), badConfig.toBuffer()],
+        program.programId,
+      );
+
+      try {
+        await program.methods.initialize(
+          10_001,
+          initializer.publicKey,
+          protocol_fee,
+          treasury.publicKey,
+          constantProduct,
+          new BN(0),
+          0,
+        ).accountsStrict({
+          initializer: initializer.publicKey,
+          admin: provider.wallet.publicKey,
+          pair: getPair(mint_x.publicKey, mint_y.publicKey, 10_001)[0],
+          registry: registry,
+          registryPage: await nextRegistryPage(),
+          mintX: mint_x.publicKey,
+          mintY: mint_y.publicKey,
+          mintLp: badMintLp,
+          vaultX: getAta(badConfig, mint_x.publicKey)[0],
+          vaultY: getAta(badConfig, mint_y.publicKey)[0],
+          config: badConfig,
+          oracle: badOracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid fee");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidFee", "Should fail with InvalidFee error");
+      }
+    });
+
     it("Initialize pool", async () => {
       await program.methods.initialize(
-        seed,
         fee,
         initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        constantProduct,
+        new BN(0),
+        0,
       ).accountsStrict({
         initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: pair,
+        registry: registry,
+        registryPage: await nextRegistryPage(),
         mintX: mint_x.publicKey,
         mintY: mint_y.publicKey,
         mintLp: mint_lp,
         vaultX: vault_x,
         vaultY: vault_y,
         config: config,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
@@ -116,12 +259,192 @@ describe("AMM Instruction Tests", () => {
 
       // check config state
       const configAccount = await program.account.config.fetch(config);
-      assert.equal(seed.toNumber(), configAccount.seed.toNumber());
       assert.equal(fee, configAccount.fee);
+      assert.equal(fee, configAccount.feeTier);
+      assert.equal(null, configAccount.legacySeed);
+      assert.equal(protocol_fee, configAccount.protocolFee);
+      assert.equal(treasury.publicKey.toBase58(), configAccount.treasury.toBase58());
       assert.equal(initializer.publicKey.toBase58(), configAccount.authority.toBase58());
       assert.equal(mint_x.publicKey.toBase58(), configAccount.mintX.toBase58());
       assert.equal(mint_y.publicKey.toBase58(), configAccount.mintY.toBase58());
-      assert.equal(false, configAccount.locked);
+      assert.equal(0, configAccount.paused);
+
+      // the pool is discoverable from its mints and fee tier
+      const pairAccount = await program.account.pair.fetch(pair);
+      assert.equal(config.toBase58(), pairAccount.config.toBase58());
+      assert.equal(fee, pairAccount.feeTier);
+
+      const index = pairAccount.registryIndex.toNumber();
+      const [registryPage] = getRegistryPage(Math.floor(index / REGISTRY_PAGE_SIZE));
+      const registryPageAccount = await program.account.registryPage.fetch(registryPage);
+      assert(registryPageAccount.pools[index % REGISTRY_PAGE_SIZE].equals(config), "Pool should be listed in the registry");
+    });
+
+    const stablePoolAccounts = () => {
+      const [stableConfig] = getConfig(mint_x.publicKey, mint_y.publicKey, fee, stableSwap);
+      return {
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: getPair(mint_x.publicKey, mint_y.publicKey, fee, stableSwap)[0],
+        registry: registry,
+        mintX: mint_x.publicKey,
+        mintY: mint_y.publicKey,
+        mintLp: anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("lp", "utf-8"), stableConfig.toBuffer()],
+          program.programId,
+        )[0],
+        vaultX: getAta(stableConfig, mint_x.publicKey)[0],
+        vaultY: getAta(stableConfig, mint_y.publicKey)[0],
+        config: stableConfig,
+        oracle: anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("oracle", "utf-8"), stableConfig.toBuffer()],
+          program.programId,
+        )[0],
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      };
+    };
+
+    it("Fail initialize without the factory admin", async () => {
+      try {
+        await program.methods.initialize(
+          fee,
+          initializer.publicKey,
+          10_000, // a full protocol cut to the initializer, left for the admin to approve
+          initializer.publicKey,
+          stableSwap,
+          new BN(100),
+          0,
+        ).accountsStrict({
+          ...stablePoolAccounts(),
+          admin: initializer.publicKey,
+          registryPage: await nextRegistryPage(),
+        })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to a missing admin approval");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidAdmin", "Should fail with InvalidAdmin error");
+      }
+    });
+
+    it("Initialize a pool of another curve for the same pair and fee tier", async () => {
+      await program.methods.initialize(
+        fee,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        stableSwap,
+        new BN(100),
+        0,
+      ).accountsStrict({
+        ...stablePoolAccounts(),
+        registryPage: await nextRegistryPage(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const pairAccount = await program.account.pair.fetch(stablePoolAccounts().pair);
+      assert.equal(stablePoolAccounts().config.toBase58(), pairAccount.config.toBase58());
+      assert.deepEqual(pairAccount.curveType, stableSwap);
+      assert.notEqual(config.toBase58(), pairAccount.config.toBase58(), "Each curve should have its own pool");
+    });
+
+    it("Fail initialize a second pool for the same pair and fee tier", async () => {
+      const [dupConfig] = getConfig(mint_y.publicKey, mint_x.publicKey, fee);
+
+      try {
+        // the mints are swapped, but resolve to the same pair account
+        await program.methods.initialize(
+          fee,
+          initializer.publicKey,
+          protocol_fee,
+          treasury.publicKey,
+          constantProduct,
+          new BN(0),
+          0,
+        ).accountsStrict({
+          initializer: initializer.publicKey,
+          admin: provider.wallet.publicKey,
+          pair: pair,
+          registry: registry,
+          registryPage: await nextRegistryPage(),
+          mintX: mint_y.publicKey,
+          mintY: mint_x.publicKey,
+          mintLp: anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("lp", "utf-8"), dupConfig.toBuffer()],
+            program.programId,
+          )[0],
+          vaultX: getAta(dupConfig, mint_y.publicKey)[0],
+          vaultY: getAta(dupConfig, mint_x.publicKey)[0],
+          config: dupConfig,
+          oracle: anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("oracle", "utf-8"), dupConfig.toBuffer()],
+            program.programId,
+          )[0],
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to a duplicate pool");
+      } catch (e) {
+        assert(e.logs.some((log: string) => log.includes("already in use")), "Pair account should already exist");
+      }
+    });
+
+    it("Fail initialize with identical mints", async () => {
+      const [sameConfig] = getConfig(mint_x.publicKey, mint_x.publicKey, fee);
+
+      try {
+        await program.methods.initialize(
+          fee,
+          initializer.publicKey,
+          protocol_fee,
+          treasury.publicKey,
+          constantProduct,
+          new BN(0),
+          0,
+        ).accountsStrict({
+          initializer: initializer.publicKey,
+          admin: provider.wallet.publicKey,
+          pair: getPair(mint_x.publicKey, mint_x.publicKey, fee)[0],
+          registry: registry,
+          registryPage: await nextRegistryPage(),
+          mintX: mint_x.publicKey,
+          mintY: mint_x.publicKey,
+          mintLp: anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("lp", "utf-8"), sameConfig.toBuffer()],
+            program.programId,
+          )[0],
+          vaultX: getAta(sameConfig, mint_x.publicKey)[0],
+          vaultY: getAta(sameConfig, mint_x.publicKey)[0],
+          config: sameConfig,
+          oracle: anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("oracle", "utf-8"), sameConfig.toBuffer()],
+            program.programId,
+          )[0],
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to identical mints");
+      } catch (e) {
+        assert(e.error.errorCode.code === "IdenticalMints", "Should fail with IdenticalMints error");
+      }
     });
   });
 
@@ -159,7 +482,8 @@ describe("AMM Instruction Tests", () => {
         1_000_000 * 10 ** 6
       );
 
-      const amount = new BN(500_000 * 10 ** 6);
+      // On the first deposit `amount` is the minimum LP accepted
+      const amount = new BN(380_000 * 10 ** 6);
       const max_x = new BN(500_000 * 10 ** 6);
       const max_y = new BN(300_000 * 10 ** 6);
 
@@ -167,9 +491,11 @@ describe("AMM Instruction Tests", () => {
         amount,
         max_x,
         max_y,
+        expiration,
       ).accountsStrict({
         user: initializer.publicKey,
         userLp: initializer_ata_lp_address,
+        nativeSol: null,
         userX: initializer_ata_x.address,
         userY: initializer_ata_y.address,
         mintX: mint_x.publicKey,
@@ -178,6 +504,9 @@ describe("AMM Instruction Tests", () => {
         vaultX: vault_x,
         vaultY: vault_y,
         config: config,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
@@ -191,6 +520,12 @@ describe("AMM Instruction Tests", () => {
       );
 
       assert(initializer_lp_ata.amount >= amount.toNumber(), "LP amount is less than expected");
+
+      // LP is the geometric mean of the deposit, minus the permanently locked minimum liquidity
+      const configAccount = await program.account.config.fetch(config);
+      const liquidity = Math.floor(Math.sqrt(max_x.toNumber() * max_y.toNumber()));
+      assert.equal(configAccount.lockedLiquidity.toNumber(), MINIMUM_LIQUIDITY);
+      assert(Math.abs(Number(initializer_lp_ata.amount) - (liquidity - MINIMUM_LIQUIDITY)) <= 1, "LP should be the geometric mean minus the locked liquidity");
         
     });
 
@@ -235,9 +570,11 @@ describe("AMM Instruction Tests", () => {
         amount,
         max_x,
         max_y,
+        expiration,
       ).accountsStrict({
         user: user1.publicKey,
         userLp: user1_ata_lp,
+        nativeSol: null,
         userX: user1_ata_x.address,
         userY: user1_ata_y.address,
         mintX: mint_x.publicKey,
@@ -246,6 +583,9 @@ describe("AMM Instruction Tests", () => {
         vaultX: vault_x,
         vaultY: vault_y,
         config: config,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
@@ -264,9 +604,11 @@ describe("AMM Instruction Tests", () => {
         amount,
         max_x,
         max_y,
+        expiration,
       ).accountsStrict({
         user: user1.publicKey,
         userLp: user1_ata_lp,
+        nativeSol: null,
         userX: user1_ata_x.address,
         userY: user1_ata_y.address,
         mintX: mint_x.publicKey,
@@ -275,6 +617,9 @@ describe("AMM Instruction Tests", () => {
         vaultX: vault_x,
         vaultY: vault_y,
         config: config,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
@@ -302,7 +647,11 @@ describe("AMM Instruction Tests", () => {
         true, // is_x = true means swapping X for Y
         amount,
         min,
+        expiration,
       ).accountsStrict({
+        referrer: null,
+        referrerToken: null,
+        nativeSol: null,
         mintX: mint_x.publicKey,
         mintY: mint_y.publicKey,
         user: user1.publicKey,
@@ -311,7 +660,9 @@ describe("AMM Instruction Tests", () => {
         vaultX: vault_x,
         vaultY: vault_y,
         config: config,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
@@ -340,6 +691,14 @@ describe("AMM Instruction Tests", () => {
       // Assert vault balances changed correctly
       assert.equal(vault_x_change, amount.toNumber(), "Vault should gain exact X amount");
       assert.equal(vault_y_change, user_y_change, "Vault Y decrease should equal user Y increase");
+
+      // Assert the fee was charged on the input and left in the pool
+      const amount_in = BigInt(amount.toNumber()) - (BigInt(amount.toNumber()) * BigInt(fee) + BigInt(9_999)) / BigInt(10_000);
+      const k = vault_x_before.amount * vault_y_before.amount;
+      const expected_out = vault_y_before.amount - k / (vault_x_before.amount + amount_in);
+      const diff = user_y_change > expected_out ? user_y_change - expected_out : expected_out - user_y_change;
+      assert(diff <= BigInt(1), "Output should be priced on the input net of fees");
+      assert(vault_x_after.amount * vault_y_after.amount > k, "k should grow by the fee");
     });
 
     it("Swap Token Y for X (Y → X)", async () => {
@@ -356,7 +715,11 @@ describe("AMM Instruction Tests", () => {
         false, // is_x = false means swapping Y for X
         amount,
         min,
+        expiration,
       ).accountsStrict({
+        referrer: null,
+        referrerToken: null,
+        nativeSol: null,
         mintX: mint_x.publicKey,
         mintY: mint_y.publicKey,
         user: user1.publicKey,
@@ -365,7 +728,9 @@ describe("AMM Instruction Tests", () => {
         vaultX: vault_x,
         vaultY: vault_y,
         config: config,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
@@ -405,7 +770,11 @@ describe("AMM Instruction Tests", () => {
           true,
           amount,
           min,
+          expiration,
         ).accountsStrict({
+          referrer: null,
+          referrerToken: null,
+          nativeSol: null,
           mintX: mint_x.publicKey,
           mintY: mint_y.publicKey,
           user: user1.publicKey,
@@ -414,7 +783,9 @@ describe("AMM Instruction Tests", () => {
           vaultX: vault_x,
           vaultY: vault_y,
           config: config,
-          tokenProgram: TOKEN_PROGRAM_ID,
+          oracle: oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
@@ -436,7 +807,11 @@ describe("AMM Instruction Tests", () => {
           true,
           amount,
           min,
+          expiration,
         ).accountsStrict({
+          referrer: null,
+          referrerToken: null,
+          nativeSol: null,
           mintX: mint_x.publicKey,
           mintY: mint_y.publicKey,
           user: user1.publicKey,
@@ -445,7 +820,9 @@ describe("AMM Instruction Tests", () => {
           vaultX: vault_x,
           vaultY: vault_y,
           config: config,
-          tokenProgram: TOKEN_PROGRAM_ID,
+          oracle: oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
@@ -457,98 +834,61 @@ describe("AMM Instruction Tests", () => {
         assert(e.error.errorCode.code === "InvalidAmount", "Should fail with InvalidAmount error");
       }
     });
-  });
-
-  describe("Withdraw", () => {
-    it("Withdraw Liquidity", async () => {
-      const amount = new BN(100_000 * 10 ** 6); // Withdraw 100k LP tokens
-      const min_x = new BN(80_000 * 10 ** 6);
-      const min_y = new BN(48_000 * 10 ** 6);
-
-      // Get initial balances
-      const initializer_lp_before = await getAccount(provider.connection, initializer_ata_lp_address);
-      const initializer_x_before = await getAccount(provider.connection, initializer_ata_x.address);
-      const initializer_y_before = await getAccount(provider.connection, initializer_ata_y.address);
-      const vault_x_before = await getAccount(provider.connection, vault_x);
-      const vault_y_before = await getAccount(provider.connection, vault_y);
-
-      // Get LP supply before
-      const lp_mint_before = await program.provider.connection.getAccountInfo(mint_lp);
-      const lp_supply_before = initializer_lp_before.amount;
-
-      await program.methods.withdraw(
-        amount,
-        min_x,
-        min_y,
-      ).accountsStrict({
-        mintLp: mint_lp,
-        userLp: initializer_ata_lp_address,
-        mintX: mint_x.publicKey,
-        mintY: mint_y.publicKey,
-        user: initializer.publicKey,
-        userX: initializer_ata_x.address,
-        userY: initializer_ata_y.address,
-        vaultX: vault_x,
-        vaultY: vault_y,
-        config: config,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
-        systemProgram: SystemProgram.programId,
-      })
-        .signers([initializer])
-        .rpc();
-
-      // Verify balances after withdraw
-      const initializer_lp_after = await getAccount(provider.connection, initializer_ata_lp_address);
-      const initializer_x_after = await getAccount(provider.connection, initializer_ata_x.address);
-      const initializer_y_after = await getAccount(provider.connection, initializer_ata_y.address);
-      const vault_x_after = await getAccount(provider.connection, vault_x);
-      const vault_y_after = await getAccount(provider.connection, vault_y);
 
-      // Calculate changes
-      const lp_burned = initializer_lp_before.amount - initializer_lp_after.amount;
-      const x_received = initializer_x_after.amount - initializer_x_before.amount;
-      const y_received = initializer_y_after.amount - initializer_y_before.amount;
-      const vault_x_change = vault_x_before.amount - vault_x_after.amount;
-      const vault_y_change = vault_y_before.amount - vault_y_after.amount;
+    it("Fail swap when expired", async () => {
+      const amount = new BN(1_000 * 10 ** 6);
+      const min = new BN(0);
+      const expired = new BN(Math.floor(Date.now() / 1000) - 3600);
 
-      // Assert LP tokens were burned correctly
-      assert.equal(lp_burned, amount.toNumber(), "Exact LP amount should be burned");
-      
-      // Assert user received tokens
-      assert(x_received > 0, "User should receive X tokens");
-      assert(y_received > 0, "User should receive Y tokens");
-      assert(x_received >= min_x.toNumber(), "X received should meet minimum requirement");
-      assert(y_received >= min_y.toNumber(), "Y received should meet minimum requirement");
-      
-      // Assert vault balances decreased
-      assert.equal(vault_x_change, x_received, "Vault X decrease should equal user X increase");
-      assert.equal(vault_y_change, y_received, "Vault Y decrease should equal user Y increase");
+      try {
+        await program.methods.swap(
+          true,
+          amount,
+          min,
+          expired,
+        ).accountsStrict({
+          referrer: null,
+          referrerToken: null,
+          nativeSol: null,
+          mintX: mint_x.publicKey,
+          mintY: mint_y.publicKey,
+          user: user1.publicKey,
+          userX: user1_ata_x.address,
+          userY: user1_ata_y.address,
+          vaultX: vault_x,
+          vaultY: vault_y,
+          config: config,
+          oracle: oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+          .signers([user1])
+          .rpc();
 
-      // Assert proportionality (approximately)
-      const x_ratio = Number(x_received) / Number(vault_x_before.amount);
-      const y_ratio = Number(y_received) / Number(vault_y_before.amount);
-      const ratio_diff = Math.abs(x_ratio - y_ratio);
-      assert(ratio_diff < 0.01, "X and Y withdrawal should be proportional");
+        assert.fail("Should have failed due to expiration");
+      } catch (e) {
+        assert(e.error.errorCode.code === "OfferExpired", "Should fail with OfferExpired error");
+      }
     });
 
-    it("Partial Withdraw", async () => {
-      const amount = new BN(10_000 * 10 ** 6); // Withdraw only 10k LP tokens
-      const min_x = new BN(8_000 * 10 ** 6);
-      const min_y = new BN(4_800 * 10 ** 6);
+    it("Swap X for an exact amount of Y", async () => {
+      const amount_out = new BN(1_000 * 10 ** 6);
+      const max_amount_in = new BN(5_000 * 10 ** 6);
 
-      // Get initial balances
-      const user1_lp_before = await getAccount(provider.connection, user1_ata_lp);
       const user1_x_before = await getAccount(provider.connection, user1_ata_x.address);
       const user1_y_before = await getAccount(provider.connection, user1_ata_y.address);
 
-      await program.methods.withdraw(
-        amount,
-        min_x,
-        min_y,
+      await program.methods.swapExactOut(
+        true,
+        amount_out,
+        max_amount_in,
+        expiration,
       ).accountsStrict({
-        mintLp: mint_lp,
-        userLp: user1_ata_lp,
+        referrer: null,
+        referrerToken: null,
+        nativeSol: null,
         mintX: mint_x.publicKey,
         mintY: mint_y.publicKey,
         user: user1.publicKey,
@@ -557,45 +897,37 @@ describe("AMM Instruction Tests", () => {
         vaultX: vault_x,
         vaultY: vault_y,
         config: config,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
         .signers([user1])
         .rpc();
 
-      // Verify balances after withdraw
-      const user1_lp_after = await getAccount(provider.connection, user1_ata_lp);
       const user1_x_after = await getAccount(provider.connection, user1_ata_x.address);
       const user1_y_after = await getAccount(provider.connection, user1_ata_y.address);
 
-      // Calculate changes
-      const lp_burned = user1_lp_before.amount - user1_lp_after.amount;
-      const x_received = user1_x_after.amount - user1_x_before.amount;
+      const x_paid = user1_x_before.amount - user1_x_after.amount;
       const y_received = user1_y_after.amount - user1_y_before.amount;
 
-      // Assert correct amounts
-      assert.equal(lp_burned, amount.toNumber(), "Exact LP amount should be burned");
-      assert(x_received >= min_x.toNumber(), "X received should meet minimum");
-      assert(y_received >= min_y.toNumber(), "Y received should meet minimum");
-
-      // User should still have LP tokens remaining
-      assert(user1_lp_after.amount > 0, "User should have remaining LP tokens");
+      assert.equal(y_received, amount_out.toNumber(), "User should receive the exact Y amount");
+      assert(x_paid > 0, "User should pay X tokens");
+      assert(x_paid <= max_amount_in.toNumber(), "User should pay at most the maximum X amount");
     });
 
-    it("Fail withdraw when slippage exceeded", async () => {
-      const amount = new BN(10_000 * 10 ** 6);
-      const min_x = new BN(1_000_000 * 10 ** 6); // Unrealistically high
-      const min_y = new BN(1_000_000 * 10 ** 6); // Unrealistically high
-
+    it("Fail exact-out swap when max amount in is exceeded", async () => {
       try {
-        await program.methods.withdraw(
-          amount,
-          min_x,
-          min_y,
+        await program.methods.swapExactOut(
+          true,
+          new BN(1_000 * 10 ** 6),
+          new BN(1),
+          expiration,
         ).accountsStrict({
-          mintLp: mint_lp,
-          userLp: user1_ata_lp,
+          referrer: null,
+          referrerToken: null,
+          nativeSol: null,
           mintX: mint_x.publicKey,
           mintY: mint_y.publicKey,
           user: user1.publicKey,
@@ -604,32 +936,3276 @@ describe("AMM Instruction Tests", () => {
           vaultX: vault_x,
           vaultY: vault_y,
           config: config,
-          tokenProgram: TOKEN_PROGRAM_ID,
+          oracle: oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
           .signers([user1])
           .rpc();
-        
+
         assert.fail("Should have failed due to slippage");
       } catch (e) {
         assert(e.error.errorCode.code === "SlippageExceeded", "Should fail with SlippageExceeded error");
       }
     });
+  });
+
+  describe("Oracle", () => {
+    const twapAccounts = () => ({
+      config: config,
+      oracle: oracle,
+    });
 
-    it("Fail withdraw when amount is zero", async () => {
-      const amount = new BN(0);
-      const min_x = new BN(0);
-      const min_y = new BN(0);
+    it("Swaps accumulate cumulative prices", async () => {
+      const oracleAccount = await program.account.oracle.fetch(oracle);
+
+      assert.equal(config.toBase58(), oracleAccount.config.toBase58());
+      assert(oracleAccount.latest.timestamp.toNumber() > oracleAccount.observations[0].timestamp.toNumber(), "Oracle should be updated by trades");
+      assert(oracleAccount.latest.priceXCumulative.gtn(0), "Cumulative X price should grow");
+      assert(oracleAccount.latest.priceYCumulative.gtn(0), "Cumulative Y price should grow");
+    });
 
+    it("Read the TWAP over a window", async () => {
+      const twap = await program.methods.twap(new BN(1))
+        .accountsStrict(twapAccounts())
+        .view();
+
+      const oracleAccount = await program.account.oracle.fetch(oracle);
+
+      assert(twap.window.toNumber() >= 1, "TWAP should cover at least the requested window");
+      assert.equal(twap.end.toNumber(), oracleAccount.latest.timestamp.toNumber(), "TWAP should end at the last update");
+      assert(twap.priceX.gtn(0) && twap.priceY.gtn(0), "TWAP prices should be positive");
+    });
+
+    it("Fail TWAP over a window longer than the history", async () => {
       try {
-        await program.methods.withdraw(
-          amount,
-          min_x,
-          min_y,
+        await program.methods.twap(new BN(24 * 60 * 60))
+          .accountsStrict(twapAccounts())
+          .rpc();
+
+        assert.fail("Should have failed due to missing history");
+      } catch (e) {
+        assert(e.error.errorCode.code === "OracleWindowTooLong", "Should fail with OracleWindowTooLong error");
+      }
+    });
+  });
+
+  describe("Quotes", () => {
+    const quoteAccounts = () => ({
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      config: config,
+      mintLp: mint_lp,
+      vaultX: vault_x,
+      vaultY: vault_y,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+    });
+
+    it("Quote a swap and match the executed swap", async () => {
+      const amount = new BN(1_000 * 10 ** 6);
+
+      const quote = await program.methods.quoteSwap(true, amount, new BN(1), expiration, 0)
+        .accountsStrict(quoteAccounts())
+        .view();
+
+      const user_y_before = await getAccount(provider.connection, user1_ata_y.address);
+
+      await program.methods.swap(true, amount, new BN(1), expiration)
+        .accountsStrict({
+          referrer: null,
+          referrerToken: null,
+          nativeSol: null,
+          user: user1.publicKey,
+          userX: user1_ata_x.address,
+          userY: user1_ata_y.address,
+          mintX: mint_x.publicKey,
+          mintY: mint_y.publicKey,
+          vaultX: vault_x,
+          vaultY: vault_y,
+          config: config,
+          oracle: oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user1])
+        .rpc();
+
+      const user_y_after = await getAccount(provider.connection, user1_ata_y.address);
+
+      assert.equal(quote.amountIn.toString(), amount.toString());
+      assert.equal(quote.fee.toNumber(), Math.ceil(amount.toNumber() * fee / 10_000));
+      assert.equal(quote.amountReceived.toString(), (user_y_after.amount - user_y_before.amount).toString(), "Quote should match the swap output");
+    });
+
+    it("Quote a deposit and a withdrawal", async () => {
+      const lp = new BN(1_000 * 10 ** 6);
+
+      const deposit = await program.methods.quoteDeposit(lp, new BN(1_000_000 * 10 ** 6), new BN(1_000_000 * 10 ** 6), expiration)
+        .accountsStrict(quoteAccounts())
+        .view();
+      const withdraw = await program.methods.quoteWithdraw(lp, new BN(0), new BN(0), expiration)
+        .accountsStrict(quoteAccounts())
+        .view();
+
+      assert.equal(deposit.lp.toString(), lp.toString());
+      assert(deposit.amountX.gte(withdraw.amountX) && deposit.amountY.gte(withdraw.amountY), "Deposits round up and withdrawals round down");
+      assert(withdraw.amountX.gtn(0) && withdraw.amountY.gtn(0), "Withdrawal should return both tokens");
+    });
+
+    it("Fail quote when slippage exceeded", async () => {
+      try {
+        await program.methods.quoteSwap(true, new BN(1_000 * 10 ** 6), new BN(1_000_000 * 10 ** 6), expiration, 0)
+          .accountsStrict(quoteAccounts())
+          .rpc();
+
+        assert.fail("Should have failed due to slippage");
+      } catch (e) {
+        assert(e.error.errorCode.code === "SlippageExceeded", "Should fail with SlippageExceeded error");
+      }
+    });
+  });
+
+  describe("Dynamic Fees", () => {
+    const min_fee = 10;
+    const max_fee = 1_000;
+
+    const quoteAccounts = () => ({
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      config: config,
+      mintLp: mint_lp,
+      vaultX: vault_x,
+      vaultY: vault_y,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+    });
+
+    const quoteFee = async () =>
+      (await program.methods.quoteSwap(true, new BN(10 ** 6), new BN(1), expiration, 0)
+        .accountsStrict(quoteAccounts())
+        .view()).feeBps;
+
+    it("Fail set dynamic fee from a non-authority", async () => {
+      try {
+        await program.methods.setDynamicFee(true, min_fee, max_fee)
+          .accountsStrict({ authority: user1.publicKey, config: config })
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid authority");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidAuthority", "Should fail with InvalidAuthority error");
+      }
+    });
+
+    it("Fail set dynamic fee with a minimum above the maximum", async () => {
+      try {
+        await program.methods.setDynamicFee(true, max_fee, min_fee)
+          .accountsStrict({ authority: initializer.publicKey, config: config })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid fee bounds");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidFee", "Should fail with InvalidFee error");
+      }
+    });
+
+    it("Raise the fee after a volatile swap", async () => {
+      await program.methods.setDynamicFee(true, min_fee, max_fee)
+        .accountsStrict({ authority: initializer.publicKey, config: config })
+        .signers([initializer])
+        .rpc();
+
+      const fee_before = await quoteFee();
+      assert(fee_before >= min_fee && fee_before <= max_fee, "Fee should stay within its bounds");
+
+      const vault = await getAccount(provider.connection, vault_x);
+      await program.methods.swap(true, new BN((vault.amount / BigInt(10)).toString()), new BN(1), expiration)
+        .accountsStrict({
+          referrer: null,
+          referrerToken: null,
+          nativeSol: null,
+          user: user1.publicKey,
+          userX: user1_ata_x.address,
+          userY: user1_ata_y.address,
+          mintX: mint_x.publicKey,
+          mintY: mint_y.publicKey,
+          vaultX: vault_x,
+          vaultY: vault_y,
+          config: config,
+          oracle: oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user1])
+        .rpc();
+
+      const fee_after = await quoteFee();
+      assert(fee_after > fee_before || fee_after === max_fee, "Fee should rise with the volatility");
+      assert(fee_after <= max_fee, "Fee should not exceed the maximum");
+
+      const configAccount = await program.account.config.fetch(config);
+      assert(configAccount.volatility.gtn(0), "Swap should be recorded in the volatility accumulator");
+    });
+
+    it("Disable the dynamic fee", async () => {
+      await program.methods.setDynamicFee(false, min_fee, max_fee)
+        .accountsStrict({ authority: initializer.publicKey, config: config })
+        .signers([initializer])
+        .rpc();
+
+      assert.equal(await quoteFee(), fee, "The static fee should apply again");
+    });
+  });
+
+  describe("Referral Fees", () => {
+    const referrer = Keypair.generate();
+    const max_referral_fee = 2_000; // basis points of the swap fee
+    const referral_fee = 1_000;
+
+    let referrer_ata_x: Account;
+    let referrer_ata_y: Account;
+
+    const getReferrer = (wallet: PublicKey) => anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("referrer", "utf-8"), config.toBuffer(), wallet.toBuffer()],
+      program.programId,
+    );
+
+    const swapAccounts = (referrerAccount: PublicKey | null, referrerToken: PublicKey | null) => ({
+      user: user1.publicKey,
+      userX: user1_ata_x.address,
+      userY: user1_ata_y.address,
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      vaultX: vault_x,
+      vaultY: vault_y,
+      config: config,
+      oracle: oracle,
+      referrer: referrerAccount,
+      referrerToken: referrerToken,
+      nativeSol: null,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    const setReferrer = (wallet: PublicKey, share: number, authority: Keypair = initializer) =>
+      program.methods.setReferrer(share)
+        .accountsStrict({
+          authority: authority.publicKey,
+          config: config,
+          wallet: wallet,
+          referrer: getReferrer(wallet)[0],
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+    before("Setup referrer accounts", async () => {
+      referrer_ata_x = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer,
+        mint_x.publicKey,
+        referrer.publicKey
+      );
+      referrer_ata_y = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer,
+        mint_y.publicKey,
+        referrer.publicKey
+      );
+    });
+
+    it("Fail register a referrer above the pool's maximum", async () => {
+      try {
+        await setReferrer(referrer.publicKey, referral_fee);
+
+        assert.fail("Should have failed due to invalid referral fee");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidReferralFee", "Should fail with InvalidReferralFee error");
+      }
+    });
+
+    it("Fail register a referrer from a non-authority", async () => {
+      try {
+        await setReferrer(referrer.publicKey, 0, user1);
+
+        assert.fail("Should have failed due to invalid authority");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidAuthority", "Should fail with InvalidAuthority error");
+      }
+    });
+
+    it("Fail referral from a referrer that isn't registered", async () => {
+      try {
+        await program.methods.swap(true, new BN(1_000 * 10 ** 6), new BN(1), expiration)
+          .accountsStrict(swapAccounts(getReferrer(referrer.publicKey)[0], referrer_ata_x.address))
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to an unregistered referrer");
+      } catch (e) {
+        assert(e.error.errorCode.code === "AccountNotInitialized", "Should fail with AccountNotInitialized error");
+      }
+    });
+
+    it("Swap without a referrer", async () => {
+      const user1_x_before = await getAccount(provider.connection, user1_ata_x.address);
+
+      await program.methods.swap(true, new BN(1_000 * 10 ** 6), new BN(1), expiration)
+        .accountsStrict(swapAccounts(null, null))
+        .signers([user1])
+        .rpc();
+
+      const user1_x_after = await getAccount(provider.connection, user1_ata_x.address);
+      assert.equal(Number(user1_x_before.amount - user1_x_after.amount), 1_000 * 10 ** 6);
+    });
+
+    it("Pay the referrer a share of the swap fee", async () => {
+      await program.methods.setMaxReferralFee(max_referral_fee)
+        .accountsStrict({ authority: initializer.publicKey, config: config })
+        .signers([initializer])
+        .rpc();
+      await setReferrer(referrer.publicKey, referral_fee);
+
+      const referrerAccount = await program.account.referrer.fetch(getReferrer(referrer.publicKey)[0]);
+      assert.equal(referrerAccount.wallet.toBase58(), referrer.publicKey.toBase58());
+      assert.equal(referrerAccount.referralFee, referral_fee);
+
+      const amount = 1_000 * 10 ** 6;
+      const quote = await program.methods.quoteSwap(true, new BN(amount), new BN(1), expiration, referral_fee)
+        .accountsStrict({
+          mintX: mint_x.publicKey,
+          mintY: mint_y.publicKey,
+          config: config,
+          mintLp: mint_lp,
+          vaultX: vault_x,
+          vaultY: vault_y,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+        })
+        .view();
+
+      const tx = await program.methods.swap(true, new BN(amount), new BN(1), expiration)
+        .accountsStrict(swapAccounts(getReferrer(referrer.publicKey)[0], referrer_ata_x.address))
+        .signers([user1])
+        .rpc({ commitment: "confirmed" });
+
+      const swap_fee = Math.ceil(amount * fee / 10_000);
+      const expected = Math.floor(swap_fee * referral_fee / 10_000);
+
+      const referrer_x = await getAccount(provider.connection, referrer_ata_x.address);
+      assert.equal(Number(referrer_x.amount), expected, "Referrer should receive its share of the fee");
+
+      // the payout is emitted for integrators to reconcile
+      const event = findEvent(await getEvents(tx), "ReferralPaid");
+      assert.equal(event.amount.toString(), expected.toString());
+      assert.equal(event.referrer.toBase58(), referrer_ata_x.address.toBase58());
+      assert.equal(quote.referral.toString(), expected.toString(), "The quote should match the payout");
+    });
+
+    it("Fail referral paid in the output token", async () => {
+      try {
+        await program.methods.swap(true, new BN(1_000 * 10 ** 6), new BN(1), expiration)
+          .accountsStrict(swapAccounts(getReferrer(referrer.publicKey)[0], referrer_ata_y.address))
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid referrer token");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidToken", "Should fail with InvalidToken error");
+      }
+    });
+
+    it("Fail referral paid to another wallet than the referrer's", async () => {
+      try {
+        await program.methods.swap(true, new BN(1_000 * 10 ** 6), new BN(1), expiration)
+          .accountsStrict(swapAccounts(getReferrer(referrer.publicKey)[0], user1_ata_x.address))
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid referrer token");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidReferrer", "Should fail with InvalidReferrer error");
+      }
+    });
+
+    it("Fail a swap referring itself", async () => {
+      await setReferrer(user1.publicKey, referral_fee);
+
+      try {
+        await program.methods.swap(true, new BN(1_000 * 10 ** 6), new BN(1), expiration)
+          .accountsStrict(swapAccounts(getReferrer(user1.publicKey)[0], user1_ata_x.address))
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to a self-referral");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidReferrer", "Should fail with InvalidReferrer error");
+      }
+    });
+  });
+
+  describe("Events", () => {
+    const poolAccounts = () => ({
+      user: user1.publicKey,
+      userX: user1_ata_x.address,
+      userY: user1_ata_y.address,
+      nativeSol: null,
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      vaultX: vault_x,
+      vaultY: vault_y,
+      config: config,
+      oracle: oracle,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    // LP-owned reserves, i.e. the vault balances minus the accrued protocol fees
+    const getReserves = async () => {
+      const configAccount = await program.account.config.fetch(config);
+      const vault_x_account = await getAccount(provider.connection, vault_x);
+      const vault_y_account = await getAccount(provider.connection, vault_y);
+
+      return [
+        (vault_x_account.amount - BigInt(configAccount.protocolFeesX.toString())).toString(),
+        (vault_y_account.amount - BigInt(configAccount.protocolFeesY.toString())).toString(),
+      ];
+    };
+
+    it("Emit the amounts, fee and post-trade reserves of a swap", async () => {
+      const amount = 1_000 * 10 ** 6;
+
+      const user_y_before = await getAccount(provider.connection, user1_ata_y.address);
+
+      const tx = await program.methods.swap(true, new BN(amount), new BN(1), expiration)
+        .accountsStrict({ referrer: null, referrerToken: null, ...poolAccounts() })
+        .signers([user1])
+        .rpc({ commitment: "confirmed" });
+
+      const user_y_after = await getAccount(provider.connection, user1_ata_y.address);
+      const event = findEvent(await getEvents(tx), "Swapped");
+
+      assert.equal(event.config.toBase58(), config.toBase58());
+      assert.equal(event.user.toBase58(), user1.publicKey.toBase58());
+      assert.equal(event.mintIn.toBase58(), mint_x.publicKey.toBase58());
+      assert.equal(event.mintOut.toBase58(), mint_y.publicKey.toBase58());
+      assert.equal(event.amountIn.toNumber(), amount);
+      assert.equal(event.amountOut.toString(), (user_y_after.amount - user_y_before.amount).toString());
+      assert(event.fee.toNumber() > 0, "Swap fee should be reported");
+      assert(event.protocolFee.lte(event.fee), "Protocol fee should be part of the swap fee");
+      assert.deepEqual([event.reserveX.toString(), event.reserveY.toString()], await getReserves());
+    });
+
+    it("Emit the LP minted and reserves of a deposit", async () => {
+      const lp_before = await getAccount(provider.connection, user1_ata_lp);
+
+      const tx = await program.methods.deposit(
+        new BN(1_000 * 10 ** 6),
+        new BN(100_000 * 10 ** 6),
+        new BN(100_000 * 10 ** 6),
+        expiration,
+      ).accountsStrict({ userLp: user1_ata_lp, mintLp: mint_lp, ...poolAccounts() })
+        .signers([user1])
+        .rpc({ commitment: "confirmed" });
+
+      const lp_after = await getAccount(provider.connection, user1_ata_lp);
+      const lp_mint = await getMint(provider.connection, mint_lp);
+      const event = findEvent(await getEvents(tx), "LiquidityAdded");
+
+      assert.equal(event.user.toBase58(), user1.publicKey.toBase58());
+      assert.equal(event.lpMinted.toString(), (lp_after.amount - lp_before.amount).toString());
+      assert.equal(event.lpSupply.toString(), lp_mint.supply.toString());
+      assert(event.amountX.toNumber() > 0 && event.amountY.toNumber() > 0, "Both tokens should be deposited");
+      assert.deepEqual([event.reserveX.toString(), event.reserveY.toString()], await getReserves());
+    });
+
+    it("Emit the LP burned and reserves of a withdrawal", async () => {
+      const user_x_before = await getAccount(provider.connection, user1_ata_x.address);
+      const user_y_before = await getAccount(provider.connection, user1_ata_y.address);
+
+      const tx = await program.methods.withdraw(
+        new BN(1_000 * 10 ** 6),
+        new BN(1),
+        new BN(1),
+        expiration,
+      ).accountsStrict({ userLp: user1_ata_lp, mintLp: mint_lp, ...poolAccounts() })
+        .signers([user1])
+        .rpc({ commitment: "confirmed" });
+
+      const user_x_after = await getAccount(provider.connection, user1_ata_x.address);
+      const user_y_after = await getAccount(provider.connection, user1_ata_y.address);
+      const lp_mint = await getMint(provider.connection, mint_lp);
+      const event = findEvent(await getEvents(tx), "LiquidityRemoved");
+
+      assert.equal(event.lpBurned.toNumber(), 1_000 * 10 ** 6);
+      assert.equal(event.lpSupply.toString(), lp_mint.supply.toString());
+      assert.equal(event.amountX.toString(), (user_x_after.amount - user_x_before.amount).toString());
+      assert.equal(event.amountY.toString(), (user_y_after.amount - user_y_before.amount).toString());
+      assert.deepEqual([event.reserveX.toString(), event.reserveY.toString()], await getReserves());
+    });
+
+    it("Emit the settings left by an admin instruction", async () => {
+      const configAccount = await program.account.config.fetch(config);
+
+      const tx = await program.methods.updateFee(configAccount.fee, configAccount.protocolFee)
+        .accountsStrict({ authority: initializer.publicKey, config })
+        .signers([initializer])
+        .rpc({ commitment: "confirmed" });
+
+      const event = findEvent(await getEvents(tx), "ConfigUpdated");
+
+      assert.equal(event.config.toBase58(), config.toBase58());
+      assert.equal(event.authority.toBase58(), initializer.publicKey.toBase58());
+      assert.equal(event.fee, configAccount.fee);
+      assert.equal(event.protocolFee, configAccount.protocolFee);
+      assert.equal(event.paused, configAccount.paused);
+    });
+  });
+
+  describe("Single-Sided Liquidity", () => {
+    const poolAccounts = () => ({
+      user: user1.publicKey,
+      userLp: user1_ata_lp,
+      nativeSol: null,
+      userX: user1_ata_x.address,
+      userY: user1_ata_y.address,
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      mintLp: mint_lp,
+      vaultX: vault_x,
+      vaultY: vault_y,
+      config: config,
+      oracle: oracle,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    let zapped_lp: bigint;
+
+    it("Deposit only token X", async () => {
+      const amount = new BN(10_000 * 10 ** 6);
+
+      const lp_before = await getAccount(provider.connection, user1_ata_lp);
+      const vault_x_before = await getAccount(provider.connection, vault_x);
+      const vault_y_before = await getAccount(provider.connection, vault_y);
+      const config_before = await program.account.config.fetch(config);
+
+      await program.methods.depositSingle(
+        true,
+        amount,
+        new BN(1),
+        expiration,
+      ).accountsStrict(poolAccounts())
+        .signers([user1])
+        .rpc();
+
+      const lp_after = await getAccount(provider.connection, user1_ata_lp);
+      const vault_x_after = await getAccount(provider.connection, vault_x);
+      const vault_y_after = await getAccount(provider.connection, vault_y);
+
+      zapped_lp = lp_after.amount - lp_before.amount;
+      assert(zapped_lp > 0, "User should receive LP tokens");
+      assert.equal(vault_x_after.amount - vault_x_before.amount, BigInt(amount.toString()), "Vault X should receive the whole deposit");
+      assert.equal(vault_y_after.amount, vault_y_before.amount, "Vault Y should not change");
+
+      const config_after = await program.account.config.fetch(config);
+      assert(config_after.volatility.gt(config_before.volatility), "The internal swap should feed the dynamic fee");
+    });
+
+    it("Fail deposit single when min LP exceeded", async () => {
+      try {
+        await program.methods.depositSingle(
+          true,
+          new BN(10_000 * 10 ** 6),
+          new BN(1_000_000 * 10 ** 6),
+          expiration,
+        ).accountsStrict(poolAccounts())
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to slippage");
+      } catch (e) {
+        assert(e.error.errorCode.code === "SlippageExceeded", "Should fail with SlippageExceeded error");
+      }
+    });
+
+    it("Withdraw only token Y", async () => {
+      const user_y_before = await getAccount(provider.connection, user1_ata_y.address);
+      const vault_x_before = await getAccount(provider.connection, vault_x);
+      const config_before = await program.account.config.fetch(config);
+
+      await program.methods.withdrawSingle(
+        false,
+        new BN(zapped_lp.toString()),
+        new BN(1),
+        expiration,
+      ).accountsStrict(poolAccounts())
+        .signers([user1])
+        .rpc();
+
+      const user_y_after = await getAccount(provider.connection, user1_ata_y.address);
+      const vault_x_after = await getAccount(provider.connection, vault_x);
+
+      assert(user_y_after.amount > user_y_before.amount, "User should receive Y tokens");
+      assert.equal(vault_x_after.amount, vault_x_before.amount, "Vault X should not change");
+
+      const config_after = await program.account.config.fetch(config);
+      assert(config_after.volatility.gt(config_before.volatility), "The internal swap should feed the dynamic fee");
+    });
+
+    it("Fail withdraw single when min out exceeded", async () => {
+      try {
+        await program.methods.withdrawSingle(
+          true,
+          new BN(1_000 * 10 ** 6),
+          new BN(1_000_000 * 10 ** 6),
+          expiration,
+        ).accountsStrict(poolAccounts())
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to slippage");
+      } catch (e) {
+        assert(e.error.errorCode.code === "SlippageExceeded", "Should fail with SlippageExceeded error");
+      }
+    });
+  });
+
+  describe("Routed Swap", () => {
+    const mint_z = Keypair.generate();
+
+    const [config_yz] = getConfig(mint_y.publicKey, mint_z.publicKey, fee);
+    const [mint_lp_yz] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("lp", "utf-8"), config_yz.toBuffer()],
+      program.programId,
+    );
+    const [oracle_yz] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle", "utf-8"), config_yz.toBuffer()],
+      program.programId,
+    );
+    const [pair_yz] = getPair(mint_y.publicKey, mint_z.publicKey, fee);
+    const [vault_yz_y] = getAta(config_yz, mint_y.publicKey);
+    const [vault_yz_z] = getAta(config_yz, mint_z.publicKey);
+
+    let initializer_ata_z: Account;
+    let user1_ata_z: Account;
+
+    before("Setup Y/Z pool", async () => {
+      await createMint(
+        provider.connection,
+        initializer,
+        initializer.publicKey,
+        null,
+        6,
+        mint_z
+      );
+
+      initializer_ata_z = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer,
+        mint_z.publicKey,
+        initializer.publicKey
+      );
+      user1_ata_z = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        user1,
+        mint_z.publicKey,
+        user1.publicKey
+      );
+
+      await mintTo(
+        provider.connection,
+        initializer,
+        mint_z.publicKey,
+        initializer_ata_z.address,
+        initializer,
+        1_000_000 * 10 ** 6
+      );
+
+      await program.methods.initialize(
+        fee,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        constantProduct,
+        new BN(0),
+        0,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: pair_yz,
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        mintX: mint_y.publicKey,
+        mintY: mint_z.publicKey,
+        mintLp: mint_lp_yz,
+        vaultX: vault_yz_y,
+        vaultY: vault_yz_z,
+        config: config_yz,
+        oracle: oracle_yz,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([initializer])
+        .rpc();
+
+      await program.methods.deposit(
+        new BN(99_000 * 10 ** 6),
+        new BN(100_000 * 10 ** 6),
+        new BN(100_000 * 10 ** 6),
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: getAta(initializer.publicKey, mint_lp_yz)[0],
+        nativeSol: null,
+        userX: initializer_ata_y.address,
+        userY: initializer_ata_z.address,
+        mintX: mint_y.publicKey,
+        mintY: mint_z.publicKey,
+        mintLp: mint_lp_yz,
+        vaultX: vault_yz_y,
+        vaultY: vault_yz_z,
+        config: config_yz,
+        oracle: oracle_yz,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([initializer])
+        .rpc();
+    });
+
+    const hops = () => [
+      { pubkey: config, isSigner: false, isWritable: true },
+      { pubkey: oracle, isSigner: false, isWritable: true },
+      { pubkey: vault_x, isSigner: false, isWritable: true },
+      { pubkey: vault_y, isSigner: false, isWritable: true },
+      { pubkey: mint_y.publicKey, isSigner: false, isWritable: false },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: config_yz, isSigner: false, isWritable: true },
+      { pubkey: oracle_yz, isSigner: false, isWritable: true },
+      { pubkey: vault_yz_y, isSigner: false, isWritable: true },
+      { pubkey: vault_yz_z, isSigner: false, isWritable: true },
+      { pubkey: mint_z.publicKey, isSigner: false, isWritable: false },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    ];
+
+    it("Swap X for Z through the X/Y and Y/Z pools", async () => {
+      const amount_in = new BN(1_000 * 10 ** 6);
+
+      const user1_x_before = await getAccount(provider.connection, user1_ata_x.address);
+      const user1_y_before = await getAccount(provider.connection, user1_ata_y.address);
+      const vault_yz_y_before = await getAccount(provider.connection, vault_yz_y);
+
+      await program.methods.swapRoute(
+        amount_in,
+        new BN(1),
+        expiration,
+      ).accountsStrict({
+        user: user1.publicKey,
+        mintIn: mint_x.publicKey,
+        userIn: user1_ata_x.address,
+        userOut: user1_ata_z.address,
+        tokenProgramIn: TOKEN_PROGRAM_ID,
+      })
+        .remainingAccounts(hops())
+        .signers([user1])
+        .rpc();
+
+      const user1_x_after = await getAccount(provider.connection, user1_ata_x.address);
+      const user1_y_after = await getAccount(provider.connection, user1_ata_y.address);
+      const user1_z_after = await getAccount(provider.connection, user1_ata_z.address);
+      const vault_yz_y_after = await getAccount(provider.connection, vault_yz_y);
+
+      assert.equal(user1_x_before.amount - user1_x_after.amount, amount_in.toNumber(), "User should pay exact X amount");
+      assert.equal(user1_y_after.amount, user1_y_before.amount, "Intermediate Y should not touch the user");
+      assert(user1_z_after.amount > 0, "User should receive Z tokens");
+      assert(vault_yz_y_after.amount > vault_yz_y_before.amount, "Y/Z pool should receive the intermediate Y");
+    });
+
+    it("Fail routed swap when slippage exceeded", async () => {
+      try {
+        await program.methods.swapRoute(
+          new BN(1_000 * 10 ** 6),
+          new BN(1_000_000 * 10 ** 6),
+          expiration,
+        ).accountsStrict({
+          user: user1.publicKey,
+          mintIn: mint_x.publicKey,
+          userIn: user1_ata_x.address,
+          userOut: user1_ata_z.address,
+          tokenProgramIn: TOKEN_PROGRAM_ID,
+        })
+          .remainingAccounts(hops())
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to slippage");
+      } catch (e) {
+        assert(e.error.errorCode.code === "SlippageExceeded", "Should fail with SlippageExceeded error");
+      }
+    });
+  });
+
+  describe("Token-2022 Pool", () => {
+    const transfer_fee_bps = 100;
+
+    // mint_a charges a transfer fee, mint_b has no extensions
+    const mint_a = Keypair.generate();
+    const mint_b = Keypair.generate();
+
+    const getAta2022 = (owner: PublicKey, mint: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, owner, true, TOKEN_2022_PROGRAM_ID);
+
+    const [config_2022] = getConfig(mint_a.publicKey, mint_b.publicKey, fee);
+    const [mint_lp_2022] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("lp", "utf-8"), config_2022.toBuffer()],
+      program.programId,
+    );
+    const [oracle_2022] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle", "utf-8"), config_2022.toBuffer()],
+      program.programId,
+    );
+    const [pair_2022] = getPair(mint_a.publicKey, mint_b.publicKey, fee);
+    const vault_a = getAta2022(config_2022, mint_a.publicKey);
+    const vault_b = getAta2022(config_2022, mint_b.publicKey);
+
+    let initializer_ata_a: Account;
+    let initializer_ata_b: Account;
+
+    const transferFee = (amount: bigint) =>
+      (amount * BigInt(transfer_fee_bps) + BigInt(9_999)) / BigInt(10_000);
+
+    const poolAccounts = () => ({
+      mintX: mint_a.publicKey,
+      mintY: mint_b.publicKey,
+      vaultX: vault_a,
+      vaultY: vault_b,
+      config: config_2022,
+      oracle: oracle_2022,
+      tokenProgramX: TOKEN_2022_PROGRAM_ID,
+      tokenProgramY: TOKEN_2022_PROGRAM_ID,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    before("Setup Token-2022 mints", async () => {
+      const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+      const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+
+      const tx = new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: initializer.publicKey,
+          newAccountPubkey: mint_a.publicKey,
+          space: mintLen,
+          lamports,
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeTransferFeeConfigInstruction(
+          mint_a.publicKey,
+          initializer.publicKey,
+          initializer.publicKey,
+          transfer_fee_bps,
+          BigInt(1_000_000 * 10 ** 6),
+          TOKEN_2022_PROGRAM_ID,
+        ),
+        createInitializeMintInstruction(
+          mint_a.publicKey,
+          6,
+          initializer.publicKey,
+          null,
+          TOKEN_2022_PROGRAM_ID,
+        ),
+      );
+      await sendAndConfirmTransaction(provider.connection, tx, [initializer, mint_a]);
+
+      await createMint(
+        provider.connection,
+        initializer,
+        initializer.publicKey,
+        null,
+        6,
+        mint_b,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      initializer_ata_a = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer,
+        mint_a.publicKey,
+        initializer.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      initializer_ata_b = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer,
+        mint_b.publicKey,
+        initializer.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      for (const [mint, ata] of [[mint_a, initializer_ata_a], [mint_b, initializer_ata_b]] as [Keypair, Account][]) {
+        await mintTo(
+          provider.connection,
+          initializer,
+          mint.publicKey,
+          ata.address,
+          initializer,
+          1_000_000 * 10 ** 6,
+          [],
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        );
+      }
+    });
+
+    it("Initialize and deposit into a Token-2022 pool", async () => {
+      await program.methods.initialize(
+        fee,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        constantProduct,
+        new BN(0),
+        0,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: pair_2022,
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        mintLp: mint_lp_2022,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const max_a = new BN(100_000 * 10 ** 6);
+      const max_b = new BN(100_000 * 10 ** 6);
+
+      await program.methods.deposit(
+        new BN(90_000 * 10 ** 6),
+        max_a,
+        max_b,
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: getAta2022(initializer.publicKey, mint_lp_2022),
+        nativeSol: null,
+        userX: initializer_ata_a.address,
+        userY: initializer_ata_b.address,
+        mintLp: mint_lp_2022,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const vault_a_after = await getAccount(provider.connection, vault_a, undefined, TOKEN_2022_PROGRAM_ID);
+      const vault_b_after = await getAccount(provider.connection, vault_b, undefined, TOKEN_2022_PROGRAM_ID);
+
+      const sent_a = BigInt(max_a.toString());
+      assert.equal(vault_a_after.amount, sent_a - transferFee(sent_a), "Vault should hold the amount net of transfer fee");
+      assert.equal(vault_b_after.amount, BigInt(max_b.toString()), "Vault should hold the full amount without transfer fee");
+    });
+
+    it("Swap a transfer fee token, priced on the amount received", async () => {
+      const amount = new BN(1_000 * 10 ** 6);
+
+      const vault_a_before = await getAccount(provider.connection, vault_a, undefined, TOKEN_2022_PROGRAM_ID);
+      const vault_b_before = await getAccount(provider.connection, vault_b, undefined, TOKEN_2022_PROGRAM_ID);
+      const user_b_before = await getAccount(provider.connection, initializer_ata_b.address, undefined, TOKEN_2022_PROGRAM_ID);
+
+      await program.methods.swap(
+        true,
+        amount,
+        new BN(1),
+        expiration,
+      ).accountsStrict({
+        referrer: null,
+        referrerToken: null,
+        nativeSol: null,
+        user: initializer.publicKey,
+        userX: initializer_ata_a.address,
+        userY: initializer_ata_b.address,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const vault_a_after = await getAccount(provider.connection, vault_a, undefined, TOKEN_2022_PROGRAM_ID);
+      const user_b_after = await getAccount(provider.connection, initializer_ata_b.address, undefined, TOKEN_2022_PROGRAM_ID);
+
+      const sent = BigInt(amount.toString());
+      const received = sent - transferFee(sent);
+      assert.equal(vault_a_after.amount - vault_a_before.amount, received, "Vault should receive the amount net of transfer fee");
+
+      const amount_in = received - (received * BigInt(fee) + BigInt(9_999)) / BigInt(10_000);
+      const k = vault_a_before.amount * vault_b_before.amount;
+      const expected_out = vault_b_before.amount - k / (vault_a_before.amount + amount_in);
+      const out = user_b_after.amount - user_b_before.amount;
+      const diff = out > expected_out ? out - expected_out : expected_out - out;
+      assert(diff <= BigInt(1), "Output should be priced on the amount the vault received");
+    });
+
+    it("Pair a Token-2022 mint with a classic SPL token", async () => {
+      // rent for the extra pool
+      await airdrop(initializer.publicKey, LAMPORTS_PER_SOL);
+
+      // X is the transfer fee mint under Token-2022, Y is mint_x under the classic program
+      const [config_mixed] = getConfig(mint_a.publicKey, mint_x.publicKey, fee);
+      const [mint_lp_mixed] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("lp", "utf-8"), config_mixed.toBuffer()],
+        program.programId,
+      );
+      const vault_mixed_a = getAta2022(config_mixed, mint_a.publicKey);
+      const [vault_mixed_x] = getAta(config_mixed, mint_x.publicKey);
+      const mixedAccounts = () => ({
+        mintX: mint_a.publicKey,
+        mintY: mint_x.publicKey,
+        vaultX: vault_mixed_a,
+        vaultY: vault_mixed_x,
+        config: config_mixed,
+        oracle: anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("oracle", "utf-8"), config_mixed.toBuffer()],
+          program.programId,
+        )[0],
+        tokenProgramX: TOKEN_2022_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      });
+
+      await program.methods.initialize(
+        fee,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        constantProduct,
+        new BN(0),
+        0,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: getPair(mint_a.publicKey, mint_x.publicKey, fee)[0],
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        mintLp: mint_lp_mixed,
+        ...mixedAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const max_a = new BN(10_000 * 10 ** 6);
+      const max_x = new BN(10_000 * 10 ** 6);
+      await program.methods.deposit(
+        new BN(9_000 * 10 ** 6),
+        max_a,
+        max_x,
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: getAta(initializer.publicKey, mint_lp_mixed)[0],
+        nativeSol: null,
+        userX: initializer_ata_a.address,
+        userY: initializer_ata_x.address,
+        mintLp: mint_lp_mixed,
+        ...mixedAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const sent_a = BigInt(max_a.toString());
+      const vault_a_after = await getAccount(provider.connection, vault_mixed_a, undefined, TOKEN_2022_PROGRAM_ID);
+      const vault_x_after = await getAccount(provider.connection, vault_mixed_x);
+      assert.equal(vault_a_after.amount, sent_a - transferFee(sent_a), "Token-2022 vault should hold the amount net of transfer fee");
+      assert.equal(vault_x_after.amount, BigInt(max_x.toString()), "Classic vault should hold the full amount");
+
+      // swap the classic token for the Token-2022 one
+      const user_a_before = await getAccount(provider.connection, initializer_ata_a.address, undefined, TOKEN_2022_PROGRAM_ID);
+      await program.methods.swap(
+        false,
+        new BN(100 * 10 ** 6),
+        new BN(1),
+        expiration,
+      ).accountsStrict({
+        referrer: null,
+        referrerToken: null,
+        nativeSol: null,
+        user: initializer.publicKey,
+        userX: initializer_ata_a.address,
+        userY: initializer_ata_x.address,
+        ...mixedAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const user_a_after = await getAccount(provider.connection, initializer_ata_a.address, undefined, TOKEN_2022_PROGRAM_ID);
+      assert(user_a_after.amount > user_a_before.amount, "User should receive the Token-2022 token");
+    });
+  });
+
+  describe("StableSwap Pool", () => {
+    const amp = new BN(100);
+    const fee_stable = 4; // a separate fee tier of the X/Y pair
+
+    const [config_stable] = getConfig(mint_x.publicKey, mint_y.publicKey, fee_stable, stableSwap);
+    const [mint_lp_stable] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("lp", "utf-8"), config_stable.toBuffer()],
+      program.programId,
+    );
+    const [oracle_stable] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle", "utf-8"), config_stable.toBuffer()],
+      program.programId,
+    );
+    const [pair_stable] = getPair(mint_x.publicKey, mint_y.publicKey, fee_stable, stableSwap);
+    const [vault_stable_x] = getAta(config_stable, mint_x.publicKey);
+    const [vault_stable_y] = getAta(config_stable, mint_y.publicKey);
+
+    const poolAccounts = () => ({
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      vaultX: vault_stable_x,
+      vaultY: vault_stable_y,
+      config: config_stable,
+      oracle: oracle_stable,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    it("Fail initialize StableSwap pool without amplification", async () => {
+      try {
+        await program.methods.initialize(
+          fee_stable,
+          initializer.publicKey,
+          protocol_fee,
+          treasury.publicKey,
+          stableSwap,
+          new BN(0),
+          0,
+        ).accountsStrict({
+          initializer: initializer.publicKey,
+          admin: provider.wallet.publicKey,
+          pair: pair_stable,
+          registry: registry,
+          registryPage: await nextRegistryPage(),
+          mintLp: mint_lp_stable,
+          ...poolAccounts(),
+        })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid amplification");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidAmp", "Should fail with InvalidAmp error");
+      }
+    });
+
+    it("Initialize and deposit into a StableSwap pool", async () => {
+      await program.methods.initialize(
+        fee_stable,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        stableSwap,
+        amp,
+        0,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: pair_stable,
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        mintLp: mint_lp_stable,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      await program.methods.deposit(
+        new BN(19_000 * 10 ** 6),
+        new BN(10_000 * 10 ** 6),
+        new BN(10_000 * 10 ** 6),
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: getAta(initializer.publicKey, mint_lp_stable)[0],
+        nativeSol: null,
+        userX: initializer_ata_x.address,
+        userY: initializer_ata_y.address,
+        mintLp: mint_lp_stable,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      // On a balanced pool the invariant D is the sum of the reserves
+      const lp = await getAccount(provider.connection, getAta(initializer.publicKey, mint_lp_stable)[0]);
+      assert.equal(Number(lp.amount), 20_000 * 10 ** 6 - MINIMUM_LIQUIDITY);
+
+      const configAccount = await program.account.config.fetch(config_stable);
+      assert.deepEqual(configAccount.curveType, stableSwap);
+      assert.equal(amp.toNumber(), configAccount.targetAmp.toNumber());
+    });
+
+    it("Swap close to 1:1 on a StableSwap pool", async () => {
+      const amount = 1_000 * 10 ** 6;
+
+      const vault_x_before = await getAccount(provider.connection, vault_stable_x);
+      const vault_y_before = await getAccount(provider.connection, vault_stable_y);
+      const user_y_before = await getAccount(provider.connection, initializer_ata_y.address);
+
+      await program.methods.swap(
+        true,
+        new BN(amount),
+        new BN(1),
+        expiration,
+      ).accountsStrict({
+        referrer: null,
+        referrerToken: null,
+        nativeSol: null,
+        user: initializer.publicKey,
+        userX: initializer_ata_x.address,
+        userY: initializer_ata_y.address,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const user_y_after = await getAccount(provider.connection, initializer_ata_y.address);
+      const out = Number(user_y_after.amount - user_y_before.amount);
+
+      const amount_in = amount - Math.ceil(amount * fee_stable / 10_000);
+      const x = Number(vault_x_before.amount);
+      const y = Number(vault_y_before.amount);
+      const constant_product_out = y - (x * y) / (x + amount_in);
+
+      assert(out > constant_product_out, "StableSwap should beat the constant-product price");
+      assert(out <= amount_in, "StableSwap should not pay out more than the net input");
+      assert(out > amount_in * 0.99, "StableSwap should stay close to 1:1");
+    });
+
+    it("Fail ramp shorter than the minimum duration", async () => {
+      try {
+        await program.methods.rampAmp(
+          new BN(200),
+          new BN(Math.floor(Date.now() / 1000) + 60),
+        )
+          .accountsStrict({ authority: initializer.publicKey, config: config_stable })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid ramp");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidRamp", "Should fail with InvalidRamp error");
+      }
+    });
+
+    it("Fail ramp on a constant-product pool", async () => {
+      try {
+        await program.methods.rampAmp(
+          new BN(200),
+          new BN(Math.floor(Date.now() / 1000) + 2 * 24 * 60 * 60),
+        )
+          .accountsStrict({ authority: initializer.publicKey, config })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to constant-product curve");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidCurve", "Should fail with InvalidCurve error");
+      }
+    });
+
+    it("Stop ramp freezes the amplification", async () => {
+      await program.methods.stopRampAmp()
+        .accountsStrict({ authority: initializer.publicKey, config: config_stable })
+        .signers([initializer])
+        .rpc();
+
+      const configAccount = await program.account.config.fetch(config_stable);
+      assert.equal(amp.toNumber(), configAccount.initialAmp.toNumber());
+      assert.equal(amp.toNumber(), configAccount.targetAmp.toNumber());
+      assert.equal(configAccount.rampStartTs.toNumber(), configAccount.rampStopTs.toNumber());
+    });
+  });
+
+  describe("Weighted Pool", () => {
+    const weight_x = 8_000; // 80/20 pool
+    const fee_weighted = 30; // a separate fee tier of the X/Y pair
+
+    const [config_weighted] = getConfig(mint_x.publicKey, mint_y.publicKey, fee_weighted, weighted);
+    const [mint_lp_weighted] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("lp", "utf-8"), config_weighted.toBuffer()],
+      program.programId,
+    );
+    const [oracle_weighted] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle", "utf-8"), config_weighted.toBuffer()],
+      program.programId,
+    );
+    const [pair_weighted] = getPair(mint_x.publicKey, mint_y.publicKey, fee_weighted, weighted);
+    const [vault_weighted_x] = getAta(config_weighted, mint_x.publicKey);
+    const [vault_weighted_y] = getAta(config_weighted, mint_y.publicKey);
+
+    const poolAccounts = () => ({
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      vaultX: vault_weighted_x,
+      vaultY: vault_weighted_y,
+      config: config_weighted,
+      oracle: oracle_weighted,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    it("Fail initialize weighted pool with a weight out of range", async () => {
+      try {
+        await program.methods.initialize(
+          fee_weighted,
+          initializer.publicKey,
+          protocol_fee,
+          treasury.publicKey,
+          weighted,
+          new BN(0),
+          10_000,
+        ).accountsStrict({
+          initializer: initializer.publicKey,
+          admin: provider.wallet.publicKey,
+          pair: pair_weighted,
+          registry: registry,
+          registryPage: await nextRegistryPage(),
+          mintLp: mint_lp_weighted,
+          ...poolAccounts(),
+        })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid weight");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidWeight", "Should fail with InvalidWeight error");
+      }
+    });
+
+    it("Initialize and deposit into an 80/20 pool", async () => {
+      await program.methods.initialize(
+        fee_weighted,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        weighted,
+        new BN(0),
+        weight_x,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: pair_weighted,
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        mintLp: mint_lp_weighted,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const max_x = 8_000 * 10 ** 6;
+      const max_y = 2_000 * 10 ** 6;
+
+      await program.methods.deposit(
+        new BN(1),
+        new BN(max_x),
+        new BN(max_y),
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: getAta(initializer.publicKey, mint_lp_weighted)[0],
+        nativeSol: null,
+        userX: initializer_ata_x.address,
+        userY: initializer_ata_y.address,
+        mintLp: mint_lp_weighted,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      // LP is the weighted geometric mean of the deposit, minus the locked minimum liquidity
+      const lp = await getAccount(provider.connection, getAta(initializer.publicKey, mint_lp_weighted)[0]);
+      const liquidity = Math.pow(max_x, 0.8) * Math.pow(max_y, 0.2);
+      assert(Math.abs(Number(lp.amount) + MINIMUM_LIQUIDITY - liquidity) <= 2, "LP should be the weighted geometric mean");
+
+      const configAccount = await program.account.config.fetch(config_weighted);
+      assert.equal(weight_x, configAccount.weightX);
+      assert.equal(10_000 - weight_x, configAccount.weightY);
+    });
+
+    it("Swap on an 80/20 pool follows the weighted invariant", async () => {
+      const amount = 100 * 10 ** 6;
+
+      const vault_x_before = await getAccount(provider.connection, vault_weighted_x);
+      const vault_y_before = await getAccount(provider.connection, vault_weighted_y);
+      const user_y_before = await getAccount(provider.connection, initializer_ata_y.address);
+
+      await program.methods.swap(
+        true,
+        new BN(amount),
+        new BN(1),
+        expiration,
+      ).accountsStrict({
+        referrer: null,
+        referrerToken: null,
+        nativeSol: null,
+        user: initializer.publicKey,
+        userX: initializer_ata_x.address,
+        userY: initializer_ata_y.address,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const user_y_after = await getAccount(provider.connection, initializer_ata_y.address);
+      const out = Number(user_y_after.amount - user_y_before.amount);
+
+      const amount_in = amount - Math.ceil(amount * fee_weighted / 10_000);
+      const x = Number(vault_x_before.amount);
+      const y = Number(vault_y_before.amount);
+      const expected_out = y * (1 - Math.pow(x / (x + amount_in), 0.8 / 0.2));
+
+      assert(out <= Math.ceil(expected_out), "Output should never exceed the weighted invariant");
+      assert(expected_out - out < 1_000, "Output should follow the weighted invariant");
+    });
+  });
+
+  describe("Mixed-Decimal Pools", () => {
+    const fee_mixed_stable = 4; // basis points
+
+    // a 9-decimal token paired with a 0-decimal one, and with the 6-decimal token X
+    const mint_9 = Keypair.generate();
+    const mint_0 = Keypair.generate();
+
+    const poolAddresses = (pool_mint_x: PublicKey, pool_mint_y: PublicKey, fee_tier: number, curve: object = constantProduct) => {
+      const [pool_config] = getConfig(pool_mint_x, pool_mint_y, fee_tier, curve);
+      const [pool_mint_lp] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("lp", "utf-8"), pool_config.toBuffer()],
+        program.programId,
+      );
+      const [pool_oracle] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("oracle", "utf-8"), pool_config.toBuffer()],
+        program.programId,
+      );
+
+      return {
+        mintLp: pool_mint_lp,
+        accounts: {
+          mintX: pool_mint_x,
+          mintY: pool_mint_y,
+          vaultX: getAta(pool_config, pool_mint_x)[0],
+          vaultY: getAta(pool_config, pool_mint_y)[0],
+          config: pool_config,
+          oracle: pool_oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        },
+      };
+    };
+
+    const pool_mixed = poolAddresses(mint_9.publicKey, mint_0.publicKey, fee);
+    const pool_mixed_stable = poolAddresses(mint_9.publicKey, mint_x.publicKey, fee_mixed_stable, stableSwap);
+
+    let initializer_ata_9: Account;
+    let initializer_ata_0: Account;
+
+    before("Setup mixed-decimal mints", async () => {
+      // rent for the extra pools
+      await airdrop(initializer.publicKey, LAMPORTS_PER_SOL);
+
+      await createMint(provider.connection, initializer, initializer.publicKey, null, 9, mint_9);
+      await createMint(provider.connection, initializer, initializer.publicKey, null, 0, mint_0);
+
+      initializer_ata_9 = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer,
+        mint_9.publicKey,
+        initializer.publicKey
+      );
+      initializer_ata_0 = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer,
+        mint_0.publicKey,
+        initializer.publicKey
+      );
+
+      await mintTo(
+        provider.connection,
+        initializer,
+        mint_9.publicKey,
+        initializer_ata_9.address,
+        initializer,
+        100_000 * 10 ** 9
+      );
+      await mintTo(
+        provider.connection,
+        initializer,
+        mint_0.publicKey,
+        initializer_ata_0.address,
+        initializer,
+        1_000_000
+      );
+    });
+
+    it("Derive the LP decimals of a constant-product pool from its mints", async () => {
+      await program.methods.initialize(
+        fee,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        constantProduct,
+        new BN(0),
+        0,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: getPair(mint_9.publicKey, mint_0.publicKey, fee)[0],
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        mintLp: pool_mixed.mintLp,
+        ...pool_mixed.accounts,
+      })
+        .signers([initializer])
+        .rpc();
+
+      // the mean of 9 and 0 decimals, rounded down
+      const lp_mint = await getMint(provider.connection, pool_mixed.mintLp);
+      assert.equal(lp_mint.decimals, 4);
+
+      const configAccount = await program.account.config.fetch(pool_mixed.accounts.config);
+      assert.equal(configAccount.decimalsX, 9);
+      assert.equal(configAccount.decimalsY, 0);
+      assert.equal(configAccount.lpDecimals, 4);
+    });
+
+    it("Deposit and withdraw proportionally on a mixed-decimal pool", async () => {
+      // 1 token of 9 decimals for 100 tokens of 0 decimals
+      const max_x = 1_000 * 10 ** 9;
+      const max_y = 100_000;
+      const user_lp = getAta(initializer.publicKey, pool_mixed.mintLp)[0];
+
+      await program.methods.deposit(
+        new BN(1),
+        new BN(max_x),
+        new BN(max_y),
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: user_lp,
+        nativeSol: null,
+        userX: initializer_ata_9.address,
+        userY: initializer_ata_0.address,
+        mintLp: pool_mixed.mintLp,
+        ...pool_mixed.accounts,
+      })
+        .signers([initializer])
+        .rpc();
+
+      const lp = Number((await getAccount(provider.connection, user_lp)).amount);
+      assert.equal(lp, Math.floor(Math.sqrt(max_x * max_y)) - MINIMUM_LIQUIDITY);
+
+      const user_x_before = await getAccount(provider.connection, initializer_ata_9.address);
+      const user_y_before = await getAccount(provider.connection, initializer_ata_0.address);
+
+      await program.methods.withdraw(
+        new BN(Math.floor(lp / 2)),
+        new BN(1),
+        new BN(1),
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: user_lp,
+        nativeSol: null,
+        userX: initializer_ata_9.address,
+        userY: initializer_ata_0.address,
+        mintLp: pool_mixed.mintLp,
+        ...pool_mixed.accounts,
+      })
+        .signers([initializer])
+        .rpc();
+
+      const user_x_after = await getAccount(provider.connection, initializer_ata_9.address);
+      const user_y_after = await getAccount(provider.connection, initializer_ata_0.address);
+      const out_x = Number(user_x_after.amount - user_x_before.amount);
+      const out_y = Number(user_y_after.amount - user_y_before.amount);
+
+      const share = Math.floor(lp / 2) / (lp + MINIMUM_LIQUIDITY);
+      assert(Math.abs(out_x - max_x * share) <= 1, "X should be paid out pro rata");
+      assert(Math.abs(out_y - max_y * share) <= 1, "Y should be paid out pro rata");
+    });
+
+    it("Scale StableSwap reserves to the larger decimals", async () => {
+      await program.methods.initialize(
+        fee_mixed_stable,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        stableSwap,
+        new BN(100),
+        0,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: getPair(mint_9.publicKey, mint_x.publicKey, fee_mixed_stable, stableSwap)[0],
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        mintLp: pool_mixed_stable.mintLp,
+        ...pool_mixed_stable.accounts,
+      })
+        .signers([initializer])
+        .rpc();
+
+      const lp_mint = await getMint(provider.connection, pool_mixed_stable.mintLp);
+      assert.equal(lp_mint.decimals, 9);
+
+      const user_lp = getAta(initializer.publicKey, pool_mixed_stable.mintLp)[0];
+
+      await program.methods.deposit(
+        new BN(1),
+        new BN(10_000 * 10 ** 9),
+        new BN(10_000 * 10 ** 6),
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: user_lp,
+        nativeSol: null,
+        userX: initializer_ata_9.address,
+        userY: initializer_ata_x.address,
+        mintLp: pool_mixed_stable.mintLp,
+        ...pool_mixed_stable.accounts,
+      })
+        .signers([initializer])
+        .rpc();
+
+      // 10,000 tokens on each side make a balanced pool, so D is their sum in 9 decimals
+      const lp = await getAccount(provider.connection, user_lp);
+      assert.equal(Number(lp.amount), 20_000 * 10 ** 9 - MINIMUM_LIQUIDITY);
+    });
+
+    it("Swap close to 1:1 in whole tokens on a mixed-decimal StableSwap pool", async () => {
+      const amount = 100 * 10 ** 9;
+
+      const user_y_before = await getAccount(provider.connection, initializer_ata_x.address);
+
+      await program.methods.swap(
+        true,
+        new BN(amount),
+        new BN(1),
+        expiration,
+      ).accountsStrict({
+        referrer: null,
+        referrerToken: null,
+        nativeSol: null,
+        user: initializer.publicKey,
+        userX: initializer_ata_9.address,
+        userY: initializer_ata_x.address,
+        ...pool_mixed_stable.accounts,
+      })
+        .signers([initializer])
+        .rpc();
+
+      const user_y_after = await getAccount(provider.connection, initializer_ata_x.address);
+      const out = Number(user_y_after.amount - user_y_before.amount);
+
+      // the net input, in the 6 decimals of the output
+      const amount_in = (amount - Math.ceil(amount * fee_mixed_stable / 10_000)) / 10 ** 3;
+
+      assert(out <= amount_in, "StableSwap should not pay out more than the net input");
+      assert(out > amount_in * 0.99, "StableSwap should stay close to 1:1");
+    });
+
+    it("Swap for an exact output close to 1:1 on a mixed-decimal StableSwap pool", async () => {
+      const amount_out = 50 * 10 ** 9;
+
+      const user_x_before = await getAccount(provider.connection, initializer_ata_9.address);
+      const user_y_before = await getAccount(provider.connection, initializer_ata_x.address);
+
+      await program.methods.swapExactOut(
+        false,
+        new BN(amount_out),
+        new BN(51 * 10 ** 6),
+        expiration,
+      ).accountsStrict({
+        referrer: null,
+        referrerToken: null,
+        nativeSol: null,
+        user: initializer.publicKey,
+        userX: initializer_ata_9.address,
+        userY: initializer_ata_x.address,
+        ...pool_mixed_stable.accounts,
+      })
+        .signers([initializer])
+        .rpc();
+
+      const user_x_after = await getAccount(provider.connection, initializer_ata_9.address);
+      const user_y_after = await getAccount(provider.connection, initializer_ata_x.address);
+      const paid = Number(user_y_before.amount - user_y_after.amount);
+
+      assert.equal(Number(user_x_after.amount - user_x_before.amount), amount_out);
+      assert(paid >= amount_out / 10 ** 3, "StableSwap should not charge less than the output");
+      assert(paid < amount_out / 10 ** 3 * 1.01, "StableSwap should stay close to 1:1");
+    });
+  });
+
+  describe("Concentrated Liquidity", () => {
+    const fee_cl = 5; // basis points
+    const tick_spacing = 10;
+    // ticks per tick array, 64 usable ticks apart by the spacing
+    const span = 64 * tick_spacing;
+    const tick_lower = -100;
+    const tick_upper = 100;
+    const liquidity = new BN(100_000_000_000);
+
+    const [config_cl] = getConfig(mint_x.publicKey, mint_y.publicKey, fee_cl, concentrated);
+    const [vault_cl_x] = getAta(config_cl, mint_x.publicKey);
+    const [vault_cl_y] = getAta(config_cl, mint_y.publicKey);
+    const [oracle_cl] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle", "utf-8"), config_cl.toBuffer()],
+      program.programId,
+    );
+
+    const tickBuffer = (tick: number) => {
+      const buffer = Buffer.alloc(4);
+      buffer.writeInt32LE(tick);
+      return buffer;
+    };
+    const getTickArray = (start_tick: number) => anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("tick_array", "utf-8"), config_cl.toBuffer(), tickBuffer(start_tick)],
+      program.programId,
+    );
+    const getPosition = (owner: PublicKey, lower: number, upper: number) => anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("position", "utf-8"), config_cl.toBuffer(), owner.toBuffer(), tickBuffer(lower), tickBuffer(upper)],
+      program.programId,
+    );
+
+    const [tick_array_below] = getTickArray(-span);
+    const [tick_array_above] = getTickArray(0);
+    const [position] = getPosition(initializer.publicKey, tick_lower, tick_upper);
+
+    const tokenAccounts = () => ({
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      config: config_cl,
+      vaultX: vault_cl_x,
+      vaultY: vault_cl_y,
+      userX: initializer_ata_x.address,
+      userY: initializer_ata_y.address,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+    });
+    const swapAccounts = () => ({
+      user: initializer.publicKey,
+      oracle: oracle_cl,
+      nativeSol: null,
+      systemProgram: SystemProgram.programId,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+      ...tokenAccounts(),
+    });
+    const positionAccounts = () => ({
+      owner: initializer.publicKey,
+      position,
+      tickArrayLower: tick_array_below,
+      tickArrayUpper: tick_array_above,
+      ...tokenAccounts(),
+    });
+
+    before("Fund rent for the pool, its tick arrays and positions", async () => {
+      await airdrop(initializer.publicKey, LAMPORTS_PER_SOL);
+    });
+
+    it("Initialize a concentrated liquidity pool at a price of 1", async () => {
+      await program.methods.initializeConcentrated(
+        fee_cl,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        tick_spacing,
+        new BN(1).shln(64),
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        mintX: mint_x.publicKey,
+        mintY: mint_y.publicKey,
+        pair: getPair(mint_x.publicKey, mint_y.publicKey, fee_cl, concentrated)[0],
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        vaultX: vault_cl_x,
+        vaultY: vault_cl_y,
+        config: config_cl,
+        oracle: oracle_cl,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([initializer])
+        .rpc();
+
+      const configAccount = await program.account.config.fetch(config_cl);
+      assert.ok(configAccount.curveType.concentrated, "Pool should use the concentrated curve");
+      assert.equal(configAccount.tickSpacing, tick_spacing);
+      assert.equal(configAccount.tickCurrent, 0);
+      assert.equal(configAccount.liquidity.toNumber(), 0);
+
+      for (const start_tick of [-span, 0]) {
+        await program.methods.initializeTickArray(start_tick).accountsStrict({
+          payer: initializer.publicKey,
+          config: config_cl,
+          tickArray: getTickArray(start_tick)[0],
+          systemProgram: SystemProgram.programId,
+        })
+          .signers([initializer])
+          .rpc();
+      }
+    });
+
+    it("Reject a position bounded by a tick off the spacing", async () => {
+      try {
+        await program.methods.openPosition(tick_lower - 5, tick_upper).accountsStrict({
+          owner: initializer.publicKey,
+          config: config_cl,
+          position: getPosition(initializer.publicKey, tick_lower - 5, tick_upper)[0],
+          systemProgram: SystemProgram.programId,
+        })
+          .signers([initializer])
+          .rpc();
+        assert.fail("Should have failed with a misaligned tick");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidTickRange", "Should fail with InvalidTickRange error");
+      }
+    });
+
+    it("Provide liquidity over a price range around the current price", async () => {
+      await program.methods.openPosition(tick_lower, tick_upper).accountsStrict({
+        owner: initializer.publicKey,
+        config: config_cl,
+        position,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([initializer])
+        .rpc();
+
+      await program.methods.increaseLiquidity(
+        liquidity,
+        new BN(1_000_000_000),
+        new BN(1_000_000_000),
+        expiration,
+      ).accountsStrict(positionAccounts())
+        .signers([initializer])
+        .rpc();
+
+      const vault_x_account = await getAccount(provider.connection, vault_cl_x);
+      const vault_y_account = await getAccount(provider.connection, vault_cl_y);
+      // at the middle of a symmetric range the position holds as much X as Y
+      assert(vault_x_account.amount > BigInt(0), "The position should be backed by X");
+      assert.equal(vault_x_account.amount, vault_y_account.amount);
+
+      const configAccount = await program.account.config.fetch(config_cl);
+      assert.equal(configAccount.liquidity.toString(), liquidity.toString());
+
+      const positionAccount = await program.account.position.fetch(position);
+      assert.equal(positionAccount.liquidity.toString(), liquidity.toString());
+    });
+
+    it("Reject tick arrays out of the swap's order", async () => {
+      try {
+        await program.methods.swapConcentrated(true, new BN(1_000_000), new BN(0), expiration)
+          .accountsStrict(swapAccounts())
+          .remainingAccounts([
+            { pubkey: tick_array_below, isSigner: false, isWritable: true },
+          ])
+          .signers([initializer])
+          .rpc();
+        assert.fail("Should have failed with the wrong tick array");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidTickArray", "Should fail with InvalidTickArray error");
+      }
+    });
+
+    it("Swap X for Y close to 1:1 within the range", async () => {
+      const amount = 1_000_000;
+      const tickArrays = [
+        { pubkey: tick_array_above, isSigner: false, isWritable: true },
+        { pubkey: tick_array_below, isSigner: false, isWritable: true },
+      ];
+
+      // a concentrated pool has no LP mint and is quoted across the same tick arrays
+      const quote = await program.methods.quoteSwap(true, new BN(amount), new BN(amount * 0.99), expiration, 0)
+        .accountsStrict({
+          mintX: mint_x.publicKey,
+          mintY: mint_y.publicKey,
+          config: config_cl,
+          mintLp: null,
+          vaultX: vault_cl_x,
+          vaultY: vault_cl_y,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(tickArrays)
+        .view();
+
+      const user_y_before = await getAccount(provider.connection, initializer_ata_y.address);
+
+      const tx = await program.methods.swapConcentrated(true, new BN(amount), new BN(amount * 0.99), expiration)
+        .accountsStrict(swapAccounts())
+        .remainingAccounts(tickArrays)
+        .signers([initializer])
+        .rpc();
+
+      const user_y_after = await getAccount(provider.connection, initializer_ata_y.address);
+      const out = Number(user_y_after.amount - user_y_before.amount);
+
+      assert(out < amount, "The swap should not pay out more than the input");
+      assert(out > amount * 0.99, "The swap should stay close to 1:1 within the range");
+
+      const swapped = findEvent(await getEvents(tx), "swapped");
+      assert.equal(swapped.amountOut.toNumber(), out);
+      assert(swapped.fee.toNumber() > 0, "The swap should charge a fee");
+      assert.equal(quote.amountReceived.toNumber(), out, "The quote should match the swap");
+      assert.equal(quote.fee.toString(), swapped.fee.toString());
+
+      // the price moved down into the range's lower half
+      const configAccount = await program.account.config.fetch(config_cl);
+      assert(configAccount.tickCurrent < 0, "Selling X should move the price down");
+      assert(configAccount.tickCurrent >= tick_lower, "The price should stay within the range");
+      assert(configAccount.feeGrowthGlobalX.gtn(0), "The fee should grow for the liquidity in range");
+      assert(configAccount.volatility.gtn(0), "The price move should feed the dynamic fee");
+
+      // the price held until the swap is accumulated by the oracle
+      const oracleAccount = await program.account.oracle.fetch(oracle_cl);
+      assert(oracleAccount.latest.priceXCumulative.gtn(0), "The swap should update the oracle");
+    });
+
+    it("Collect the fees the position earned", async () => {
+      const user_x_before = await getAccount(provider.connection, initializer_ata_x.address);
+
+      await program.methods.collectFees().accountsStrict(positionAccounts())
+        .signers([initializer])
+        .rpc();
+
+      const user_x_after = await getAccount(provider.connection, initializer_ata_x.address);
+      assert(user_x_after.amount > user_x_before.amount, "The position should earn fees in X");
+
+      const positionAccount = await program.account.position.fetch(position);
+      assert.equal(positionAccount.feesOwedX.toNumber(), 0);
+      assert.equal(positionAccount.feesOwedY.toNumber(), 0);
+    });
+
+    it("Reject closing a position that still holds liquidity", async () => {
+      try {
+        await program.methods.closePosition().accountsStrict({
+          owner: initializer.publicKey,
+          config: config_cl,
+          position,
+        })
+          .signers([initializer])
+          .rpc();
+        assert.fail("Should have failed with liquidity left");
+      } catch (e) {
+        assert(e.error.errorCode.code === "PositionNotEmpty", "Should fail with PositionNotEmpty error");
+      }
+    });
+
+    it("Withdraw all liquidity and close the position", async () => {
+      await program.methods.decreaseLiquidity(liquidity, new BN(0), new BN(0), expiration)
+        .accountsStrict(positionAccounts())
+        .signers([initializer])
+        .rpc();
+
+      const configAccount = await program.account.config.fetch(config_cl);
+      assert.equal(configAccount.liquidity.toNumber(), 0);
+
+      // rounding in the pool's favour leaves it a few units of dust at most
+      const vault_x_account = await getAccount(provider.connection, vault_cl_x);
+      const vault_y_account = await getAccount(provider.connection, vault_cl_y);
+      assert(vault_x_account.amount - BigInt(configAccount.protocolFeesX.toString()) <= BigInt(10), "The vault should be emptied of X");
+      assert(vault_y_account.amount <= BigInt(10), "The vault should be emptied of Y");
+
+      await program.methods.closePosition().accountsStrict({
+        owner: initializer.publicKey,
+        config: config_cl,
+        position,
+      })
+        .signers([initializer])
+        .rpc();
+
+      const closed = await provider.connection.getAccountInfo(position);
+      assert.isNull(closed, "The position account should be closed");
+
+      const closedConfig = await program.account.config.fetch(config_cl);
+      assert.equal(closedConfig.positionCount.toNumber(), 0);
+    });
+
+    it("Close the concentrated pool once its positions are closed", async () => {
+      const [pair_cl] = getPair(mint_x.publicKey, mint_y.publicKey, fee_cl, concentrated);
+      const pairAccount = await program.account.pair.fetch(pair_cl);
+      const [registry_page_cl] = getRegistryPage(Math.floor(pairAccount.registryIndex.toNumber() / REGISTRY_PAGE_SIZE));
+      const configAccount = await program.account.config.fetch(config_cl);
+      // the treasury's token accounts may not exist yet
+      const treasury_x_before = await getAccount(provider.connection, getAta(treasury.publicKey, mint_x.publicKey)[0])
+        .then((account) => account.amount)
+        .catch(() => BigInt(0));
+
+      // a concentrated pool has no LP mint
+      const tx = await program.methods.closePool().accountsStrict({
+        closer: initializer.publicKey,
+        initializer: initializer.publicKey,
+        treasury: treasury.publicKey,
+        mintX: mint_x.publicKey,
+        mintY: mint_y.publicKey,
+        mintLp: null,
+        config: config_cl,
+        oracle: oracle_cl,
+        pair: pair_cl,
+        registryPage: registry_page_cl,
+        vaultX: vault_cl_x,
+        vaultY: vault_cl_y,
+        treasuryX: getAta(treasury.publicKey, mint_x.publicKey)[0],
+        treasuryY: getAta(treasury.publicKey, mint_y.publicKey)[0],
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+      })
+        .signers([initializer])
+        .rpc({ commitment: "confirmed" });
+
+      for (const account of [config_cl, oracle_cl, pair_cl, vault_cl_x, vault_cl_y]) {
+        assert.equal(await provider.connection.getAccountInfo(account), null, "Pool account should be closed");
+      }
+
+      // the protocol fees go to the treasury, the rounding dust is burned
+      const event = findEvent(await getEvents(tx), "PoolClosed");
+      assert.equal(event.amountX.toString(), configAccount.protocolFeesX.toString());
+      const treasury_x_after = await getAccount(provider.connection, getAta(treasury.publicKey, mint_x.publicKey)[0]);
+      assert.equal(treasury_x_after.amount - treasury_x_before, BigInt(configAccount.protocolFeesX.toString()));
+    });
+
+    it("Reset a closed pool's tick array for a new pool with another tick spacing", async () => {
+      const new_tick_spacing = 2 * tick_spacing;
+
+      await program.methods.initializeConcentrated(
+        fee_cl,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        new_tick_spacing,
+        new BN(1).shln(64),
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        mintX: mint_x.publicKey,
+        mintY: mint_y.publicKey,
+        pair: getPair(mint_x.publicKey, mint_y.publicKey, fee_cl, concentrated)[0],
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        vaultX: vault_cl_x,
+        vaultY: vault_cl_y,
+        config: config_cl,
+        oracle: oracle_cl,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([initializer])
+        .rpc();
+
+      // the closed pool's array at tick 0 is still there, with the old spacing
+      assert.equal((await program.account.tickArray.fetch(tick_array_above)).tickSpacing, tick_spacing);
+
+      await program.methods.initializeTickArray(0).accountsStrict({
+        payer: initializer.publicKey,
+        config: config_cl,
+        tickArray: tick_array_above,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([initializer])
+        .rpc();
+
+      const tickArrayAccount = await program.account.tickArray.fetch(tick_array_above);
+      assert.equal(tickArrayAccount.tickSpacing, new_tick_spacing);
+      assert(tickArrayAccount.ticks.every((tick) => tick.liquidityGross.isZero()), "The ticks should be reset");
+    });
+  });
+
+  describe("Native SOL Pool", () => {
+
+    // SOL is token X, held by the pool as wSOL
+    const [config_sol] = getConfig(NATIVE_MINT, mint_y.publicKey, fee);
+    const [mint_lp_sol] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("lp", "utf-8"), config_sol.toBuffer()],
+      program.programId,
+    );
+    const [oracle_sol] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle", "utf-8"), config_sol.toBuffer()],
+      program.programId,
+    );
+    const [pair_sol] = getPair(NATIVE_MINT, mint_y.publicKey, fee);
+    const [vault_sol] = getAta(config_sol, NATIVE_MINT);
+    const [vault_sol_y] = getAta(config_sol, mint_y.publicKey);
+
+    // Ephemeral wSOL account that native SOL is wrapped in and unwrapped from
+    const getNativeSol = (owner: PublicKey) => anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("native_sol", "utf-8"), owner.toBuffer()],
+      program.programId,
+    );
+
+    const poolAccounts = () => ({
+      mintX: NATIVE_MINT,
+      mintY: mint_y.publicKey,
+      vaultX: vault_sol,
+      vaultY: vault_sol_y,
+      config: config_sol,
+      oracle: oracle_sol,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    const swapAccounts = (nativeSol: PublicKey | null) => ({
+      user: user1.publicKey,
+      userX: getAta(user1.publicKey, NATIVE_MINT)[0],
+      userY: user1_ata_y.address,
+      referrer: null,
+      referrerToken: null,
+      nativeSol,
+      ...poolAccounts(),
+    });
+
+    const wsolBalance = async (owner: PublicKey) =>
+      Number((await getAccount(provider.connection, getAta(owner, NATIVE_MINT)[0])).amount);
+
+    before("Setup a SOL pool", async () => {
+      await airdrop(initializer.publicKey, 10 * LAMPORTS_PER_SOL);
+      await airdrop(user1.publicKey, 10 * LAMPORTS_PER_SOL);
+
+      // user1 already holds some wSOL, which native SOL swaps must leave alone
+      const user1_wsol = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        user1,
+        NATIVE_MINT,
+        user1.publicKey,
+      );
+      await sendAndConfirmTransaction(
+        provider.connection,
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: user1.publicKey,
+            toPubkey: user1_wsol.address,
+            lamports: LAMPORTS_PER_SOL,
+          }),
+          createSyncNativeInstruction(user1_wsol.address),
+        ),
+        [user1],
+      );
+
+      await program.methods.initialize(
+        fee,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        constantProduct,
+        new BN(0),
+        0,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: pair_sol,
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        mintLp: mint_lp_sol,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+    });
+
+    it("Deposit native SOL through an ephemeral wSOL account", async () => {
+      const lamports_before = await provider.connection.getBalance(initializer.publicKey);
+      const max_sol = 5 * LAMPORTS_PER_SOL;
+
+      await program.methods.deposit(
+        new BN(1),
+        new BN(max_sol),
+        new BN(5_000 * 10 ** 6),
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: getAta(initializer.publicKey, mint_lp_sol)[0],
+        nativeSol: getNativeSol(initializer.publicKey)[0],
+        userX: getAta(initializer.publicKey, NATIVE_MINT)[0],
+        userY: initializer_ata_y.address,
+        mintLp: mint_lp_sol,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const vault = await getAccount(provider.connection, vault_sol);
+      assert.equal(Number(vault.amount), max_sol, "SOL should be wrapped into the vault");
+
+      const lamports_after = await provider.connection.getBalance(initializer.publicKey);
+      assert(lamports_before - lamports_after >= max_sol, "SOL should come from the user's lamports");
+      assert.equal(await wsolBalance(initializer.publicKey), 0, "The user's wSOL account should be left in place");
+      assert.equal(
+        await provider.connection.getAccountInfo(getNativeSol(initializer.publicKey)[0]),
+        null,
+        "The ephemeral wSOL account should be closed",
+      );
+    });
+
+    it("Swap native SOL for token Y", async () => {
+      const amount = LAMPORTS_PER_SOL / 10;
+      const user_y_before = await getAccount(provider.connection, user1_ata_y.address);
+      const wsol_before = await wsolBalance(user1.publicKey);
+      const lamports_before = await provider.connection.getBalance(user1.publicKey);
+
+      await program.methods.swap(true, new BN(amount), new BN(1), expiration)
+        .accountsStrict(swapAccounts(getNativeSol(user1.publicKey)[0]))
+        .signers([user1])
+        .rpc();
+
+      const user_y_after = await getAccount(provider.connection, user1_ata_y.address);
+      assert(user_y_after.amount > user_y_before.amount, "User should receive token Y");
+
+      const lamports_after = await provider.connection.getBalance(user1.publicKey);
+      assert(lamports_before - lamports_after >= amount, "SOL should come from the user's lamports");
+      assert.equal(await wsolBalance(user1.publicKey), wsol_before, "The user's wSOL should be untouched");
+      assert.equal(await provider.connection.getAccountInfo(getNativeSol(user1.publicKey)[0]), null, "The ephemeral wSOL account should be closed");
+    });
+
+    it("Swap token Y back to native SOL", async () => {
+      const wsol_before = await wsolBalance(user1.publicKey);
+      const lamports_before = await provider.connection.getBalance(user1.publicKey);
+
+      await program.methods.swap(false, new BN(100 * 10 ** 6), new BN(1), expiration)
+        .accountsStrict(swapAccounts(getNativeSol(user1.publicKey)[0]))
+        .signers([user1])
+        .rpc();
+
+      // the output arrives as lamports, net of the transaction fee
+      const lamports_after = await provider.connection.getBalance(user1.publicKey);
+      assert(lamports_after > lamports_before, "User should receive SOL");
+      assert.equal(await wsolBalance(user1.publicKey), wsol_before, "The user's wSOL should be untouched");
+      assert.equal(await provider.connection.getAccountInfo(getNativeSol(user1.publicKey)[0]), null, "The ephemeral wSOL account should be closed");
+    });
+
+    it("Swap wSOL from the user's token account", async () => {
+      const amount = LAMPORTS_PER_SOL / 10;
+      const wsol_before = await wsolBalance(user1.publicKey);
+
+      await program.methods.swap(true, new BN(amount), new BN(1), expiration)
+        .accountsStrict(swapAccounts(null))
+        .signers([user1])
+        .rpc();
+
+      assert.equal(await wsolBalance(user1.publicKey), wsol_before - amount, "wSOL should be paid like any token");
+    });
+  });
+
+  describe("Flash Loans", () => {
+    const borrowAccounts = () => ({
+      user: user1.publicKey,
+      mint: mint_x.publicKey,
+      config: config,
+      vault: vault_x,
+      userToken: user1_ata_x.address,
+      instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    });
+
+    const repayIx = () => program.methods.flashRepay()
+      .accountsStrict({
+        user: user1.publicKey,
+        mint: mint_x.publicKey,
+        config: config,
+        vault: vault_x,
+        userToken: user1_ata_x.address,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction();
+
+    it("Borrow and repay X in one transaction", async () => {
+      const amount = 1_000 * 10 ** 6;
+      const vault_x_before = await getAccount(provider.connection, vault_x);
+
+      await program.methods.flashBorrow(new BN(amount))
+        .accountsStrict(borrowAccounts())
+        .postInstructions([await repayIx()])
+        .signers([user1])
+        .rpc();
+
+      const vault_x_after = await getAccount(provider.connection, vault_x);
+      const flash_fee = Math.ceil(amount * fee / 10_000);
+      assert.equal(Number(vault_x_after.amount - vault_x_before.amount), flash_fee, "Vault should keep the flash loan fee");
+      assert.equal(0, (await program.account.config.fetch(config)).flashLoanOwed.toNumber());
+    });
+
+    it("Charge at least one unit on a zero-fee pool", async () => {
+      const fee_free = 0; // a separate fee tier of the X/Y pair
+      const [config_free] = getConfig(mint_x.publicKey, mint_y.publicKey, fee_free);
+      const [mint_lp_free] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("lp", "utf-8"), config_free.toBuffer()],
+        program.programId,
+      );
+      const [vault_free_x] = getAta(config_free, mint_x.publicKey);
+      const [vault_free_y] = getAta(config_free, mint_y.publicKey);
+      const poolAccounts = {
+        mintX: mint_x.publicKey,
+        mintY: mint_y.publicKey,
+        vaultX: vault_free_x,
+        vaultY: vault_free_y,
+        config: config_free,
+        oracle: anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("oracle", "utf-8"), config_free.toBuffer()],
+          program.programId,
+        )[0],
+        mintLp: mint_lp_free,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      };
+
+      await program.methods.initialize(
+        fee_free,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        constantProduct,
+        new BN(0),
+        0,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: getPair(mint_x.publicKey, mint_y.publicKey, fee_free)[0],
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        ...poolAccounts,
+      })
+        .signers([initializer])
+        .rpc();
+
+      await program.methods.deposit(
+        new BN(1),
+        new BN(1_000 * 10 ** 6),
+        new BN(1_000 * 10 ** 6),
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: getAta(initializer.publicKey, mint_lp_free)[0],
+        nativeSol: null,
+        userX: initializer_ata_x.address,
+        userY: initializer_ata_y.address,
+        ...poolAccounts,
+      })
+        .signers([initializer])
+        .rpc();
+
+      const freeAccounts = {
+        user: user1.publicKey,
+        mint: mint_x.publicKey,
+        config: config_free,
+        vault: vault_free_x,
+        userToken: user1_ata_x.address,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      };
+      const vault_x_before = await getAccount(provider.connection, vault_free_x);
+
+      await program.methods.flashBorrow(new BN(100 * 10 ** 6))
+        .accountsStrict({ ...freeAccounts, instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY })
+        .postInstructions([await program.methods.flashRepay().accountsStrict(freeAccounts).instruction()])
+        .signers([user1])
+        .rpc();
+
+      const vault_x_after = await getAccount(provider.connection, vault_free_x);
+      assert.equal(Number(vault_x_after.amount - vault_x_before.amount), 1, "A zero-fee pool should still charge one unit");
+    });
+
+    it("Fail borrow without a repay", async () => {
+      try {
+        await program.methods.flashBorrow(new BN(1_000 * 10 ** 6))
+          .accountsStrict(borrowAccounts())
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to missing repay");
+      } catch (e) {
+        assert(e.error.errorCode.code === "FlashLoanNotRepaid", "Should fail with FlashLoanNotRepaid error");
+      }
+    });
+
+    it("Fail nested borrow", async () => {
+      const nestedIx = await program.methods.flashBorrow(new BN(1_000 * 10 ** 6))
+        .accountsStrict(borrowAccounts())
+        .instruction();
+
+      try {
+        await program.methods.flashBorrow(new BN(1_000 * 10 ** 6))
+          .accountsStrict(borrowAccounts())
+          .postInstructions([nestedIx, await repayIx()])
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to nested borrow");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidFlashLoan", "Should fail with InvalidFlashLoan error");
+      }
+    });
+
+    it("Borrow from two pools in one transaction", async () => {
+      // The StableSwap pool of the X/Y pair, registered under its fee tier
+      const { config: config_stable } = await program.account.pair.fetch(
+        getPair(mint_x.publicKey, mint_y.publicKey, 4, stableSwap)[0]
+      );
+      const [vault_stable_x] = getAta(config_stable, mint_x.publicKey);
+      const stableAccounts = {
+        user: user1.publicKey,
+        mint: mint_x.publicKey,
+        config: config_stable,
+        vault: vault_stable_x,
+        userToken: user1_ata_x.address,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      };
+
+      const borrowStableIx = await program.methods.flashBorrow(new BN(100 * 10 ** 6))
+        .accountsStrict({ ...stableAccounts, instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY })
+        .instruction();
+      const repayStableIx = await program.methods.flashRepay()
+        .accountsStrict(stableAccounts)
+        .instruction();
+
+      await program.methods.flashBorrow(new BN(100 * 10 ** 6))
+        .accountsStrict(borrowAccounts())
+        .postInstructions([borrowStableIx, repayStableIx, await repayIx()])
+        .signers([user1])
+        .rpc();
+
+      assert.equal(0, (await program.account.config.fetch(config)).flashLoanOwed.toNumber());
+      assert.equal(0, (await program.account.config.fetch(config_stable)).flashLoanOwed.toNumber());
+    });
+
+    it("Fail repay without a borrow", async () => {
+      try {
+        await program.methods.flashRepay()
+          .accountsStrict({
+            user: user1.publicKey,
+            mint: mint_x.publicKey,
+            config: config,
+            vault: vault_x,
+            userToken: user1_ata_x.address,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to missing borrow");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidFlashLoan", "Should fail with InvalidFlashLoan error");
+      }
+    });
+  });
+
+  describe("Protocol Fees", () => {
+    const [treasury_x] = getAta(treasury.publicKey, mint_x.publicKey);
+    const [treasury_y] = getAta(treasury.publicKey, mint_y.publicKey);
+
+    const collectAccounts = (collector: PublicKey, treasuryOwner = treasury.publicKey) => ({
+      collector,
+      treasury: treasuryOwner,
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      config: config,
+      vaultX: vault_x,
+      vaultY: vault_y,
+      treasuryX: getAta(treasuryOwner, mint_x.publicKey)[0],
+      treasuryY: getAta(treasuryOwner, mint_y.publicKey)[0],
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+    });
+
+    it("Fail collect to an account other than the treasury", async () => {
+      try {
+        await program.methods.collectProtocolFees()
+          .accountsStrict(collectAccounts(user1.publicKey, user1.publicKey))
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to wrong treasury");
+      } catch (e) {
+        assert(e.error.errorCode.code === "ConstraintHasOne", "Should fail with ConstraintHasOne error");
+      }
+    });
+
+    it("Collect protocol fees to the treasury", async () => {
+      const configBefore = await program.account.config.fetch(config);
+      assert(configBefore.protocolFeesX.toNumber() > 0, "Protocol fees should accrue in X");
+      assert(configBefore.protocolFeesY.toNumber() > 0, "Protocol fees should accrue in Y");
+
+      const vault_x_before = await getAccount(provider.connection, vault_x);
+      const vault_y_before = await getAccount(provider.connection, vault_y);
+
+      // Collection is permissionless, the fees can only go to the treasury
+      await program.methods.collectProtocolFees()
+        .accountsStrict(collectAccounts(user1.publicKey))
+        .signers([user1])
+        .rpc();
+
+      const configAfter = await program.account.config.fetch(config);
+      const treasury_x_after = await getAccount(provider.connection, treasury_x);
+      const treasury_y_after = await getAccount(provider.connection, treasury_y);
+      const vault_x_after = await getAccount(provider.connection, vault_x);
+      const vault_y_after = await getAccount(provider.connection, vault_y);
+
+      assert.equal(configAfter.protocolFeesX.toNumber(), 0);
+      assert.equal(configAfter.protocolFeesY.toNumber(), 0);
+      assert.equal(Number(treasury_x_after.amount), configBefore.protocolFeesX.toNumber());
+      assert.equal(Number(treasury_y_after.amount), configBefore.protocolFeesY.toNumber());
+      assert.equal(Number(vault_x_before.amount - vault_x_after.amount), configBefore.protocolFeesX.toNumber());
+      assert.equal(Number(vault_y_before.amount - vault_y_after.amount), configBefore.protocolFeesY.toNumber());
+    });
+  });
+
+  describe("Withdraw", () => {
+    it("Withdraw Liquidity", async () => {
+      const amount = new BN(100_000 * 10 ** 6); // Withdraw 100k LP tokens
+      const min_x = new BN(80_000 * 10 ** 6);
+      const min_y = new BN(48_000 * 10 ** 6);
+
+      // Get initial balances
+      const initializer_lp_before = await getAccount(provider.connection, initializer_ata_lp_address);
+      const initializer_x_before = await getAccount(provider.connection, initializer_ata_x.address);
+      const initializer_y_before = await getAccount(provider.connection, initializer_ata_y.address);
+      const vault_x_before = await getAccount(provider.connection, vault_x);
+      const vault_y_before = await getAccount(provider.connection, vault_y);
+
+      // Get LP supply before
+      const lp_mint_before = await program.provider.connection.getAccountInfo(mint_lp);
+      const lp_supply_before = initializer_lp_before.amount;
+
+      await program.methods.withdraw(
+        amount,
+        min_x,
+        min_y,
+        expiration,
+      ).accountsStrict({
+        mintLp: mint_lp,
+        userLp: initializer_ata_lp_address,
+        nativeSol: null,
+        mintX: mint_x.publicKey,
+        mintY: mint_y.publicKey,
+        user: initializer.publicKey,
+        userX: initializer_ata_x.address,
+        userY: initializer_ata_y.address,
+        vaultX: vault_x,
+        vaultY: vault_y,
+        config: config,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([initializer])
+        .rpc();
+
+      // Verify balances after withdraw
+      const initializer_lp_after = await getAccount(provider.connection, initializer_ata_lp_address);
+      const initializer_x_after = await getAccount(provider.connection, initializer_ata_x.address);
+      const initializer_y_after = await getAccount(provider.connection, initializer_ata_y.address);
+      const vault_x_after = await getAccount(provider.connection, vault_x);
+      const vault_y_after = await getAccount(provider.connection, vault_y);
+
+      // Calculate changes
+      const lp_burned = initializer_lp_before.amount - initializer_lp_after.amount;
+      const x_received = initializer_x_after.amount - initializer_x_before.amount;
+      const y_received = initializer_y_after.amount - initializer_y_before.amount;
+      const vault_x_change = vault_x_before.amount - vault_x_after.amount;
+      const vault_y_change = vault_y_before.amount - vault_y_after.amount;
+
+      // Assert LP tokens were burned correctly
+      assert.equal(lp_burned, amount.toNumber(), "Exact LP amount should be burned");
+      
+      // Assert user received tokens
+      assert(x_received > 0, "User should receive X tokens");
+      assert(y_received > 0, "User should receive Y tokens");
+      assert(x_received >= min_x.toNumber(), "X received should meet minimum requirement");
+      assert(y_received >= min_y.toNumber(), "Y received should meet minimum requirement");
+      
+      // Assert vault balances decreased
+      assert.equal(vault_x_change, x_received, "Vault X decrease should equal user X increase");
+      assert.equal(vault_y_change, y_received, "Vault Y decrease should equal user Y increase");
+
+      // Assert proportionality (approximately)
+      const x_ratio = Number(x_received) / Number(vault_x_before.amount);
+      const y_ratio = Number(y_received) / Number(vault_y_before.amount);
+      const ratio_diff = Math.abs(x_ratio - y_ratio);
+      assert(ratio_diff < 0.01, "X and Y withdrawal should be proportional");
+    });
+
+    it("Partial Withdraw", async () => {
+      const amount = new BN(10_000 * 10 ** 6); // Withdraw only 10k LP tokens
+      const min_x = new BN(8_000 * 10 ** 6);
+      const min_y = new BN(4_800 * 10 ** 6);
+
+      // Get initial balances
+      const user1_lp_before = await getAccount(provider.connection, user1_ata_lp);
+      const user1_x_before = await getAccount(provider.connection, user1_ata_x.address);
+      const user1_y_before = await getAccount(provider.connection, user1_ata_y.address);
+
+      await program.methods.withdraw(
+        amount,
+        min_x,
+        min_y,
+        expiration,
+      ).accountsStrict({
+        mintLp: mint_lp,
+        userLp: user1_ata_lp,
+        nativeSol: null,
+        mintX: mint_x.publicKey,
+        mintY: mint_y.publicKey,
+        user: user1.publicKey,
+        userX: user1_ata_x.address,
+        userY: user1_ata_y.address,
+        vaultX: vault_x,
+        vaultY: vault_y,
+        config: config,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([user1])
+        .rpc();
+
+      // Verify balances after withdraw
+      const user1_lp_after = await getAccount(provider.connection, user1_ata_lp);
+      const user1_x_after = await getAccount(provider.connection, user1_ata_x.address);
+      const user1_y_after = await getAccount(provider.connection, user1_ata_y.address);
+
+      // Calculate changes
+      const lp_burned = user1_lp_before.amount - user1_lp_after.amount;
+      const x_received = user1_x_after.amount - user1_x_before.amount;
+      const y_received = user1_y_after.amount - user1_y_before.amount;
+
+      // Assert correct amounts
+      assert.equal(lp_burned, amount.toNumber(), "Exact LP amount should be burned");
+      assert(x_received >= min_x.toNumber(), "X received should meet minimum");
+      assert(y_received >= min_y.toNumber(), "Y received should meet minimum");
+
+      // User should still have LP tokens remaining
+      assert(user1_lp_after.amount > 0, "User should have remaining LP tokens");
+    });
+
+    it("Fail withdraw when slippage exceeded", async () => {
+      const amount = new BN(10_000 * 10 ** 6);
+      const min_x = new BN(1_000_000 * 10 ** 6); // Unrealistically high
+      const min_y = new BN(1_000_000 * 10 ** 6); // Unrealistically high
+
+      try {
+        await program.methods.withdraw(
+          amount,
+          min_x,
+          min_y,
+          expiration,
+        ).accountsStrict({
+          mintLp: mint_lp,
+          userLp: user1_ata_lp,
+          nativeSol: null,
+          mintX: mint_x.publicKey,
+          mintY: mint_y.publicKey,
+          user: user1.publicKey,
+          userX: user1_ata_x.address,
+          userY: user1_ata_y.address,
+          vaultX: vault_x,
+          vaultY: vault_y,
+          config: config,
+          oracle: oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+          .signers([user1])
+          .rpc();
+        
+        assert.fail("Should have failed due to slippage");
+      } catch (e) {
+        assert(e.error.errorCode.code === "SlippageExceeded", "Should fail with SlippageExceeded error");
+      }
+    });
+
+    it("Fail withdraw when amount is zero", async () => {
+      const amount = new BN(0);
+      const min_x = new BN(0);
+      const min_y = new BN(0);
+
+      try {
+        await program.methods.withdraw(
+          amount,
+          min_x,
+          min_y,
+          expiration,
+        ).accountsStrict({
+          mintLp: mint_lp,
+          userLp: user1_ata_lp,
+          nativeSol: null,
+          mintX: mint_x.publicKey,
+          mintY: mint_y.publicKey,
+          user: user1.publicKey,
+          userX: user1_ata_x.address,
+          userY: user1_ata_y.address,
+          vaultX: vault_x,
+          vaultY: vault_y,
+          config: config,
+          oracle: oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+          .signers([user1])
+          .rpc();
+        
+        assert.fail("Should have failed due to zero amount");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidAmount", "Should fail with InvalidAmount error");
+      }
+    });
+  });
+
+  describe("Farm", () => {
+    const reward_mint = Keypair.generate();
+    const reward_per_second = 1_000_000;
+
+    const [farm] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("farm", "utf-8"), config.toBuffer()],
+      program.programId,
+    );
+    const [staker] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("staker", "utf-8"), farm.toBuffer(), initializer.publicKey.toBuffer()],
+      program.programId,
+    );
+    const [stake_vault] = getAta(farm, mint_lp);
+    const [reward_vault] = getAta(farm, reward_mint.publicKey);
+    const [initializer_ata_reward] = getAta(initializer.publicKey, reward_mint.publicKey);
+
+    const stakeAccounts = () => ({
+      user: initializer.publicKey,
+      mintLp: mint_lp,
+      farm: farm,
+      staker: staker,
+      userLp: initializer_ata_lp_address,
+      nativeSol: null,
+      stakeVault: stake_vault,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    });
+
+    const harvest = () =>
+      program.methods.harvest()
+        .accountsStrict({
+          user: initializer.publicKey,
+          rewardMint: reward_mint.publicKey,
+          farm: farm,
+          staker: staker,
+          userReward: initializer_ata_reward,
+          rewardVault: reward_vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([initializer])
+        .rpc();
+
+    before("Setup a farm", async () => {
+      await createMint(
+        provider.connection,
+        initializer,
+        initializer.publicKey,
+        null,
+        6,
+        reward_mint
+      );
+
+      const authority_reward = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        initializer,
+        reward_mint.publicKey,
+        initializer.publicKey
+      );
+
+      await mintTo(
+        provider.connection,
+        initializer,
+        reward_mint.publicKey,
+        authority_reward.address,
+        initializer,
+        1_000_000 * 10 ** 6
+      );
+
+      await program.methods.initializeFarm(
+        new BN(reward_per_second),
+        new BN(Math.floor(Date.now() / 1000) + 3600),
+      ).accountsStrict({
+        authority: initializer.publicKey,
+        config: config,
+        mintLp: mint_lp,
+        rewardMint: reward_mint.publicKey,
+        farm: farm,
+        stakeVault: stake_vault,
+        rewardVault: reward_vault,
+        lpTokenProgram: TOKEN_PROGRAM_ID,
+        rewardTokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([initializer])
+        .rpc();
+
+      await program.methods.fundFarm(new BN(1_000 * 10 ** 6))
+        .accountsStrict({
+          authority: initializer.publicKey,
+          config: config,
+          farm: farm,
+          rewardMint: reward_mint.publicKey,
+          authorityReward: authority_reward.address,
+          rewardVault: reward_vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([initializer])
+        .rpc();
+    });
+
+    it("Fail set emission from a non-authority", async () => {
+      try {
+        await program.methods.setEmission(new BN(0), new BN(Math.floor(Date.now() / 1000) + 3600))
+          .accountsStrict({ authority: user1.publicKey, config: config, farm: farm })
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid authority");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidAuthority", "Should fail with InvalidAuthority error");
+      }
+    });
+
+    it("Stake LP tokens and harvest rewards", async () => {
+      const lp = await getAccount(provider.connection, initializer_ata_lp_address);
+
+      await program.methods.stake(new BN(lp.amount.toString()))
+        .accountsStrict({ ...stakeAccounts(), systemProgram: SystemProgram.programId })
+        .signers([initializer])
+        .rpc();
+
+      const farmAccount = await program.account.farm.fetch(farm);
+      assert.equal(lp.amount.toString(), farmAccount.totalStaked.toString());
+
+      await new Promise((resolve) => setTimeout(resolve, 2_000));
+
+      const started = farmAccount.lastUpdateTs.toNumber();
+      await harvest();
+
+      // the only staker earns the whole emission since it staked
+      const elapsed = (await program.account.farm.fetch(farm)).lastUpdateTs.toNumber() - started;
+      const reward = await getAccount(provider.connection, initializer_ata_reward);
+      assert(elapsed > 0, "Time should have passed");
+      assert(Number(reward.amount) <= elapsed * reward_per_second, "Rewards should not exceed the emission");
+      assert(Number(reward.amount) >= elapsed * reward_per_second - 2, "The only staker should earn the whole emission");
+    });
+
+    it("Fail unstake more than staked", async () => {
+      const stakerAccount = await program.account.staker.fetch(staker);
+
+      try {
+        await program.methods.unstake(stakerAccount.amount.addn(1))
+          .accountsStrict(stakeAccounts())
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to insufficient stake");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InsufficientBalance", "Should fail with InsufficientBalance error");
+      }
+    });
+
+    it("Stop the emission and unstake", async () => {
+      await program.methods.setEmission(new BN(0), new BN(Math.floor(Date.now() / 1000) + 3600))
+        .accountsStrict({ authority: initializer.publicKey, config: config, farm: farm })
+        .signers([initializer])
+        .rpc();
+
+      const stakerAccount = await program.account.staker.fetch(staker);
+      await program.methods.unstake(stakerAccount.amount)
+        .accountsStrict(stakeAccounts())
+        .signers([initializer])
+        .rpc();
+
+      const lp = await getAccount(provider.connection, initializer_ata_lp_address);
+      assert.equal(stakerAccount.amount.toString(), lp.amount.toString(), "LP tokens should be returned");
+
+      // rewards accrued until the emission stopped are still owed
+      await harvest();
+      const reward_before = await getAccount(provider.connection, initializer_ata_reward);
+      await new Promise((resolve) => setTimeout(resolve, 1_000));
+      await harvest();
+      const reward_after = await getAccount(provider.connection, initializer_ata_reward);
+      assert.equal(reward_before.amount, reward_after.amount, "Nothing should be emitted once stopped");
+    });
+  });
+
+  describe("Close Pool", () => {
+    const fee_close = 100; // a separate fee tier of the X/Y pair
+
+    const [config_close] = getConfig(mint_x.publicKey, mint_y.publicKey, fee_close);
+    const [mint_lp_close] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("lp", "utf-8"), config_close.toBuffer()],
+      program.programId,
+    );
+    const [oracle_close] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle", "utf-8"), config_close.toBuffer()],
+      program.programId,
+    );
+    const [pair_close] = getPair(mint_x.publicKey, mint_y.publicKey, fee_close);
+    const [vault_close_x] = getAta(config_close, mint_x.publicKey);
+    const [vault_close_y] = getAta(config_close, mint_y.publicKey);
+    const [initializer_ata_lp_close] = getAta(initializer.publicKey, mint_lp_close);
+
+    const poolAccounts = () => ({
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      vaultX: vault_close_x,
+      vaultY: vault_close_y,
+      config: config_close,
+      oracle: oracle_close,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    // the page listing the pool, known once it is registered
+    let registry_page_close: PublicKey;
+
+    const closeAccounts = (closer: PublicKey) => ({
+      closer: closer,
+      initializer: initializer.publicKey,
+      treasury: treasury.publicKey,
+      mintX: mint_x.publicKey,
+      mintY: mint_y.publicKey,
+      mintLp: mint_lp_close,
+      config: config_close,
+      oracle: oracle_close,
+      pair: pair_close,
+      registryPage: registry_page_close,
+      vaultX: vault_close_x,
+      vaultY: vault_close_y,
+      treasuryX: getAta(treasury.publicKey, mint_x.publicKey)[0],
+      treasuryY: getAta(treasury.publicKey, mint_y.publicKey)[0],
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+    });
+
+    before("Setup a pool to close", async () => {
+      await program.methods.initialize(
+        fee_close,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        constantProduct,
+        new BN(0),
+        0,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: pair_close,
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        mintLp: mint_lp_close,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const pairAccount = await program.account.pair.fetch(pair_close);
+      [registry_page_close] = getRegistryPage(Math.floor(pairAccount.registryIndex.toNumber() / REGISTRY_PAGE_SIZE));
+
+      await program.methods.deposit(
+        new BN(1),
+        new BN(1_000 * 10 ** 6),
+        new BN(1_000 * 10 ** 6),
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: initializer_ata_lp_close,
+        nativeSol: null,
+        userX: initializer_ata_x.address,
+        userY: initializer_ata_y.address,
+        mintLp: mint_lp_close,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+    });
+
+    it("Fail close while LP tokens are outstanding", async () => {
+      try {
+        await program.methods.closePool()
+          .accountsStrict(closeAccounts(initializer.publicKey))
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to outstanding liquidity");
+      } catch (e) {
+        assert(e.error.errorCode.code === "LiquidityOutstanding", "Should fail with LiquidityOutstanding error");
+      }
+    });
+
+    it("Fail close from a non-authority", async () => {
+      try {
+        await program.methods.closePool()
+          .accountsStrict(closeAccounts(user1.publicKey))
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid authority");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidAuthority", "Should fail with InvalidAuthority error");
+      }
+    });
+
+    it("Close an empty pool and refund its rent", async () => {
+      const lp = await getAccount(provider.connection, initializer_ata_lp_close);
+
+      await program.methods.withdraw(
+        new BN(lp.amount.toString()),
+        new BN(0),
+        new BN(0),
+        expiration,
+      ).accountsStrict({
+        user: initializer.publicKey,
+        userLp: initializer_ata_lp_close,
+        nativeSol: null,
+        userX: initializer_ata_x.address,
+        userY: initializer_ata_y.address,
+        mintLp: mint_lp_close,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      const rent = (
+        await Promise.all([config_close, oracle_close, pair_close, vault_close_x, vault_close_y]
+          .map((account) => provider.connection.getBalance(account)))
+      ).reduce((sum, lamports) => sum + lamports, 0);
+      const balance_before = await provider.connection.getBalance(initializer.publicKey);
+      const locked_x = (await getAccount(provider.connection, vault_close_x)).amount;
+      const locked_y = (await getAccount(provider.connection, vault_close_y)).amount;
+
+      // without protocol fees to sweep, the treasury's token accounts aren't needed
+      const tx = await program.methods.closePool()
+        .accountsStrict({ ...closeAccounts(initializer.publicKey), treasuryX: null, treasuryY: null })
+        .signers([initializer])
+        .rpc({ commitment: "confirmed" });
+
+      for (const account of [config_close, oracle_close, pair_close, vault_close_x, vault_close_y]) {
+        assert.equal(await provider.connection.getAccountInfo(account), null, "Pool account should be closed");
+      }
+
+      // the refund outweighs the transaction fee
+      const balance_after = await provider.connection.getBalance(initializer.publicKey);
+      assert(balance_after - balance_before >= rent - 10_000, "Rent should be refunded to the initializer");
+
+      const registryPageAccount = await program.account.registryPage.fetch(registry_page_close);
+      assert(!registryPageAccount.pools.some((pool) => pool.equals(config_close)), "Pool should be removed from the registry");
+
+      // the reserves backing the locked minimum liquidity are burned, not swept to the treasury
+      const event = findEvent(await getEvents(tx), "PoolClosed");
+      assert.equal(event.amountX.toNumber(), 0);
+      assert.equal(event.amountY.toNumber(), 0);
+      assert.equal(event.burnedX.toString(), locked_x.toString());
+      assert.equal(event.burnedY.toString(), locked_y.toString());
+
+      // the LP mint can't be closed and stays behind without supply
+      const lp_mint = await getMint(provider.connection, mint_lp_close);
+      assert.equal(lp_mint.supply, BigInt(0));
+    });
+
+    it("Reopen the closed pool's pair and fee tier", async () => {
+      await program.methods.initialize(
+        fee_close,
+        initializer.publicKey,
+        protocol_fee,
+        treasury.publicKey,
+        constantProduct,
+        new BN(0),
+        0,
+      ).accountsStrict({
+        initializer: initializer.publicKey,
+        admin: provider.wallet.publicKey,
+        pair: pair_close,
+        registry: registry,
+        registryPage: await nextRegistryPage(),
+        mintLp: mint_lp_close,
+        ...poolAccounts(),
+      })
+        .signers([initializer])
+        .rpc();
+
+      // the new pool reuses the LP mint and is listed again
+      const configAccount = await program.account.config.fetch(config_close);
+      assert.equal(configAccount.feeTier, fee_close);
+      const pairAccount = await program.account.pair.fetch(pair_close);
+      assert.equal(pairAccount.config.toBase58(), config_close.toBase58());
+    });
+  });
+
+  describe("Admin", () => {
+    it("Fail lock when signer is not the authority", async () => {
+      try {
+        await program.methods.lock()
+          .accountsStrict({ authority: user1.publicKey, config })
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid authority");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidAuthority", "Should fail with InvalidAuthority error");
+      }
+    });
+
+    it("Lock blocks deposits until unlocked", async () => {
+      await program.methods.lock()
+        .accountsStrict({ authority: initializer.publicKey, config })
+        .signers([initializer])
+        .rpc();
+
+      assert.equal(PAUSE_ALL, (await program.account.config.fetch(config)).paused);
+
+      try {
+        await program.methods.deposit(
+          new BN(1_000 * 10 ** 6),
+          new BN(1_000_000 * 10 ** 6),
+          new BN(1_000_000 * 10 ** 6),
+          expiration,
         ).accountsStrict({
-          mintLp: mint_lp,
+          user: user1.publicKey,
           userLp: user1_ata_lp,
+          nativeSol: null,
+          userX: user1_ata_x.address,
+          userY: user1_ata_y.address,
+          mintX: mint_x.publicKey,
+          mintY: mint_y.publicKey,
+          mintLp: mint_lp,
+          vaultX: vault_x,
+          vaultY: vault_y,
+          config: config,
+          oracle: oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to locked pool");
+      } catch (e) {
+        assert(e.error.errorCode.code === "PoolLocked", "Should fail with PoolLocked error");
+      }
+
+      await program.methods.unlock()
+        .accountsStrict({ authority: initializer.publicKey, config })
+        .signers([initializer])
+        .rpc();
+
+      assert.equal(0, (await program.account.config.fetch(config)).paused);
+    });
+
+    it("Withdraw-only mode blocks swaps", async () => {
+      try {
+        await program.methods.setPaused(1 << 3)
+          .accountsStrict({ authority: initializer.publicKey, config })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to unknown pause flag");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidPauseFlags", "Should fail with InvalidPauseFlags error");
+      }
+
+      await program.methods.setPaused(PAUSE_SWAP | PAUSE_DEPOSIT)
+        .accountsStrict({ authority: initializer.publicKey, config })
+        .signers([initializer])
+        .rpc();
+
+      try {
+        await program.methods.swap(
+          true,
+          new BN(1_000 * 10 ** 6),
+          new BN(0),
+          expiration,
+        ).accountsStrict({
+          referrer: null,
+          referrerToken: null,
+          nativeSol: null,
           mintX: mint_x.publicKey,
           mintY: mint_y.publicKey,
           user: user1.publicKey,
@@ -638,18 +4214,181 @@ describe("AMM Instruction Tests", () => {
           vaultX: vault_x,
           vaultY: vault_y,
           config: config,
-          tokenProgram: TOKEN_PROGRAM_ID,
+          oracle: oracle,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
           .signers([user1])
           .rpc();
-        
-        assert.fail("Should have failed due to zero amount");
+
+        assert.fail("Should have failed due to paused swaps");
       } catch (e) {
-        assert(e.error.errorCode.code === "InvalidAmount", "Should fail with InvalidAmount error");
+        assert(e.error.errorCode.code === "PoolLocked", "Should fail with PoolLocked error");
+      }
+
+      await program.methods.withdraw(
+        new BN(1_000 * 10 ** 6),
+        new BN(0),
+        new BN(0),
+        expiration,
+      ).accountsStrict({
+        mintLp: mint_lp,
+        userLp: user1_ata_lp,
+        nativeSol: null,
+        mintX: mint_x.publicKey,
+        mintY: mint_y.publicKey,
+        user: user1.publicKey,
+        userX: user1_ata_x.address,
+        userY: user1_ata_y.address,
+        vaultX: vault_x,
+        vaultY: vault_y,
+        config: config,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([user1])
+        .rpc();
+
+      await program.methods.unlock()
+        .accountsStrict({ authority: initializer.publicKey, config })
+        .signers([initializer])
+        .rpc();
+    });
+
+    it("Update fee", async () => {
+      try {
+        await program.methods.updateFee(10_000, protocol_fee)
+          .accountsStrict({ authority: initializer.publicKey, config })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid fee");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidFee", "Should fail with InvalidFee error");
+      }
+
+      await program.methods.updateFee(30, 1_000)
+        .accountsStrict({ authority: initializer.publicKey, config })
+        .signers([initializer])
+        .rpc();
+
+      const configAccount = await program.account.config.fetch(config);
+      assert.equal(30, configAccount.fee);
+      assert.equal(1_000, configAccount.protocolFee);
+    });
+
+    it("Transfer authority in two steps", async () => {
+      await program.methods.proposeAuthority(user1.publicKey)
+        .accountsStrict({ authority: initializer.publicKey, config })
+        .signers([initializer])
+        .rpc();
+
+      try {
+        await program.methods.acceptAuthority()
+          .accountsStrict({ pendingAuthority: initializer.publicKey, config })
+          .signers([initializer])
+          .rpc();
+
+        assert.fail("Should have failed due to invalid pending authority");
+      } catch (e) {
+        assert(e.error.errorCode.code === "InvalidAuthority", "Should fail with InvalidAuthority error");
+      }
+
+      await program.methods.acceptAuthority()
+        .accountsStrict({ pendingAuthority: user1.publicKey, config })
+        .signers([user1])
+        .rpc();
+
+      const configAccount = await program.account.config.fetch(config);
+      assert.equal(user1.publicKey.toBase58(), configAccount.authority.toBase58());
+      assert.equal(null, configAccount.pendingAuthority);
+    });
+
+    it("Renounce authority", async () => {
+      await program.methods.renounceAuthority()
+        .accountsStrict({ authority: user1.publicKey, config })
+        .signers([user1])
+        .rpc();
+
+      assert.equal(null, (await program.account.config.fetch(config)).authority);
+
+      try {
+        await program.methods.lock()
+          .accountsStrict({ authority: user1.publicKey, config })
+          .signers([user1])
+          .rpc();
+
+        assert.fail("Should have failed due to missing authority");
+      } catch (e) {
+        assert(e.error.errorCode.code === "NoAuthoritySet", "Should fail with NoAuthoritySet error");
       }
     });
+
+    it("Collect protocol fees without an authority", async () => {
+      await program.methods.swap(
+        true,
+        new BN(1_000 * 10 ** 6),
+        new BN(1),
+        expiration,
+      ).accountsStrict({
+        referrer: null,
+        referrerToken: null,
+        nativeSol: null,
+        mintX: mint_x.publicKey,
+        mintY: mint_y.publicKey,
+        user: user1.publicKey,
+        userX: user1_ata_x.address,
+        userY: user1_ata_y.address,
+        vaultX: vault_x,
+        vaultY: vault_y,
+        config: config,
+        oracle: oracle,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+        .signers([user1])
+        .rpc();
+
+      const [treasury_x] = getAta(treasury.publicKey, mint_x.publicKey);
+      const [treasury_y] = getAta(treasury.publicKey, mint_y.publicKey);
+      const configBefore = await program.account.config.fetch(config);
+      const treasury_x_before = await getAccount(provider.connection, treasury_x);
+      assert(configBefore.protocolFeesX.toNumber() > 0, "Protocol fees should accrue in X");
+
+      await program.methods.collectProtocolFees()
+        .accountsStrict({
+          collector: user1.publicKey,
+          treasury: treasury.publicKey,
+          mintX: mint_x.publicKey,
+          mintY: mint_y.publicKey,
+          config: config,
+          vaultX: vault_x,
+          vaultY: vault_y,
+          treasuryX: treasury_x,
+          treasuryY: treasury_y,
+          tokenProgramX: TOKEN_PROGRAM_ID,
+          tokenProgramY: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_PROGRAM_ID,
+        })
+        .signers([user1])
+        .rpc();
+
+      const treasury_x_after = await getAccount(provider.connection, treasury_x);
+      assert.equal(0, (await program.account.config.fetch(config)).protocolFeesX.toNumber());
+      assert.equal(
+        Number(treasury_x_after.amount - treasury_x_before.amount),
+        configBefore.protocolFeesX.toNumber()
+      );
+    });
   });
 
 });